@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+use psx::gpu::VideoMode;
+use psx::timer::Timer1;
+use psx::{dprintln, Framebuffer};
+
+// Ticks per quarter note at 120 BPM
+const TICK_RATE: u32 = 240;
+const TICKS_PER_BEAT: u32 = TICK_RATE / 2;
+
+static mut TICKS: u32 = 0;
+
+fn tick() {
+    // SAFETY: This only runs in the interrupt handler and main only reads it
+    unsafe {
+        TICKS += 1;
+    }
+}
+
+#[no_mangle]
+fn main() {
+    let mut fb = Framebuffer::new((0, 0), (0, 240), (320, 240), VideoMode::NTSC, None).unwrap();
+    let font = fb.load_default_font();
+    let mut txt = font.new_text_box((0, 8), (320, 240));
+    let mut timer = Timer1::new();
+    timer.start(TICK_RATE, tick).unwrap();
+    loop {
+        let ticks = unsafe { core::ptr::read_volatile(core::ptr::addr_of!(TICKS)) };
+        txt.reset();
+        dprintln!(txt, "Ticks: {}", ticks);
+        dprintln!(txt, "Beat: {}", ticks / TICKS_PER_BEAT);
+        fb.draw_sync();
+        fb.wait_vblank();
+        fb.swap();
+    }
+}
@@ -0,0 +1,513 @@
+//! ISO9660 filesystem reader for Sony PlayStation 1 discs
+//!
+//! [`Filesystem::mount`] reads the primary volume descriptor, the path table
+//! and every directory through a [`SectorRead`] and caches the directory tree
+//! in RAM, so looking up files doesn't access the disc. [`SectorRead`] is
+//! implemented for ISO images in memory, so the parser also runs on the host.
+//! The `psx` crate implements it for the CD-ROM drive and re-exports this
+//! crate as `psx::cdrom::iso9660`.
+#![no_std]
+#![deny(missing_docs)]
+
+use core::marker::PhantomData;
+
+/// The size of a logical sector in bytes.
+pub const SECTOR_SIZE: usize = 2048;
+/// The size of a logical sector in 32-bit words.
+pub const SECTOR_WORDS: usize = SECTOR_SIZE / 4;
+/// The maximum length of a file or directory name without its version.
+pub const MAX_NAME_LEN: usize = 31;
+
+/// The logical block address of the first volume descriptor.
+const FIRST_DESCRIPTOR: u32 = 16;
+/// The maximum number of volume descriptors searched for the primary one.
+const MAX_DESCRIPTORS: u32 = 16;
+const PRIMARY_DESCRIPTOR: u8 = 1;
+const TERMINATOR: u8 = 0xFF;
+const STANDARD_ID: &[u8; 5] = b"CD001";
+
+// Primary volume descriptor field offsets
+const VOLUME_ID: usize = 40;
+const VOLUME_ID_LEN: usize = 32;
+const BLOCK_SIZE: usize = 128;
+const PATH_TABLE_SIZE: usize = 132;
+const PATH_TABLE_LBA: usize = 140;
+
+// Directory record field offsets
+const EXTENT: usize = 2;
+const DATA_LEN: usize = 10;
+const FLAGS: usize = 25;
+const NAME_LEN: usize = 32;
+const NAME: usize = 33;
+const DIRECTORY_FLAG: u8 = 1 << 1;
+
+/// An ISO9660-specific error, where `E` is the [`SectorRead`]'s error.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error<E> {
+    /// Reading a sector failed.
+    Read(E),
+    /// The primary volume descriptor is missing or invalid.
+    InvalidVolume,
+    /// A path table or directory record is invalid.
+    InvalidRecord,
+    /// The filesystem has more entries than the cache can hold.
+    TooManyEntries,
+    /// No file or directory matches the path.
+    NotFound,
+    /// A path component other than the last one is a file.
+    NotADirectory,
+}
+
+impl<E> From<E> for Error<E> {
+    fn from(err: E) -> Self {
+        Error::Read(err)
+    }
+}
+
+/// A source of 2048-byte logical sectors.
+pub trait SectorRead {
+    /// The error returned when a sector can't be read.
+    type Error;
+
+    /// Reads the sector at a logical block address into `buf`.
+    fn read_sector(&mut self, lba: u32, buf: &mut [u32; SECTOR_WORDS]) -> Result<(), Self::Error>;
+}
+
+/// The error returned when reading past the end of an ISO image in memory.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct EndOfImage;
+
+/// Reads sectors from an ISO image with 2048-byte sectors.
+impl SectorRead for &[u8] {
+    type Error = EndOfImage;
+
+    fn read_sector(&mut self, lba: u32, buf: &mut [u32; SECTOR_WORDS]) -> Result<(), EndOfImage> {
+        let start = lba as usize * SECTOR_SIZE;
+        let sector = self.get(start..start + SECTOR_SIZE).ok_or(EndOfImage)?;
+        for (word, bytes) in buf.iter_mut().zip(sector.chunks(4)) {
+            *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        Ok(())
+    }
+}
+
+/// Reads bytes sequentially from consecutive sectors.
+struct Cursor<'r, R: SectorRead> {
+    reader: &'r mut R,
+    buf: [u32; SECTOR_WORDS],
+    lba: u32,
+    offset: usize,
+}
+
+impl<'r, R: SectorRead> Cursor<'r, R> {
+    fn new<E: From<R::Error>>(reader: &'r mut R, lba: u32) -> Result<Self, Error<E>> {
+        let mut cursor = Cursor {
+            reader,
+            buf: [0; SECTOR_WORDS],
+            lba,
+            offset: 0,
+        };
+        cursor.load()?;
+        Ok(cursor)
+    }
+
+    fn load<E: From<R::Error>>(&mut self) -> Result<(), Error<E>> {
+        self.offset = 0;
+        self.reader
+            .read_sector(self.lba, &mut self.buf)
+            .map_err(|err| Error::Read(err.into()))
+    }
+
+    /// Moves to the start of the next sector.
+    fn next_sector<E: From<R::Error>>(&mut self) -> Result<(), Error<E>> {
+        self.lba += 1;
+        self.load()
+    }
+
+    /// Gets the byte at the cursor without advancing it.
+    fn peek(&self) -> u8 {
+        (self.buf[self.offset / 4] >> (8 * (self.offset % 4))) as u8
+    }
+
+    fn read<E: From<R::Error>>(&mut self, dst: &mut [u8]) -> Result<(), Error<E>> {
+        for byte in dst {
+            if self.offset == SECTOR_SIZE {
+                self.next_sector()?;
+            }
+            *byte = self.peek();
+            self.offset += 1;
+        }
+        Ok(())
+    }
+
+    /// Gets the number of bytes read since the start of the first sector.
+    fn position(&self, start_lba: u32) -> usize {
+        (self.lba - start_lba) as usize * SECTOR_SIZE + self.offset
+    }
+}
+
+fn u16_le(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn u32_le(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+/// Removes the `;1` version and the trailing `.` of names without an
+/// extension.
+fn strip_version(name: &[u8]) -> &[u8] {
+    let name = match name.iter().position(|&b| b == b';') {
+        Some(idx) => &name[..idx],
+        None => name,
+    };
+    name.strip_suffix(b".").unwrap_or(name)
+}
+
+/// A cached file or directory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Entry {
+    name: [u8; MAX_NAME_LEN],
+    name_len: u8,
+    parent: u16,
+    dir: bool,
+    lba: u32,
+    size: u32,
+}
+
+impl Entry {
+    const EMPTY: Entry = Entry {
+        name: [0; MAX_NAME_LEN],
+        name_len: 0,
+        parent: 0,
+        dir: false,
+        lba: 0,
+        size: 0,
+    };
+
+    fn new(name: &[u8], parent: usize, dir: bool, lba: u32, size: u32) -> Option<Self> {
+        let name = strip_version(name);
+        if name.len() > MAX_NAME_LEN {
+            return None
+        }
+        let mut entry = Entry {
+            name_len: name.len() as u8,
+            parent: parent as u16,
+            dir,
+            lba,
+            size,
+            ..Entry::EMPTY
+        };
+        entry.name[..name.len()].copy_from_slice(name);
+        Some(entry)
+    }
+
+    /// Gets the name without its version. The root directory's name is
+    /// empty.
+    pub fn name(&self) -> &str {
+        core::str::from_utf8(&self.name[..self.name_len as usize]).unwrap_or("")
+    }
+
+    /// Checks if this is a directory.
+    pub fn is_dir(&self) -> bool {
+        self.dir
+    }
+
+    /// Gets the logical block address of the first sector.
+    pub fn lba(&self) -> u32 {
+        self.lba
+    }
+
+    /// Gets the size in bytes.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Gets the number of sectors used.
+    pub fn sectors(&self) -> u32 {
+        self.size.div_ceil(SECTOR_SIZE as u32)
+    }
+
+    fn matches(&self, name: &[u8]) -> bool {
+        self.name[..self.name_len as usize].eq_ignore_ascii_case(strip_version(name))
+    }
+}
+
+/// The directory tree of an ISO9660 volume with up to `N` files and
+/// directories.
+///
+/// `E` is the error for failed reads, which may be converted from the
+/// [`SectorRead`]'s error.
+#[derive(Debug)]
+pub struct Filesystem<const N: usize = 128, E = EndOfImage> {
+    volume_id: [u8; VOLUME_ID_LEN],
+    entries: [Entry; N],
+    len: usize,
+    _error: PhantomData<E>,
+}
+
+impl<const N: usize, E> Filesystem<N, E> {
+    /// Reads and caches the directory tree.
+    pub fn mount<R: SectorRead>(reader: &mut R) -> Result<Self, Error<E>>
+    where E: From<R::Error> {
+        let mut fs = Filesystem {
+            volume_id: [b' '; VOLUME_ID_LEN],
+            entries: [Entry::EMPTY; N],
+            len: 0,
+            _error: PhantomData,
+        };
+        let (path_table_lba, path_table_size) = fs.read_volume_descriptor(reader)?;
+        fs.read_path_table(reader, path_table_lba, path_table_size)?;
+        let dirs = fs.len;
+        for dir in 0..dirs {
+            fs.read_directory(reader, dir)?;
+        }
+        Ok(fs)
+    }
+
+    fn push(&mut self, entry: Option<Entry>) -> Result<(), Error<E>> {
+        let entry = entry.ok_or(Error::InvalidRecord)?;
+        let slot = self
+            .entries
+            .get_mut(self.len)
+            .ok_or(Error::TooManyEntries)?;
+        *slot = entry;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Finds the primary volume descriptor and gets the path table's
+    /// location and size.
+    fn read_volume_descriptor<R: SectorRead>(
+        &mut self, reader: &mut R,
+    ) -> Result<(u32, u32), Error<E>>
+    where E: From<R::Error> {
+        let mut header = [0; 256];
+        for lba in FIRST_DESCRIPTOR..FIRST_DESCRIPTOR + MAX_DESCRIPTORS {
+            Cursor::new(reader, lba)?.read(&mut header)?;
+            if &header[1..6] != STANDARD_ID || header[0] == TERMINATOR {
+                break
+            }
+            if header[0] != PRIMARY_DESCRIPTOR {
+                continue
+            }
+            if u16_le(&header, BLOCK_SIZE) as usize != SECTOR_SIZE {
+                return Err(Error::InvalidVolume)
+            }
+            self.volume_id
+                .copy_from_slice(&header[VOLUME_ID..VOLUME_ID + VOLUME_ID_LEN]);
+            return Ok((
+                u32_le(&header, PATH_TABLE_LBA),
+                u32_le(&header, PATH_TABLE_SIZE),
+            ))
+        }
+        Err(Error::InvalidVolume)
+    }
+
+    /// Adds every directory from the little-endian path table.
+    ///
+    /// The path table lists parents before their children, so each
+    /// directory's index in the cache is its path table index.
+    fn read_path_table<R: SectorRead>(
+        &mut self, reader: &mut R, lba: u32, size: u32,
+    ) -> Result<(), Error<E>>
+    where E: From<R::Error> {
+        let mut cursor = Cursor::new(reader, lba)?;
+        let mut name = [0; 256];
+        while cursor.position(lba) < size as usize {
+            let mut header = [0; 8];
+            cursor.read(&mut header)?;
+            let name_len = header[0] as usize;
+            let padded_len = name_len + name_len % 2;
+            cursor.read(&mut name[..padded_len])?;
+            let extent = u32_le(&header, 2);
+            let parent = u16_le(&header, 6) as usize;
+            if name_len == 0 || parent == 0 || parent > self.len.max(1) {
+                return Err(Error::InvalidRecord)
+            }
+            let entry = if self.len == 0 {
+                // The root directory is its own parent
+                Entry::new(&[], 0, true, extent, 0)
+            } else {
+                Entry::new(&name[..name_len], parent - 1, true, extent, 0)
+            };
+            self.push(entry)?;
+        }
+        if self.len == 0 {
+            return Err(Error::InvalidRecord)
+        }
+        Ok(())
+    }
+
+    /// Adds the files in a directory and sets the directory's size.
+    fn read_directory<R: SectorRead>(
+        &mut self, reader: &mut R, dir: usize,
+    ) -> Result<(), Error<E>>
+    where E: From<R::Error> {
+        let lba = self.entries[dir].lba;
+        let mut cursor = Cursor::new(reader, lba)?;
+        let mut record = [0; 256];
+        // The first record is the directory itself
+        let mut size = SECTOR_SIZE;
+        let mut first = true;
+        loop {
+            if cursor.offset == SECTOR_SIZE || cursor.peek() == 0 {
+                // Records don't cross sectors so the rest is padding
+                if cursor.position(lba).next_multiple_of(SECTOR_SIZE) >= size {
+                    break
+                }
+                cursor.next_sector()?;
+                continue
+            }
+            let record_len = cursor.peek() as usize;
+            if record_len <= NAME || cursor.offset + record_len > SECTOR_SIZE {
+                return Err(Error::InvalidRecord)
+            }
+            cursor.read(&mut record[..record_len])?;
+            let name_len = record[NAME_LEN] as usize;
+            if NAME + name_len > record_len {
+                return Err(Error::InvalidRecord)
+            }
+            let name = &record[NAME..NAME + name_len];
+            let extent = u32_le(&record, EXTENT);
+            let data_len = u32_le(&record, DATA_LEN);
+            if first {
+                size = data_len as usize;
+                self.entries[dir].size = data_len;
+                first = false;
+                continue
+            }
+            // Skip the parent directory and subdirectories, which were added
+            // from the path table
+            let is_dir = record[FLAGS] & DIRECTORY_FLAG != 0;
+            if name_len == 1 && name[0] <= 1 {
+                continue
+            }
+            if is_dir {
+                if let Some(subdir) = self.entries[..self.len]
+                    .iter_mut()
+                    .find(|e| e.dir && e.lba == extent)
+                {
+                    subdir.size = data_len;
+                }
+                continue
+            }
+            self.push(Entry::new(name, dir, false, extent, data_len))?;
+        }
+        Ok(())
+    }
+
+    /// Gets the volume identifier without its padding.
+    pub fn volume_id(&self) -> &str {
+        let len = self
+            .volume_id
+            .iter()
+            .rposition(|&b| b != b' ')
+            .map_or(0, |idx| idx + 1);
+        core::str::from_utf8(&self.volume_id[..len]).unwrap_or("")
+    }
+
+    /// Gets the root directory.
+    pub fn root(&self) -> &Entry {
+        &self.entries[0]
+    }
+
+    /// Gets all cached files and directories.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries[..self.len]
+    }
+
+    /// Finds a file or directory by its path.
+    ///
+    /// Components may be separated by `/` or `\` and are compared ignoring
+    /// case and versions, so `\DATA\LEVEL1.BIN;1`, `cdrom:\DATA\LEVEL1.BIN`
+    /// and `data/level1.bin` are equivalent.
+    pub fn find(&self, path: &str) -> Result<&Entry, Error<E>> {
+        self.find_index(path).map(|idx| &self.entries[idx])
+    }
+
+    /// Gets the files and subdirectories in a directory.
+    pub fn read_dir(&self, path: &str) -> Result<impl Iterator<Item = &Entry>, Error<E>> {
+        let dir = self.find_index(path)?;
+        if !self.entries[dir].dir {
+            return Err(Error::NotADirectory)
+        }
+        Ok(self.entries[1..self.len]
+            .iter()
+            .filter(move |entry| entry.parent as usize == dir))
+    }
+
+    fn find_index(&self, path: &str) -> Result<usize, Error<E>> {
+        let path = path.strip_prefix("cdrom:").unwrap_or(path);
+        let mut current = 0;
+        for component in path.split(['/', '\\']).filter(|c| !c.is_empty()) {
+            if !self.entries[current].dir {
+                return Err(Error::NotADirectory)
+            }
+            current = (1..self.len)
+                .find(|&idx| {
+                    let entry = &self.entries[idx];
+                    entry.parent as usize == current && entry.matches(component.as_bytes())
+                })
+                .ok_or(Error::NotFound)?;
+        }
+        Ok(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EndOfImage, Error, Filesystem};
+
+    const IMAGE: &[u8] = include_bytes!("../test_files/test.iso");
+
+    #[test]
+    fn mount() {
+        let fs = Filesystem::<16>::mount(&mut { IMAGE }).unwrap();
+        assert!(fs.entries().len() == 7);
+        assert!(fs.volume_id() == "TEST");
+        assert!(fs.root().is_dir());
+        assert!(fs.root().lba() == 20);
+        let cnf = fs.find("SYSTEM.CNF;1").unwrap();
+        assert!(cnf.lba() == 23);
+        assert!(cnf.size() == 30);
+        let level = fs.find("cdrom:\\DATA\\LEVEL1.BIN;1").unwrap();
+        assert!(level.lba() == 24);
+        assert!(level.size() == 3000);
+        assert!(level.sectors() == 2);
+        assert!(fs.find("/data/levels/map.dat").unwrap().lba() == 26);
+        assert!(fs.find("README").unwrap().name() == "README");
+        assert!(fs.find("DATA/LEVELS").unwrap().is_dir());
+    }
+
+    #[test]
+    fn lookup_errors() {
+        let fs = Filesystem::<16>::mount(&mut { IMAGE }).unwrap();
+        assert!(fs.find("MISSING.BIN") == Err(Error::NotFound));
+        assert!(fs.find("SYSTEM.CNF/FOO") == Err(Error::NotADirectory));
+        assert!(fs.read_dir("SYSTEM.CNF").err() == Some(Error::NotADirectory));
+        assert!(Filesystem::<4>::mount(&mut { IMAGE }).err() == Some(Error::TooManyEntries));
+        assert!(
+            Filesystem::<16>::mount(&mut &IMAGE[..0x8000]).err() == Some(Error::Read(EndOfImage))
+        );
+    }
+
+    #[test]
+    fn directories() {
+        let fs = Filesystem::<16>::mount(&mut { IMAGE }).unwrap();
+        let mut names = fs.read_dir("/").unwrap().map(|entry| entry.name());
+        assert!(names.next() == Some("DATA"));
+        assert!(names.next() == Some("README"));
+        assert!(names.next() == Some("SYSTEM.CNF"));
+        assert!(names.next().is_none());
+        let mut names = fs.read_dir("DATA").unwrap().map(|entry| entry.name());
+        assert!(names.next() == Some("LEVELS"));
+        assert!(names.next() == Some("LEVEL1.BIN"));
+        assert!(names.next().is_none());
+    }
+}
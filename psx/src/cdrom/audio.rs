@@ -0,0 +1,369 @@
+//! CD audio playback
+//!
+//! These functions play Red Book audio tracks through a [`CdRom`]. CD audio
+//! is mixed into the SPU's output, so it's only audible after calling
+//! [`set_spu_volume`] and [`set_volume`].
+use crate::cdrom::{from_bcd, send, to_bcd, with_state, CdRom, DriveStatus, Error, Msf, Phase,
+                   Response, Speed, State};
+use crate::hw::cdrom::{Bank, Bank2, Bank3, Command, Status};
+use crate::hw::spu::{CDVolumeLeft, CDVolumeRight, Control, Volume as _};
+use crate::hw::Register;
+
+/// The maximum number of tracks on a disc.
+pub const MAX_TRACKS: usize = 99;
+
+/// The bit set in the second byte of a report with a relative position.
+const RELATIVE: u8 = 0x80;
+
+/// A position report sent while playing with [`set_report`] enabled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Report {
+    /// The track being played.
+    pub track: u8,
+    /// The index within the track.
+    pub index: u8,
+    /// The position on the disc or within the track if `relative` is set.
+    pub position: Msf,
+    /// Whether the position is relative to the start of the track.
+    pub relative: bool,
+    /// The peak level of the audio since the last report.
+    pub peak: u16,
+}
+
+/// The drive head's position returned by [`position`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Position {
+    /// The track being played.
+    pub track: u8,
+    /// The index within the track.
+    pub index: u8,
+    /// The position within the track.
+    pub relative: Msf,
+    /// The position on the disc.
+    pub absolute: Msf,
+}
+
+/// The disc's table of contents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Toc {
+    first: u8,
+    last: u8,
+    starts: [Msf; MAX_TRACKS + 1],
+}
+
+impl Toc {
+    /// Gets the first track number.
+    pub fn first_track(&self) -> u8 {
+        self.first
+    }
+
+    /// Gets the last track number.
+    pub fn last_track(&self) -> u8 {
+        self.last
+    }
+
+    /// Gets the end of the last track.
+    pub fn disc_end(&self) -> Msf {
+        self.starts[0]
+    }
+
+    /// Gets the start and end of a track.
+    pub fn track(&self, track: u8) -> Option<(Msf, Msf)> {
+        if track < self.first || track > self.last {
+            return None
+        }
+        let end = if track == self.last {
+            self.disc_end()
+        } else {
+            self.starts[track as usize + 1]
+        };
+        Some((self.starts[track as usize], end))
+    }
+}
+
+/// Routes the CD audio outputs into the SPU's CD audio inputs.
+///
+/// `0x80` is normal volume and `0xFF` is about double.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Volume {
+    /// The volume of the left output into the left input.
+    pub left_to_left: u8,
+    /// The volume of the left output into the right input.
+    pub left_to_right: u8,
+    /// The volume of the right output into the left input.
+    pub right_to_left: u8,
+    /// The volume of the right output into the right input.
+    pub right_to_right: u8,
+}
+
+impl Volume {
+    /// Normal volume stereo output.
+    pub const STEREO: Volume = Volume {
+        left_to_left: 0x80,
+        left_to_right: 0,
+        right_to_left: 0,
+        right_to_right: 0x80,
+    };
+    /// Both outputs mixed into both inputs at normal volume.
+    pub const MONO: Volume = Volume {
+        left_to_left: 0x40,
+        left_to_right: 0x40,
+        right_to_left: 0x40,
+        right_to_right: 0x40,
+    };
+    /// No output.
+    pub const MUTED: Volume = Volume {
+        left_to_left: 0,
+        left_to_right: 0,
+        right_to_left: 0,
+        right_to_right: 0,
+    };
+}
+
+/// Parses an INT1 position report.
+fn parse_report(data: &[u8]) -> Option<Report> {
+    if data.len() < 8 {
+        return None
+    }
+    Some(Report {
+        track: from_bcd(data[1]),
+        index: from_bcd(data[2]),
+        position: Msf::from_bcd([data[3], data[4] & !RELATIVE, data[5]]),
+        relative: data[4] & RELATIVE != 0,
+        peak: u16::from_le_bytes([data[6], data[7]]),
+    })
+}
+
+/// Records a position report and pauses at the end of a range being played.
+pub(super) fn on_report(state: &mut State, res: &Response) {
+    let Some(report) = parse_report(res.data()) else {
+        return
+    };
+    state.report = Some(report);
+    let Some(end) = state.play_end else { return };
+    if report.relative || report.position < end {
+        return
+    }
+    // Try again on the next report if a command is being sent
+    if state.phase == Phase::Idle && !Status::new().busy() {
+        send(state, Command::Pause, &[], true);
+        state.play_end = None;
+        state.play_ended = true;
+    }
+}
+
+/// Checks the track numbers from `GetTN`, clamping the last one to
+/// [`MAX_TRACKS`].
+fn track_range(first: u8, last: u8) -> Option<(u8, u8)> {
+    let last = last.min(MAX_TRACKS as u8);
+    if first == 0 || first > last {
+        return None
+    }
+    Some((first, last))
+}
+
+/// Gets the first and last track numbers.
+///
+/// Malformed track numbers are clamped to [`MAX_TRACKS`] or rejected.
+pub fn tracks(cdrom: &mut CdRom) -> Result<(u8, u8), Error> {
+    let res = cdrom.command(Command::GetTN, &[])?;
+    let data = res.data();
+    if data.len() < 3 {
+        return Err(Error::Command(res.status(), 0))
+    }
+    track_range(from_bcd(data[1]), from_bcd(data[2])).ok_or(Error::Command(res.status(), 0))
+}
+
+/// Gets the start of a track. Track 0 gets the end of the disc.
+pub fn track_start(cdrom: &mut CdRom, track: u8) -> Result<Msf, Error> {
+    let res = cdrom.command(Command::GetTD, &[to_bcd(track)])?;
+    let data = res.data();
+    if data.len() < 3 {
+        return Err(Error::Command(res.status(), 0))
+    }
+    Ok(Msf::from_bcd([data[1], data[2], 0]))
+}
+
+/// Reads the table of contents with `GetTN` and `GetTD`.
+pub fn read_toc(cdrom: &mut CdRom) -> Result<Toc, Error> {
+    let (first, last) = tracks(cdrom)?;
+    let mut toc = Toc {
+        first,
+        last,
+        starts: [Msf::default(); MAX_TRACKS + 1],
+    };
+    toc.starts[0] = track_start(cdrom, 0)?;
+    for track in first..=last {
+        toc.starts[track as usize] = track_start(cdrom, track)?;
+    }
+    Ok(toc)
+}
+
+/// Switches to single speed, which CD audio plays at, and resets the
+/// playback state.
+fn prepare(cdrom: &mut CdRom) -> Result<(), Error> {
+    let mut mode = cdrom.mode();
+    mode.set_speed(Speed::Single);
+    if mode != cdrom.mode() {
+        cdrom.set_mode(mode)?;
+    }
+    with_state(|state| {
+        state.report = None;
+        state.play_end = None;
+        state.play_ended = false;
+    });
+    Ok(())
+}
+
+/// Plays a track and continues with the following ones unless
+/// [`set_auto_pause`] is enabled.
+///
+/// This switches the drive to single speed.
+pub fn play_track(cdrom: &mut CdRom, track: u8) -> Result<(), Error> {
+    prepare(cdrom)?;
+    cdrom.command(Command::Play, &[to_bcd(track)]).map(|_| ())
+}
+
+/// Plays from `start` until `end`.
+///
+/// This switches the drive to single speed and enables [`set_report`] to
+/// pause when a report reaches `end`, so playback may overshoot `end` by a
+/// few sectors.
+pub fn play_range(cdrom: &mut CdRom, start: Msf, end: Msf) -> Result<(), Error> {
+    prepare(cdrom)?;
+    set_report(cdrom, true)?;
+    cdrom.set_loc(start)?;
+    cdrom.command(Command::Play, &[])?;
+    with_state(|state| state.play_end = Some(end));
+    Ok(())
+}
+
+/// Pauses playback.
+pub fn pause(cdrom: &mut CdRom) -> Result<(), Error> {
+    with_state(|state| state.play_end = None);
+    cdrom.pause()
+}
+
+/// Resumes playback from the current position.
+pub fn resume(cdrom: &mut CdRom) -> Result<(), Error> {
+    with_state(|state| state.play_ended = false);
+    cdrom.command(Command::Play, &[]).map(|_| ())
+}
+
+/// Checks if playback stopped at the end of a track with
+/// [`set_auto_pause`], the end of the disc or the end of a range.
+pub fn ended() -> bool {
+    with_state(|state| state.play_ended)
+}
+
+/// Pauses playback at the end of each track.
+pub fn set_auto_pause(cdrom: &mut CdRom, auto_pause: bool) -> Result<(), Error> {
+    let mut mode = cdrom.mode();
+    mode.set_auto_pause(auto_pause);
+    cdrom.set_mode(mode)
+}
+
+/// Sends position reports while playing, which are available through
+/// [`last_report`].
+pub fn set_report(cdrom: &mut CdRom, report: bool) -> Result<(), Error> {
+    let mut mode = cdrom.mode();
+    mode.set_report(report);
+    cdrom.set_mode(mode)
+}
+
+/// Gets the latest position report.
+pub fn last_report() -> Option<Report> {
+    with_state(|state| state.report)
+}
+
+/// Gets the drive head's position with `GetlocP`.
+pub fn position(cdrom: &mut CdRom) -> Result<Position, Error> {
+    let res = cdrom.command(Command::GetlocP, &[])?;
+    // This response doesn't start with the drive status
+    let data = res.data();
+    if data.len() < 8 {
+        return Err(Error::Command(DriveStatus(0), 0))
+    }
+    Ok(Position {
+        track: from_bcd(data[0]),
+        index: from_bcd(data[1]),
+        relative: Msf::from_bcd([data[2], data[3], data[4]]),
+        absolute: Msf::from_bcd([data[5], data[6], data[7]]),
+    })
+}
+
+/// Mutes CD audio and XA-ADPCM output with the `Mute` command.
+pub fn mute(cdrom: &mut CdRom) -> Result<(), Error> {
+    cdrom.command(Command::Mute, &[]).map(|_| ())
+}
+
+/// Unmutes CD audio and XA-ADPCM output with the `Demute` command.
+pub fn demute(cdrom: &mut CdRom) -> Result<(), Error> {
+    cdrom.command(Command::Demute, &[]).map(|_| ())
+}
+
+/// Sets the volume of the CD audio outputs into the SPU's inputs.
+pub fn set_volume(_cdrom: &mut CdRom, volume: Volume) {
+    with_state(|state| {
+        Bank2::select(&mut state.selector)
+            .set_left_to_left(volume.left_to_left)
+            .set_left_to_right(volume.left_to_right);
+        Bank3::select(&mut state.selector)
+            .set_right_to_right(volume.right_to_right)
+            .set_right_to_left(volume.right_to_left)
+            .apply_volume(false);
+    })
+}
+
+/// Enables the SPU's CD audio input and sets its volume in the SPU mixer.
+pub fn set_spu_volume(left: i16, right: i16) {
+    CDVolumeLeft::skip_load().set_fixed(left).store();
+    CDVolumeRight::skip_load().set_fixed(right).store();
+    Control::new().enable_cd_audio(true).store();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_report, track_range, Msf, Toc, MAX_TRACKS};
+
+    #[test_case]
+    fn reports() {
+        let report = parse_report(&[0x80, 0x02, 0x01, 0x03, 0x25, 0x70, 0x34, 0x12]).unwrap();
+        assert!(report.track == 2);
+        assert!(report.index == 1);
+        assert!(report.position == Msf::new(3, 25, 70));
+        assert!(!report.relative);
+        assert!(report.peak == 0x1234);
+        let report = parse_report(&[0x80, 0x02, 0x01, 0x00, 0x85, 0x10, 0, 0]).unwrap();
+        assert!(report.relative);
+        assert!(report.position == Msf::new(0, 5, 10));
+        assert!(parse_report(&[0x80, 0x02]).is_none());
+    }
+
+    #[test_case]
+    fn toc() {
+        let mut toc = Toc {
+            first: 1,
+            last: 3,
+            starts: [Msf::default(); MAX_TRACKS + 1],
+        };
+        toc.starts[0] = Msf::new(40, 0, 0);
+        toc.starts[1] = Msf::new(0, 2, 0);
+        toc.starts[2] = Msf::new(10, 0, 0);
+        toc.starts[3] = Msf::new(25, 30, 0);
+        assert!(toc.track(0).is_none());
+        assert!(toc.track(1) == Some((Msf::new(0, 2, 0), Msf::new(10, 0, 0))));
+        assert!(toc.track(3) == Some((Msf::new(25, 30, 0), Msf::new(40, 0, 0))));
+        assert!(toc.track(4).is_none());
+    }
+
+    #[test_case]
+    fn track_ranges() {
+        assert!(track_range(1, 12) == Some((1, 12)));
+        // 0xA0 decodes to 100
+        assert!(track_range(1, 100) == Some((1, 99)));
+        assert!(track_range(5, 4).is_none());
+        assert!(track_range(0, 4).is_none());
+        assert!(track_range(100, 100).is_none());
+    }
+}
@@ -0,0 +1,260 @@
+//! Disc changes
+//!
+//! The drive sets the shell open bit in its status when the lid is opened
+//! and keeps it set until the status is read with the lid closed, so polling
+//! [`status`] catches every disc change. The error the drive sends when the
+//! lid is opened is also recorded by the interrupt handler and reported by
+//! [`lid_opened`] without sending a command.
+//!
+//! [`DiscRequest`] walks the player through swapping discs in multi-disc
+//! games.
+use crate::cdrom::iso9660::{self, Filesystem};
+use crate::cdrom::{with_state, CdRom, DriveStatus, Error};
+use crate::hw::cdrom::Command;
+use core::mem;
+
+// GetID flags
+const UNLICENSED: u8 = 1 << 7;
+const MISSING: u8 = 1 << 6;
+const AUDIO: u8 = 1 << 4;
+
+/// The region a disc is licensed for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Region {
+    /// `SCEI`
+    Japan,
+    /// `SCEA`
+    America,
+    /// `SCEE`
+    Europe,
+}
+
+/// The disc identification returned by [`get_id`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiscId {
+    /// Whether the disc passed the license check.
+    pub licensed: bool,
+    /// Whether the disc is an audio CD.
+    pub audio: bool,
+    /// The disc type from the TOC, which is `0x20` for data discs.
+    pub disc_type: u8,
+    /// The license string, like `SCEA`, or zeros for unlicensed discs.
+    pub license: [u8; 4],
+}
+
+impl DiscId {
+    /// Parses the response to `GetID`.
+    fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < 8 {
+            return None
+        }
+        let flags = data[1];
+        Some(DiscId {
+            licensed: flags & UNLICENSED == 0,
+            audio: flags & AUDIO != 0,
+            disc_type: data[2],
+            license: [data[4], data[5], data[6], data[7]],
+        })
+    }
+
+    /// Gets the region from the license string.
+    pub fn region(&self) -> Option<Region> {
+        match &self.license {
+            b"SCEI" => Some(Region::Japan),
+            b"SCEA" => Some(Region::America),
+            b"SCEE" => Some(Region::Europe),
+            _ => None,
+        }
+    }
+}
+
+/// Gets the drive status with `GetStat`, recording whether the lid was
+/// opened for [`lid_opened`].
+pub fn status(cdrom: &mut CdRom) -> Result<DriveStatus, Error> {
+    let status = cdrom.get_stat()?;
+    if status.shell_open() {
+        with_state(|state| state.lid_opened = true);
+    }
+    Ok(status)
+}
+
+/// Checks if the lid was opened since the last call.
+pub fn lid_opened() -> bool {
+    with_state(|state| mem::take(&mut state.lid_opened))
+}
+
+/// Identifies the disc with `GetID`.
+///
+/// This returns [`Error::NoDisc`] if the lid is closed without a disc.
+/// Unlicensed and audio discs aren't errors, but the drive only reads
+/// licensed discs.
+pub fn get_id(cdrom: &mut CdRom) -> Result<DiscId, Error> {
+    match cdrom.command(Command::GetID, &[]) {
+        Ok(res) => DiscId::parse(res.data()).ok_or(Error::Command(res.status(), 0)),
+        Err(Error::Command(_, flags)) if flags & MISSING != 0 => Err(Error::NoDisc),
+        // The error response only has the flags
+        Err(Error::Command(status, flags)) if status.id_error() => Ok(DiscId {
+            licensed: flags & UNLICENSED == 0,
+            audio: flags & AUDIO != 0,
+            disc_type: 0,
+            license: [0; 4],
+        }),
+        Err(err) => Err(err),
+    }
+}
+
+/// Identifies a newly inserted disc and mounts its filesystem.
+///
+/// This turns the spindle motor on if it's off and sets the drive mode
+/// again. Commands fail while the drive spins up and reads the TOC after
+/// the lid is closed, so call this again later if it returns
+/// [`Error::Command`]. Audio discs return [`Error::IdError`].
+pub fn identify<const N: usize>(
+    cdrom: &mut CdRom,
+) -> Result<(DiscId, Filesystem<N>), iso9660::Error> {
+    let status = status(cdrom)?;
+    if status.shell_open() {
+        return Err(Error::LidOpen.into())
+    }
+    if !status.motor_on() {
+        cdrom.command(Command::MotorOn, &[])?;
+    }
+    let id = get_id(cdrom)?;
+    if id.audio {
+        return Err(Error::IdError.into())
+    }
+    cdrom.set_mode(cdrom.mode())?;
+    let fs = Filesystem::mount(cdrom)?;
+    Ok((id, fs))
+}
+
+/// What the game should ask the player to do, returned by
+/// [`DiscRequest::poll`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Prompt {
+    /// The requested disc is in the drive.
+    Ready,
+    /// The drive is reading the disc.
+    Reading,
+    /// Open the lid and insert the requested disc.
+    InsertDisc,
+    /// The disc in the drive isn't the requested one.
+    WrongDisc,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Step {
+    Identify,
+    WaitOpen,
+    WaitClose,
+    Done,
+}
+
+/// Asks the player to insert a disc and waits until they do.
+///
+/// The disc in the drive is checked first, so nothing is shown if it's
+/// already the requested one. Otherwise the player is prompted to open the
+/// lid, insert the disc and close it until a disc accepted by the check is
+/// inserted.
+#[derive(Debug)]
+pub struct DiscRequest<F, const N: usize = 128> {
+    check: F,
+    step: Step,
+    prompt: Prompt,
+    fs: Option<Filesystem<N>>,
+}
+
+impl<F: FnMut(&DiscId, &Filesystem<N>) -> bool, const N: usize> DiscRequest<F, N> {
+    /// Requests a disc which `check` accepts, usually by comparing its
+    /// [`volume_id`][Filesystem::volume_id].
+    pub fn new(check: F) -> Self {
+        DiscRequest {
+            check,
+            step: Step::Identify,
+            prompt: Prompt::Reading,
+            fs: None,
+        }
+    }
+
+    /// Checks the drive and gets the prompt to show.
+    ///
+    /// This sends a few commands each call and reads the filesystem of each
+    /// inserted disc, so it should be called about once per frame.
+    pub fn poll(&mut self, cdrom: &mut CdRom) -> Result<Prompt, iso9660::Error> {
+        let (step, prompt) = match self.step {
+            Step::Identify => match identify::<N>(cdrom) {
+                Ok((id, fs)) => {
+                    if (self.check)(&id, &fs) {
+                        self.fs = Some(fs);
+                        (Step::Done, Prompt::Ready)
+                    } else {
+                        (Step::WaitOpen, Prompt::WrongDisc)
+                    }
+                },
+                Err(iso9660::Error::Read(err)) => match err {
+                    Error::LidOpen => (Step::WaitClose, Prompt::InsertDisc),
+                    Error::NoDisc => (Step::WaitOpen, Prompt::InsertDisc),
+                    Error::IdError => (Step::WaitOpen, Prompt::WrongDisc),
+                    // The drive is still spinning up
+                    Error::Command(..) | Error::Timeout | Error::MotorOff | Error::SeekError => {
+                        (Step::Identify, Prompt::Reading)
+                    },
+                    err => return Err(err.into()),
+                },
+                // Not an ISO9660 disc
+                Err(iso9660::Error::InvalidVolume | iso9660::Error::InvalidRecord) => {
+                    (Step::WaitOpen, Prompt::WrongDisc)
+                },
+                Err(err) => return Err(err),
+            },
+            Step::WaitOpen => {
+                if status(cdrom)?.shell_open() {
+                    (Step::WaitClose, Prompt::InsertDisc)
+                } else {
+                    (Step::WaitOpen, self.prompt)
+                }
+            },
+            Step::WaitClose => {
+                if status(cdrom)?.shell_open() {
+                    (Step::WaitClose, Prompt::InsertDisc)
+                } else {
+                    (Step::Identify, Prompt::Reading)
+                }
+            },
+            Step::Done => (Step::Done, Prompt::Ready),
+        };
+        self.step = step;
+        self.prompt = prompt;
+        Ok(prompt)
+    }
+
+    /// Gets the filesystem of the requested disc once it's inserted.
+    pub fn filesystem(&self) -> Option<&Filesystem<N>> {
+        self.fs.as_ref()
+    }
+
+    /// Gets the filesystem of the requested disc once it's inserted,
+    /// consuming the request.
+    pub fn into_filesystem(self) -> Option<Filesystem<N>> {
+        self.fs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DiscId, Region};
+
+    #[test_case]
+    fn disc_ids() {
+        let id = DiscId::parse(&[0x02, 0x00, 0x20, 0x00, b'S', b'C', b'E', b'A']).unwrap();
+        assert!(id.licensed);
+        assert!(!id.audio);
+        assert!(id.disc_type == 0x20);
+        assert!(id.region() == Some(Region::America));
+        let id = DiscId::parse(&[0x0A, 0x90, 0x00, 0x00, 0, 0, 0, 0]).unwrap();
+        assert!(!id.licensed);
+        assert!(id.audio);
+        assert!(id.region().is_none());
+        assert!(DiscId::parse(&[0x02]).is_none());
+    }
+}
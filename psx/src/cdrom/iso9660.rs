@@ -0,0 +1,33 @@
+//! ISO9660 filesystem reader
+//!
+//! [`Filesystem::mount`] reads the primary volume descriptor, the path table
+//! and every directory through a [`SectorRead`] and caches the directory tree
+//! in RAM, so looking up files doesn't access the disc. Besides [`CdRom`],
+//! [`SectorRead`] is implemented for ISO images in memory.
+//!
+//! The parser is in the target-independent `psx-iso9660` crate so it can be
+//! tested on the host.
+use crate::cdrom::{self, CdRom};
+
+pub use psx_iso9660::{EndOfImage, Entry, SectorRead, MAX_NAME_LEN, SECTOR_SIZE, SECTOR_WORDS};
+
+/// An ISO9660-specific error.
+pub type Error = psx_iso9660::Error<cdrom::Error>;
+
+/// The directory tree of an ISO9660 volume with up to `N` files and
+/// directories.
+pub type Filesystem<const N: usize = 128> = psx_iso9660::Filesystem<N, cdrom::Error>;
+
+impl SectorRead for CdRom {
+    type Error = cdrom::Error;
+
+    fn read_sector(&mut self, lba: u32, buf: &mut [u32; SECTOR_WORDS]) -> Result<(), cdrom::Error> {
+        self.read(lba, buf)
+    }
+}
+
+impl From<EndOfImage> for cdrom::Error {
+    fn from(_: EndOfImage) -> Self {
+        cdrom::Error::EndOfDisc
+    }
+}
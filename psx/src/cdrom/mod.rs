@@ -0,0 +1,546 @@
+//! CD-ROM drive routines
+//!
+//! [`CdRom`] drives the controller through the registers in
+//! [`hw::cdrom`][crate::hw::cdrom] without going through the BIOS. Sectors
+//! are transferred into RAM through the CD-ROM DMA channel from the CD-ROM
+//! interrupt handler, so [`CdRom::read_async`] can keep the game running
+//! while data streams in.
+//!
+//! Creating a [`CdRom`] removes the BIOS's CD-ROM interrupt handlers, so the
+//! BIOS file functions can't access `cdrom:` afterwards.
+use crate::dma;
+use crate::hw::cdrom::{Bank, Bank0, Bank1, Command, IntCause, Selector, Status, ALL_INTERRUPTS};
+use crate::hw::cop0::IntSrc;
+use crate::hw::irq::IRQ;
+use crate::hw::{cop0, irq, Register};
+use crate::sys::interrupt::{self, Handler};
+use crate::sys::kernel;
+use core::ptr::addr_of_mut;
+use core::slice;
+
+pub mod audio;
+pub mod disc;
+pub mod iso9660;
+mod mode;
+mod msf;
+mod read;
+pub mod xa;
+
+pub use mode::{DriveStatus, Mode, SectorSize, Speed};
+pub use msf::{from_bcd, to_bcd, Msf, PREGAP_SECTORS, SECTORS_PER_SECOND};
+pub use read::Read;
+
+/// The number of times a read is restarted after an error.
+pub const MAX_RETRIES: u32 = 3;
+/// The maximum size of a command response in bytes.
+pub const RESPONSE_SIZE: usize = 16;
+/// The error code returned when the controller can't respond yet, e.g. before
+/// the TOC or the first sector header was read or without a disc.
+pub const NOT_READY: u8 = 0x80;
+
+/// The number of times the driver state is polled without progress before
+/// timing out.
+const TIMEOUT_POLLS: u32 = 0x0100_0000;
+/// The number of times the data FIFO is polled in the interrupt handler
+/// before giving up on a sector.
+const FIFO_POLLS: u32 = 0x0001_0000;
+
+/// A CD-ROM-specific error.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error {
+    /// The controller didn't respond in time.
+    Timeout,
+    /// A command failed with the given drive status and error code.
+    Command(DriveStatus, u8),
+    /// The lid is open or was opened since the drive status was last read.
+    LidOpen,
+    /// The spindle motor is stopped.
+    MotorOff,
+    /// The drive failed to seek to the requested position.
+    SeekError,
+    /// `GetID` found an unlicensed or audio disc.
+    IdError,
+    /// The lid is closed but there's no disc.
+    NoDisc,
+    /// A read reached the end of the disc.
+    EndOfDisc,
+    /// The buffer's length isn't a multiple of the sector size.
+    InvalidBuffer,
+    /// A DMA transfer from the data FIFO failed.
+    Transfer(dma::Error),
+}
+
+/// A response to a command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Response {
+    len: u8,
+    bytes: [u8; RESPONSE_SIZE],
+}
+
+impl Response {
+    const fn new() -> Self {
+        Response {
+            len: 0,
+            bytes: [0; RESPONSE_SIZE],
+        }
+    }
+
+    /// Gets the response's bytes.
+    pub fn data(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+
+    /// Gets the drive status from the first byte of the response.
+    pub fn status(&self) -> DriveStatus {
+        DriveStatus(self.bytes[0])
+    }
+
+    fn error(&self) -> Error {
+        let code = self.data().get(1).copied().unwrap_or(0);
+        if self.status().shell_open() {
+            Error::LidOpen
+        } else {
+            Error::Command(self.status(), code)
+        }
+    }
+}
+
+/// The response the driver is waiting for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Phase {
+    Idle,
+    Acknowledge,
+    Complete,
+}
+
+#[derive(Debug)]
+struct ReadState {
+    dst: *mut u32,
+    words: usize,
+    sectors: usize,
+    done: usize,
+    // The sectors released by `release_sector` when `dst` is a ring buffer
+    released: usize,
+    error: Option<Error>,
+}
+
+#[derive(Debug)]
+struct State {
+    phase: Phase,
+    completes: bool,
+    first: Option<Response>,
+    second: Option<Response>,
+    error: Option<Error>,
+    // Set when the responses are for a command sent by the interrupt handler
+    discard: bool,
+    read: Option<ReadState>,
+    // Set when an unsolicited error reports that the lid was opened
+    lid_opened: bool,
+    report: Option<audio::Report>,
+    play_end: Option<Msf>,
+    play_ended: bool,
+    selector: Selector,
+}
+
+static mut STATE: State = State {
+    phase: Phase::Idle,
+    completes: false,
+    first: None,
+    second: None,
+    error: None,
+    discard: false,
+    read: None,
+    lid_opened: false,
+    report: None,
+    play_end: None,
+    play_ended: false,
+    // SAFETY: This is the only selector
+    selector: unsafe { Selector::new() },
+};
+static mut HANDLER: Handler = Handler::new(on_interrupt);
+static mut INSTALLED: bool = false;
+
+/// Checks if a command sends a second response with INT2 when it completes.
+const fn completes(cmd: Command) -> bool {
+    matches!(
+        cmd,
+        Command::MotorOn |
+            Command::Stop |
+            Command::Pause |
+            Command::Init |
+            Command::SetSession |
+            Command::SeekL |
+            Command::SeekP |
+            Command::GetID |
+            Command::ReadTOC
+    )
+}
+
+/// Sends a command and sets up the state to wait for its responses.
+///
+/// The responses are ignored if `discard` is set.
+fn send(state: &mut State, cmd: Command, params: &[u8], discard: bool) {
+    state.phase = Phase::Acknowledge;
+    state.completes = completes(cmd);
+    state.first = None;
+    state.second = None;
+    state.error = None;
+    state.discard = discard;
+    Bank0::select(&mut state.selector)
+        .push_parameters(params)
+        .send_command(cmd);
+}
+
+/// Runs `f` on the driver state in a critical section.
+fn with_state<R>(mut f: impl FnMut(&mut State) -> R) -> R {
+    cop0::Status::new().critical_section(|_| {
+        // SAFETY: We're in a critical section so the interrupt handler can't
+        // access the state
+        f(unsafe { &mut *addr_of_mut!(STATE) })
+    })
+}
+
+/// Polls `f` until it returns `Some`, timing out if it doesn't.
+fn poll<T>(mut f: impl FnMut(&mut State) -> Option<T>) -> Result<T, Error> {
+    for _ in 0..TIMEOUT_POLLS {
+        if let Some(res) = with_state(&mut f) {
+            return Ok(res)
+        }
+    }
+    Err(Error::Timeout)
+}
+
+extern "C" fn on_interrupt() -> u32 {
+    if irq::Mask::new().irq_disabled(IRQ::CDROM) || !irq::Status::new().requested(IRQ::CDROM) {
+        return 0
+    }
+    // Writing ones leaves the other interrupt requests alone
+    irq::Status::skip_load().assign(!0).ack(IRQ::CDROM).store();
+    // The interrupted code may have selected a different bank
+    let idx = Status::new().get_idx();
+    // SAFETY: This runs in the BIOS interrupt handler so it can't be
+    // interrupted by the `CdRom` methods
+    service(unsafe { &mut *addr_of_mut!(STATE) });
+    Status::skip_load().set_idx(idx).store();
+    0
+}
+
+/// Handles the pending CD-ROM interrupt.
+fn service(state: &mut State) {
+    let mut bank = Bank1::select(&mut state.selector);
+    let cause = bank.int_cause();
+    let mut res = Response::new();
+    res.len = bank.read_responses(&mut res.bytes) as u8;
+    bank.ack_interrupts(ALL_INTERRUPTS);
+    let Some(cause) = cause else { return };
+    match cause {
+        IntCause::Acknowledge => {
+            if state.phase == Phase::Acknowledge {
+                if !state.discard {
+                    state.first = Some(res);
+                }
+                state.phase = if state.completes {
+                    Phase::Complete
+                } else {
+                    Phase::Idle
+                };
+            }
+        },
+        IntCause::Complete => {
+            if state.phase == Phase::Complete {
+                if !state.discard {
+                    state.second = Some(res);
+                }
+                state.phase = Phase::Idle;
+            }
+        },
+        IntCause::DiscError => {
+            if res.status().shell_open() {
+                state.lid_opened = true;
+            }
+            if state.phase != Phase::Idle {
+                if !state.discard {
+                    state.error = Some(res.error());
+                }
+                state.phase = Phase::Idle;
+            } else if let Some(read) = &mut state.read {
+                read.error.get_or_insert(res.error());
+            }
+        },
+        // Position reports are also sent with INT1 while playing
+        IntCause::DataReady if state.read.is_none() && res.status().playing() => {
+            audio::on_report(state, &res)
+        },
+        IntCause::DataReady => receive_sector(state),
+        IntCause::DataEnd => match &mut state.read {
+            Some(read) => {
+                read.error.get_or_insert(Error::EndOfDisc);
+            },
+            None => state.play_ended = true,
+        },
+    }
+}
+
+/// Transfers a sector from the data FIFO into the active read's buffer or
+/// discards it if there isn't one.
+fn receive_sector(state: &mut State) {
+    let mut bank = Bank0::select(&mut state.selector);
+    let read = match &mut state.read {
+        Some(read) if read.error.is_none() && read.done - read.released < read.sectors => read,
+        _ => {
+            bank.request_data(false, false);
+            return
+        },
+    };
+    bank.request_data(true, false);
+    // The FIFO may never fill, e.g. if the lid was opened during the read
+    if (0..FIFO_POLLS).all(|_| Status::new().data_fifo_empty()) {
+        read.error = Some(Error::Timeout);
+        bank.request_data(false, false);
+        return
+    }
+    // SAFETY: `dst` points to a buffer of `sectors * words` words which is
+    // borrowed until the read is stopped
+    let slot = read.done % read.sectors;
+    let block = unsafe { slice::from_raw_parts_mut(read.dst.add(slot * read.words), read.words) };
+    match dma::CDROM::new().receive_and(block, || ()) {
+        Ok(()) => read.done += 1,
+        Err(err) => read.error = Some(Error::Transfer(err)),
+    }
+    bank.request_data(false, false);
+}
+
+/// A handle to the CD-ROM drive.
+#[derive(Debug)]
+pub struct CdRom {
+    mode: Mode,
+}
+
+impl CdRom {
+    /// Takes over the CD-ROM controller from the BIOS and sets the drive to
+    /// read 2048-byte sectors at double speed.
+    ///
+    /// This installs the interrupt handler if necessary and enables the
+    /// CD-ROM interrupt in [`irq::Mask`] and hardware interrupts in cop0.
+    pub fn new() -> Result<Self, Error> {
+        cop0::Status::new().critical_section(|_| {
+            // SAFETY: We're in a critical section so the interrupt handlers
+            // can't run
+            unsafe {
+                if !INSTALLED {
+                    kernel::psx_cd_remove();
+                    interrupt::enqueue(interrupt::DEFAULT_PRIORITY, &mut *addr_of_mut!(HANDLER));
+                    INSTALLED = true;
+                }
+            }
+            // SAFETY: The interrupt handler can't access the state in a critical
+            // section
+            let state = unsafe { &mut *addr_of_mut!(STATE) };
+            Bank1::select(&mut state.selector)
+                .set_interrupt_enable(ALL_INTERRUPTS)
+                .ack_interrupts(ALL_INTERRUPTS);
+            irq::Mask::new().enable_irq(IRQ::CDROM).store();
+        });
+        cop0::Status::new()
+            .unmask_interrupt(IntSrc::Hardware)
+            .enable_interrupts()
+            .store();
+        let mut cdrom = CdRom { mode: Mode::new() };
+        // The first GetStat after the lid was opened reports it, so ignore it
+        cdrom.get_stat()?;
+        let mut mode = Mode::new();
+        mode.set_speed(Speed::Double);
+        cdrom.set_mode(mode)?;
+        Ok(cdrom)
+    }
+
+    /// Sends a command with the given parameters and waits for its
+    /// responses.
+    ///
+    /// Returns the second response for commands which send one or the first
+    /// response otherwise.
+    pub fn command(&mut self, cmd: Command, params: &[u8]) -> Result<Response, Error> {
+        while Status::new().busy() {}
+        // Wait for responses to commands sent by the interrupt handler
+        poll(|state| {
+            if state.phase != Phase::Idle {
+                return None
+            }
+            send(state, cmd, params, false);
+            Some(())
+        })?;
+        let wait = |state: &mut State, second: bool| {
+            if let Some(err) = state.error.take() {
+                return Some(Err(err))
+            }
+            let res = if second {
+                state.second.take()
+            } else {
+                state.first.take()
+            };
+            res.map(Ok)
+        };
+        let first = poll(|state| wait(state, false)).and_then(|res| res);
+        let res = if completes(cmd) {
+            first.and_then(|_| poll(|state| wait(state, true)).and_then(|res| res))
+        } else {
+            first
+        };
+        if res.is_err() {
+            with_state(|state| state.phase = Phase::Idle);
+        }
+        res
+    }
+
+    /// Gets the drive status, clearing the lid open bit if the lid was
+    /// closed.
+    pub fn get_stat(&mut self) -> Result<DriveStatus, Error> {
+        self.command(Command::GetStat, &[]).map(|res| res.status())
+    }
+
+    /// Gets the drive mode.
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Sets the drive mode.
+    pub fn set_mode(&mut self, mode: Mode) -> Result<(), Error> {
+        self.command(Command::Setmode, &[mode.bits()])?;
+        self.mode = mode;
+        Ok(())
+    }
+
+    /// Sets the drive speed.
+    pub fn set_speed(&mut self, speed: Speed) -> Result<(), Error> {
+        let mut mode = self.mode;
+        mode.set_speed(speed);
+        self.set_mode(mode)
+    }
+
+    /// Sets the size of the sectors transferred by reads.
+    pub fn set_sector_size(&mut self, size: SectorSize) -> Result<(), Error> {
+        let mut mode = self.mode;
+        mode.set_sector_size(size);
+        self.set_mode(mode)
+    }
+
+    /// Sets the position the next read or seek starts at.
+    pub fn set_loc(&mut self, msf: Msf) -> Result<(), Error> {
+        self.command(Command::Setloc, &msf.to_bcd()).map(|_| ())
+    }
+
+    /// Stops reading or playing and keeps the motor on.
+    pub fn pause(&mut self) -> Result<(), Error> {
+        self.command(Command::Pause, &[]).map(|_| ())
+    }
+
+    /// Reads sectors starting at a logical block address into `buf`.
+    ///
+    /// The length of `buf` must be a multiple of the sector size set by
+    /// [`CdRom::set_sector_size`]. This uses `ReadN`, which makes the drive
+    /// retry sectors it fails to read, and restarts the read from the failed
+    /// sector up to [`MAX_RETRIES`] times. The drive is paused afterwards.
+    pub fn read(&mut self, lba: u32, buf: &mut [u32]) -> Result<(), Error> {
+        self.read_async(lba, buf)?.wait()
+    }
+
+    /// Reads sectors like [`CdRom::read`] using `ReadS`, which doesn't make
+    /// the drive retry sectors it fails to read.
+    ///
+    /// This avoids stalls when streaming data which tolerates errors.
+    pub fn read_streaming(&mut self, lba: u32, buf: &mut [u32]) -> Result<(), Error> {
+        self.read_streaming_async(lba, buf)?.wait()
+    }
+
+    /// Starts reading sectors like [`CdRom::read`] without waiting for them.
+    ///
+    /// This only blocks until the drive acknowledges the read. Sectors are
+    /// transferred into `buf` by the CD-ROM interrupt handler while the
+    /// returned [`Read`] is polled or awaited.
+    pub fn read_async<'a>(&'a mut self, lba: u32, buf: &'a mut [u32]) -> Result<Read<'a>, Error> {
+        Read::start(self, Command::ReadN, lba, buf)
+    }
+
+    /// Starts reading sectors like [`CdRom::read_streaming`] without waiting
+    /// for them.
+    pub fn read_streaming_async<'a>(
+        &'a mut self, lba: u32, buf: &'a mut [u32],
+    ) -> Result<Read<'a>, Error> {
+        Read::start(self, Command::ReadS, lba, buf)
+    }
+
+    /// Starts transferring sectors into `buf` and sends the read command.
+    ///
+    /// `buf` is used as a ring buffer if sectors are freed with
+    /// [`release_sector`].
+    pub(crate) fn start_read(
+        &mut self, cmd: Command, lba: u32, buf: &mut [u32], words: usize,
+    ) -> Result<(), Error> {
+        let read = ReadState {
+            dst: buf.as_mut_ptr(),
+            words,
+            sectors: buf.len() / words,
+            done: 0,
+            released: 0,
+            error: None,
+        };
+        let mut read = Some(read);
+        with_state(|state| state.read = read.take());
+        self.set_loc(Msf::from_lba(lba))?;
+        self.command(cmd, &[]).map(|_| ())
+    }
+}
+
+/// Stops transferring sectors for the active read, returning the number of
+/// sectors received.
+pub(crate) fn stop_read() -> usize {
+    with_state(|state| state.read.take().map_or(0, |read| read.done))
+}
+
+/// Gets the index in the active read's buffer of the oldest sector which
+/// wasn't released yet.
+///
+/// Sectors received while the buffer is full of unreleased sectors are
+/// discarded.
+pub(crate) fn next_sector() -> Result<Option<usize>, Error> {
+    with_state(|state| match &state.read {
+        Some(read) if read.released < read.done => Ok(Some(read.released % read.sectors)),
+        Some(ReadState {
+            error: Some(err), ..
+        }) => Err(*err),
+        _ => Ok(None),
+    })
+}
+
+/// Releases the sector returned by [`next_sector`] so the active read can
+/// reuse its space in the buffer.
+pub(crate) fn release_sector() {
+    with_state(|state| {
+        if let Some(read) = &mut state.read {
+            read.released = (read.released + 1).min(read.done);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DriveStatus, Error, Response, NOT_READY};
+
+    fn response(data: &[u8]) -> Response {
+        let mut res = Response::new();
+        res.bytes[..data.len()].copy_from_slice(data);
+        res.len = data.len() as u8;
+        res
+    }
+
+    #[test_case]
+    fn errors() {
+        // The controller isn't ready, e.g. before the TOC is read
+        let stat = 0x02;
+        let not_ready = Error::Command(DriveStatus(stat | 1), NOT_READY);
+        assert!(response(&[stat | 1, 0x80]).error() == not_ready);
+        assert!(response(&[0x12 | 1, 0x80]).error() == Error::LidOpen);
+        assert!(response(&[stat | 1, 0x10]).error() == Error::Command(DriveStatus(stat | 1), 0x10));
+        assert!(response(&[stat | 1]).error() == Error::Command(DriveStatus(stat | 1), 0));
+    }
+}
@@ -0,0 +1,260 @@
+use crate::cdrom::Error;
+
+// Setmode parameter bits
+const CDDA: u8 = 0;
+const AUTO_PAUSE: u8 = 1;
+const REPORT: u8 = 2;
+const XA_FILTER: u8 = 3;
+const SECTOR_SIZE: u8 = 5;
+const XA_ADPCM: u8 = 6;
+const SPEED: u8 = 7;
+
+// Drive status bits
+const ERROR: u8 = 0;
+const MOTOR_ON: u8 = 1;
+const SEEK_ERROR: u8 = 2;
+const ID_ERROR: u8 = 3;
+const SHELL_OPEN: u8 = 4;
+const READING: u8 = 5;
+const SEEKING: u8 = 6;
+const PLAYING: u8 = 7;
+
+/// The drive's rotation speed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Speed {
+    /// 75 sectors per second. CD audio and XA-ADPCM play at this speed.
+    Single = 0,
+    /// 150 sectors per second.
+    Double,
+}
+
+/// The part of each sector transferred by reads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SectorSize {
+    /// The 2048 bytes of user data.
+    Data = 0,
+    /// The 2340 bytes after the sync pattern, including the header and
+    /// subheader.
+    Raw,
+}
+
+impl SectorSize {
+    /// Gets the sector size in bytes.
+    pub const fn bytes(self) -> usize {
+        match self {
+            SectorSize::Data => 2048,
+            SectorSize::Raw => 2340,
+        }
+    }
+
+    /// Gets the sector size in 32-bit words.
+    pub const fn words(self) -> usize {
+        self.bytes() / 4
+    }
+}
+
+/// The drive mode set by the `Setmode` command.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Mode(u8);
+
+impl Mode {
+    /// Creates a mode for reading 2048-byte data sectors at single speed.
+    pub const fn new() -> Self {
+        Mode(0)
+    }
+
+    /// Gets the mode's bits.
+    pub const fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// Creates a mode from its bits.
+    pub const fn from_bits(bits: u8) -> Self {
+        Mode(bits)
+    }
+
+    fn set(&mut self, bit: u8, value: bool) -> &mut Self {
+        self.0 = (self.0 & !(1 << bit)) | (value as u8) << bit;
+        self
+    }
+
+    fn get(&self, bit: u8) -> bool {
+        self.0 & (1 << bit) != 0
+    }
+
+    /// Gets the drive speed.
+    pub fn speed(&self) -> Speed {
+        if self.get(SPEED) {
+            Speed::Double
+        } else {
+            Speed::Single
+        }
+    }
+
+    /// Sets the drive speed.
+    pub fn set_speed(&mut self, speed: Speed) -> &mut Self {
+        self.set(SPEED, speed == Speed::Double)
+    }
+
+    /// Gets the size of the sectors transferred by reads.
+    pub fn sector_size(&self) -> SectorSize {
+        if self.get(SECTOR_SIZE) {
+            SectorSize::Raw
+        } else {
+            SectorSize::Data
+        }
+    }
+
+    /// Sets the size of the sectors transferred by reads.
+    pub fn set_sector_size(&mut self, size: SectorSize) -> &mut Self {
+        self.set(SECTOR_SIZE, size == SectorSize::Raw)
+    }
+
+    /// Checks if CD audio sectors can be read as data.
+    pub fn cdda(&self) -> bool {
+        self.get(CDDA)
+    }
+
+    /// Allows reading CD audio sectors as data.
+    pub fn set_cdda(&mut self, cdda: bool) -> &mut Self {
+        self.set(CDDA, cdda)
+    }
+
+    /// Checks if playback pauses at the end of each track.
+    pub fn auto_pause(&self) -> bool {
+        self.get(AUTO_PAUSE)
+    }
+
+    /// Pauses playback at the end of each track with an INT4.
+    pub fn set_auto_pause(&mut self, auto_pause: bool) -> &mut Self {
+        self.set(AUTO_PAUSE, auto_pause)
+    }
+
+    /// Checks if position reports are sent while playing.
+    pub fn report(&self) -> bool {
+        self.get(REPORT)
+    }
+
+    /// Sends position reports with INT1 while playing CD audio.
+    pub fn set_report(&mut self, report: bool) -> &mut Self {
+        self.set(REPORT, report)
+    }
+
+    /// Checks if XA-ADPCM sectors are filtered by file and channel.
+    pub fn xa_filter(&self) -> bool {
+        self.get(XA_FILTER)
+    }
+
+    /// Only plays XA-ADPCM sectors matching the `Setfilter` file and channel.
+    pub fn set_xa_filter(&mut self, filter: bool) -> &mut Self {
+        self.set(XA_FILTER, filter)
+    }
+
+    /// Checks if XA-ADPCM sectors are sent to the SPU.
+    pub fn xa_adpcm(&self) -> bool {
+        self.get(XA_ADPCM)
+    }
+
+    /// Sends XA-ADPCM sectors to the SPU instead of the data FIFO.
+    pub fn set_xa_adpcm(&mut self, xa_adpcm: bool) -> &mut Self {
+        self.set(XA_ADPCM, xa_adpcm)
+    }
+}
+
+/// The drive status byte returned in most command responses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DriveStatus(pub u8);
+
+impl DriveStatus {
+    fn get(&self, bit: u8) -> bool {
+        self.0 & (1 << bit) != 0
+    }
+
+    /// Checks if the command failed.
+    pub fn error(&self) -> bool {
+        self.get(ERROR)
+    }
+
+    /// Checks if the spindle motor is on.
+    pub fn motor_on(&self) -> bool {
+        self.get(MOTOR_ON)
+    }
+
+    /// Checks if the last seek failed.
+    pub fn seek_error(&self) -> bool {
+        self.get(SEEK_ERROR)
+    }
+
+    /// Checks if `GetID` failed to identify the disc.
+    pub fn id_error(&self) -> bool {
+        self.get(ID_ERROR)
+    }
+
+    /// Checks if the lid was opened since the last `GetStat`.
+    pub fn shell_open(&self) -> bool {
+        self.get(SHELL_OPEN)
+    }
+
+    /// Checks if the drive is reading data sectors.
+    pub fn reading(&self) -> bool {
+        self.get(READING)
+    }
+
+    /// Checks if the drive is seeking.
+    pub fn seeking(&self) -> bool {
+        self.get(SEEKING)
+    }
+
+    /// Checks if the drive is playing CD audio.
+    pub fn playing(&self) -> bool {
+        self.get(PLAYING)
+    }
+
+    /// Checks the status for an open lid, a stopped spindle motor and seek
+    /// or `GetID` errors, in that order.
+    pub fn check(&self) -> Result<(), Error> {
+        if self.shell_open() {
+            Err(Error::LidOpen)
+        } else if !self.motor_on() {
+            Err(Error::MotorOff)
+        } else if self.seek_error() {
+            Err(Error::SeekError)
+        } else if self.id_error() {
+            Err(Error::IdError)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DriveStatus, Mode, SectorSize, Speed};
+    use crate::cdrom::Error;
+
+    #[test_case]
+    fn mode_bits() {
+        let mut mode = Mode::new();
+        mode.set_speed(Speed::Double)
+            .set_sector_size(SectorSize::Raw);
+        assert!(mode.bits() == 0xA0);
+        assert!(mode.speed() == Speed::Double);
+        assert!(mode.sector_size() == SectorSize::Raw);
+        mode.set_speed(Speed::Single)
+            .set_xa_adpcm(true)
+            .set_xa_filter(true);
+        assert!(mode.bits() == 0x68);
+        assert!(SectorSize::Raw.words() == 585);
+    }
+
+    #[test_case]
+    fn drive_status() {
+        assert!(DriveStatus(0x02).check() == Ok(()));
+        assert!(DriveStatus(0x22).check() == Ok(()));
+        assert!(DriveStatus(0x12).check() == Err(Error::LidOpen));
+        assert!(DriveStatus(0x10).check() == Err(Error::LidOpen));
+        assert!(DriveStatus(0x00).check() == Err(Error::MotorOff));
+        assert!(DriveStatus(0x06).check() == Err(Error::SeekError));
+        assert!(DriveStatus(0x0A).check() == Err(Error::IdError));
+    }
+}
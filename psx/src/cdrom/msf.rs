@@ -0,0 +1,99 @@
+/// The number of sectors per second of CD audio.
+pub const SECTORS_PER_SECOND: u32 = 75;
+/// The number of sectors in the lead-in before logical block address 0.
+pub const PREGAP_SECTORS: u32 = 150;
+
+/// Converts a value from 0 to 99 to binary-coded decimal.
+pub const fn to_bcd(value: u8) -> u8 {
+    (value / 10) << 4 | (value % 10)
+}
+
+/// Converts a binary-coded decimal value to binary.
+pub const fn from_bcd(bcd: u8) -> u8 {
+    (bcd >> 4) * 10 + (bcd & 0xF)
+}
+
+/// A position on the disc in minutes, seconds and sectors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Msf {
+    /// The minute from 0 to 99.
+    pub minute: u8,
+    /// The second from 0 to 59.
+    pub second: u8,
+    /// The sector from 0 to 74.
+    pub sector: u8,
+}
+
+impl Msf {
+    /// Creates a position from its minute, second and sector.
+    pub const fn new(minute: u8, second: u8, sector: u8) -> Self {
+        Msf {
+            minute,
+            second,
+            sector,
+        }
+    }
+
+    /// Gets the position of a logical block address, which starts after the
+    /// 2 second pregap.
+    pub const fn from_lba(lba: u32) -> Self {
+        let sectors = lba + PREGAP_SECTORS;
+        let seconds = sectors / SECTORS_PER_SECOND;
+        Msf {
+            minute: (seconds / 60) as u8,
+            second: (seconds % 60) as u8,
+            sector: (sectors % SECTORS_PER_SECOND) as u8,
+        }
+    }
+
+    /// Gets the logical block address of the position, saturating at 0 in
+    /// the pregap.
+    pub const fn to_lba(&self) -> u32 {
+        let seconds = self.minute as u32 * 60 + self.second as u32;
+        let sectors = seconds * SECTORS_PER_SECOND + self.sector as u32;
+        sectors.saturating_sub(PREGAP_SECTORS)
+    }
+
+    /// Gets the position from a BCD-encoded minute, second and sector.
+    pub const fn from_bcd(bcd: [u8; 3]) -> Self {
+        Msf::new(from_bcd(bcd[0]), from_bcd(bcd[1]), from_bcd(bcd[2]))
+    }
+
+    /// Gets the position as a BCD-encoded minute, second and sector.
+    pub const fn to_bcd(&self) -> [u8; 3] {
+        [
+            to_bcd(self.minute),
+            to_bcd(self.second),
+            to_bcd(self.sector),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_bcd, to_bcd, Msf};
+
+    #[test_case]
+    fn bcd() {
+        assert!(to_bcd(0) == 0);
+        assert!(to_bcd(59) == 0x59);
+        assert!(from_bcd(0x74) == 74);
+        for value in 0..100 {
+            assert!(from_bcd(to_bcd(value)) == value);
+        }
+    }
+
+    #[test_case]
+    fn lba() {
+        assert!(Msf::from_lba(0) == Msf::new(0, 2, 0));
+        assert!(Msf::from_lba(16) == Msf::new(0, 2, 16));
+        assert!(Msf::from_lba(4350) == Msf::new(1, 0, 0));
+        assert!(Msf::new(0, 2, 0).to_lba() == 0);
+        assert!(Msf::new(0, 0, 10).to_lba() == 0);
+        assert!(Msf::from_bcd([0x12, 0x34, 0x56]) == Msf::new(12, 34, 56));
+        assert!(Msf::new(12, 34, 56).to_bcd() == [0x12, 0x34, 0x56]);
+        fuzz!(|lba: u16| {
+            assert!(Msf::from_lba(lba as u32).to_lba() == lba as u32);
+        });
+    }
+}
@@ -0,0 +1,176 @@
+use crate::cdrom::{stop_read, with_state, CdRom, Error, MAX_RETRIES, TIMEOUT_POLLS};
+use crate::hw::cdrom::Command;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+/// A handle to a read started by [`CdRom::read_async`].
+///
+/// Dropping the handle before the read completes cancels it.
+#[derive(Debug)]
+pub struct Read<'a> {
+    cdrom: &'a mut CdRom,
+    buf: &'a mut [u32],
+    cmd: Command,
+    lba: u32,
+    words: usize,
+    // The number of sectors received by previous attempts
+    done: usize,
+    retries: u32,
+    last_progress: usize,
+    stalled_polls: u32,
+    result: Option<Result<(), Error>>,
+}
+
+impl<'a> Read<'a> {
+    pub(super) fn start(
+        cdrom: &'a mut CdRom, cmd: Command, lba: u32, buf: &'a mut [u32],
+    ) -> Result<Self, Error> {
+        let words = cdrom.mode().sector_size().words();
+        if buf.len() % words != 0 {
+            return Err(Error::InvalidBuffer)
+        }
+        let mut read = Read {
+            cdrom,
+            buf,
+            cmd,
+            lba,
+            words,
+            done: 0,
+            retries: 0,
+            last_progress: 0,
+            stalled_polls: 0,
+            result: None,
+        };
+        if read.sectors() == 0 {
+            read.result = Some(Ok(()));
+            return Ok(read)
+        }
+        // Only finish the read early if it couldn't be started
+        if let Err(err) = read.restart() {
+            if let Poll::Ready(Err(err)) = read.retry(Err(err)) {
+                return Err(err)
+            }
+        }
+        Ok(read)
+    }
+
+    /// Gets the number of sectors being read.
+    pub fn sectors(&self) -> usize {
+        self.buf.len() / self.words
+    }
+
+    /// Gets the number of sectors received so far.
+    pub fn sectors_read(&self) -> usize {
+        if self.result.is_some() {
+            return self.done
+        }
+        self.done + with_state(|state| state.read.as_ref().map_or(0, |read| read.done))
+    }
+
+    /// Checks if the read completed, restarting it from the first missing
+    /// sector if it failed and retries are left.
+    ///
+    /// The drive is paused when this returns [`Poll::Ready`].
+    pub fn poll(&mut self) -> Poll<Result<(), Error>> {
+        if let Some(res) = self.result {
+            return Poll::Ready(res)
+        }
+        let status = with_state(|state| {
+            let read = state.read.as_ref()?;
+            Some((read.done, read.sectors, read.error))
+        });
+        let res = match status {
+            Some((_, _, Some(err))) => Err(err),
+            Some((done, sectors, None)) if done < sectors => {
+                if done != self.last_progress {
+                    self.last_progress = done;
+                    self.stalled_polls = 0;
+                    return Poll::Pending
+                }
+                self.stalled_polls += 1;
+                if self.stalled_polls < TIMEOUT_POLLS {
+                    return Poll::Pending
+                }
+                Err(Error::Timeout)
+            },
+            _ => Ok(()),
+        };
+        self.retry(res)
+    }
+
+    /// Blocks until the read completes.
+    ///
+    /// This times out if no sectors are received for a while.
+    pub fn wait(mut self) -> Result<(), Error> {
+        loop {
+            if let Poll::Ready(res) = self.poll() {
+                return res
+            }
+        }
+    }
+
+    /// Stops the read and pauses the drive.
+    ///
+    /// Sectors received before the read was cancelled are left in the
+    /// buffer.
+    pub fn cancel(mut self) -> Result<(), Error> {
+        self.stop()
+    }
+
+    fn stop(&mut self) -> Result<(), Error> {
+        if self.result.is_some() {
+            return Ok(())
+        }
+        self.done += stop_read();
+        self.result = Some(Ok(()));
+        self.cdrom.pause()
+    }
+
+    fn restart(&mut self) -> Result<(), Error> {
+        let lba = self.lba + self.done as u32;
+        let remaining = &mut self.buf[self.done * self.words..];
+        self.last_progress = 0;
+        self.stalled_polls = 0;
+        self.cdrom.start_read(self.cmd, lba, remaining, self.words)
+    }
+
+    /// Finishes the read with `res` or restarts it if it failed.
+    fn retry(&mut self, mut res: Result<(), Error>) -> Poll<Result<(), Error>> {
+        loop {
+            // Sectors received before an error don't need to be read again
+            self.done += stop_read();
+            if res.is_ok() || self.retries == MAX_RETRIES {
+                break
+            }
+            self.retries += 1;
+            res = self.restart();
+            if res.is_ok() {
+                return Poll::Pending
+            }
+        }
+        let res = res.and(self.cdrom.pause());
+        self.result = Some(res);
+        Poll::Ready(res)
+    }
+}
+
+impl Future for Read<'_> {
+    type Output = Result<(), Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let res = self.get_mut().poll();
+        if res.is_pending() {
+            // Sectors are received by the interrupt handler so there's
+            // nothing to register the waker with
+            cx.waker().wake_by_ref();
+        }
+        res
+    }
+}
+
+impl Drop for Read<'_> {
+    fn drop(&mut self) {
+        self.stop().ok();
+    }
+}
@@ -0,0 +1,317 @@
+//! XA-ADPCM streaming
+//!
+//! XA files interleave ADPCM audio sectors for several channels with data
+//! sectors. The drive decodes the sectors for the channel selected with
+//! `Setfilter` and sends them straight to the SPU's CD audio input while the
+//! data sectors are transferred like a normal read. The volume is set with
+//! [`audio::set_volume`] and [`audio::set_spu_volume`].
+//!
+//! [`audio::set_volume`]: crate::cdrom::audio::set_volume
+//! [`audio::set_spu_volume`]: crate::cdrom::audio::set_spu_volume
+use crate::cdrom::{stop_read, with_state, CdRom, DriveStatus, Error, Mode, Msf, SectorSize,
+                   NOT_READY};
+use crate::hw::cdrom::{Command, Status};
+use crate::hw::Register;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+// Subheader submode bits
+const AUDIO: u8 = 2;
+const END_OF_FILE: u8 = 7;
+
+/// The words in each data sector transferred while streaming.
+const DATA_WORDS: usize = SectorSize::Data.words();
+
+/// An interleaved XA channel selected by its subheader file and channel
+/// numbers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Channel {
+    /// The file number, which is usually 1.
+    pub file: u8,
+    /// The channel number from 0 to 31.
+    pub channel: u8,
+}
+
+impl Channel {
+    /// Creates a channel from its file and channel numbers.
+    pub const fn new(file: u8, channel: u8) -> Self {
+        Channel { file, channel }
+    }
+}
+
+/// The header and subheader of the last sector read, returned by
+/// [`last_header`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SectorHeader {
+    /// The sector's position.
+    pub msf: Msf,
+    /// The sector mode, which is 2 for XA sectors.
+    pub mode: u8,
+    /// The subheader file number.
+    pub file: u8,
+    /// The subheader channel number.
+    pub channel: u8,
+    /// The subheader submode bits.
+    pub submode: u8,
+    /// The subheader coding info for audio sectors.
+    pub coding: u8,
+}
+
+impl SectorHeader {
+    /// Parses the response to `GetlocL`.
+    fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < 8 {
+            return None
+        }
+        Some(SectorHeader {
+            msf: Msf::from_bcd([data[0], data[1], data[2]]),
+            mode: data[3],
+            file: data[4],
+            channel: data[5],
+            submode: data[6],
+            coding: data[7],
+        })
+    }
+
+    /// Gets the sector's logical block address.
+    pub fn lba(&self) -> u32 {
+        self.msf.to_lba()
+    }
+
+    /// Gets the sector's channel.
+    pub fn xa_channel(&self) -> Channel {
+        Channel::new(self.file, self.channel)
+    }
+
+    /// Checks if the sector contains XA-ADPCM audio.
+    pub fn audio(&self) -> bool {
+        self.submode & (1 << AUDIO) != 0
+    }
+
+    /// Checks if the sector is the last one of its channel.
+    pub fn end_of_file(&self) -> bool {
+        self.submode & (1 << END_OF_FILE) != 0
+    }
+}
+
+/// Selects the channel XA-ADPCM sectors are played from with `Setfilter`.
+pub fn set_filter(cdrom: &mut CdRom, channel: Channel) -> Result<(), Error> {
+    cdrom
+        .command(Command::Setfilter, &[channel.file, channel.channel])
+        .map(|_| ())
+}
+
+/// Gets the header of the last data or XA-ADPCM sector read with `GetlocL`.
+///
+/// This fails if the drive hasn't read a sector since it started reading.
+pub fn last_header(cdrom: &mut CdRom) -> Result<SectorHeader, Error> {
+    let res = cdrom.command(Command::GetlocL, &[])?;
+    // This response doesn't start with the drive status
+    SectorHeader::parse(res.data()).ok_or(Error::Command(DriveStatus(0), 0))
+}
+
+/// Checks if the drive's XA-ADPCM buffer is empty.
+pub fn buffer_empty() -> bool {
+    Status::new().xa_adpcm_empty()
+}
+
+/// Starts playing a channel from an XA file.
+///
+/// See [`play_with_data`].
+pub fn play(
+    cdrom: &mut CdRom, lba: u32, sectors: u32, channel: Channel,
+) -> Result<Stream<'_>, Error> {
+    Stream::start(cdrom, lba, sectors, channel, &mut [])
+}
+
+/// Starts playing a channel from an XA file, transferring its interleaved
+/// data sectors into `data`.
+///
+/// This selects `channel` and enables XA-ADPCM and filtering in the drive
+/// mode, keeping the current speed, then reads the file with `ReadS`. XA
+/// files are usually mastered for double speed. The length of `data` must
+/// be a multiple of 2048 bytes and data sectors past its end are discarded.
+/// The previous mode is restored when the stream ends.
+pub fn play_with_data<'a>(
+    cdrom: &'a mut CdRom, lba: u32, sectors: u32, channel: Channel, data: &'a mut [u32],
+) -> Result<Stream<'a>, Error> {
+    Stream::start(cdrom, lba, sectors, channel, data)
+}
+
+/// A handle to an XA-ADPCM stream started by [`play`] or [`play_with_data`].
+///
+/// Dropping the handle stops the stream.
+#[derive(Debug)]
+pub struct Stream<'a> {
+    cdrom: &'a mut CdRom,
+    data: &'a mut [u32],
+    // The mode before the stream started
+    mode: Mode,
+    channel: Channel,
+    end: u32,
+    done: usize,
+    reached_end: bool,
+    result: Option<Result<(), Error>>,
+}
+
+impl<'a> Stream<'a> {
+    fn start(
+        cdrom: &'a mut CdRom, lba: u32, sectors: u32, channel: Channel, data: &'a mut [u32],
+    ) -> Result<Self, Error> {
+        if data.len() % DATA_WORDS != 0 {
+            return Err(Error::InvalidBuffer)
+        }
+        let mode = cdrom.mode();
+        let stream = Stream {
+            cdrom,
+            data,
+            mode,
+            channel,
+            end: lba + sectors,
+            done: 0,
+            reached_end: false,
+            result: None,
+        };
+        let mut xa_mode = mode;
+        xa_mode
+            .set_xa_adpcm(true)
+            .set_xa_filter(true)
+            .set_sector_size(SectorSize::Data);
+        // Dropping the stream restores the mode if this fails
+        set_filter(stream.cdrom, channel)?;
+        stream.cdrom.set_mode(xa_mode)?;
+        stream
+            .cdrom
+            .start_read(Command::ReadS, lba, stream.data, DATA_WORDS)?;
+        Ok(stream)
+    }
+
+    /// Gets the channel being played.
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    /// Switches to another channel in the same file without interrupting
+    /// the stream.
+    pub fn set_channel(&mut self, channel: Channel) -> Result<(), Error> {
+        set_filter(self.cdrom, channel)?;
+        self.channel = channel;
+        Ok(())
+    }
+
+    /// Gets the number of data sectors received so far.
+    pub fn data_sectors_read(&self) -> usize {
+        if self.result.is_some() || self.reached_end {
+            return self.done
+        }
+        with_state(|state| state.read.as_ref().map_or(0, |read| read.done))
+    }
+
+    /// Checks if the stream ended.
+    ///
+    /// The stream reaches its end at the last sector of the file or at a
+    /// sector of the channel with the end of file bit set. The drive is then
+    /// paused and the stream ends once the XA-ADPCM buffer is empty. This
+    /// sends `GetlocL` until the end is reached, so it's best to call it
+    /// about once per frame.
+    pub fn poll(&mut self) -> Poll<Result<(), Error>> {
+        if let Some(res) = self.result {
+            return Poll::Ready(res)
+        }
+        if !self.reached_end {
+            let error = with_state(|state| state.read.as_ref().and_then(|read| read.error));
+            if let Some(err) = error {
+                return Poll::Ready(self.finish(Err(err)))
+            }
+            match last_header(self.cdrom) {
+                Ok(header) => {
+                    let end_of_file = header.end_of_file() && header.xa_channel() == self.channel;
+                    if header.lba() + 1 < self.end && !end_of_file {
+                        return Poll::Pending
+                    }
+                },
+                // No sector header was read yet
+                Err(Error::Command(_, NOT_READY)) => return Poll::Pending,
+                Err(err) => return Poll::Ready(self.finish(Err(err))),
+            }
+            self.reached_end = true;
+            self.done = stop_read();
+            if let Err(err) = self.cdrom.pause() {
+                return Poll::Ready(self.finish(Err(err)))
+            }
+        }
+        if !buffer_empty() {
+            return Poll::Pending
+        }
+        Poll::Ready(self.finish(Ok(())))
+    }
+
+    /// Blocks until the stream ends.
+    pub fn wait(mut self) -> Result<(), Error> {
+        loop {
+            if let Poll::Ready(res) = self.poll() {
+                return res
+            }
+        }
+    }
+
+    /// Stops the stream immediately.
+    pub fn stop(mut self) -> Result<(), Error> {
+        self.finish(Ok(()))
+    }
+
+    /// Stops the drive if necessary and restores the previous mode.
+    fn finish(&mut self, res: Result<(), Error>) -> Result<(), Error> {
+        if let Some(res) = self.result {
+            return res
+        }
+        let mut res = res;
+        if !self.reached_end {
+            self.reached_end = true;
+            self.done = stop_read();
+            res = res.and(self.cdrom.pause());
+        }
+        let res = res.and(self.cdrom.set_mode(self.mode));
+        self.result = Some(res);
+        res
+    }
+}
+
+impl Future for Stream<'_> {
+    type Output = Result<(), Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let res = self.get_mut().poll();
+        if res.is_pending() {
+            cx.waker().wake_by_ref();
+        }
+        res
+    }
+}
+
+impl Drop for Stream<'_> {
+    fn drop(&mut self) {
+        self.finish(Ok(())).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Channel, Msf, SectorHeader};
+
+    #[test_case]
+    fn headers() {
+        let header =
+            SectorHeader::parse(&[0x00, 0x02, 0x16, 0x02, 0x01, 0x03, 0x64, 0x01]).unwrap();
+        assert!(header.msf == Msf::new(0, 2, 16));
+        assert!(header.lba() == 16);
+        assert!(header.xa_channel() == Channel::new(1, 3));
+        assert!(header.audio());
+        assert!(!header.end_of_file());
+        let header =
+            SectorHeader::parse(&[0x00, 0x02, 0x17, 0x02, 0x01, 0x03, 0xE4, 0x01]).unwrap();
+        assert!(header.end_of_file());
+        assert!(SectorHeader::parse(&[0x00, 0x02]).is_none());
+    }
+}
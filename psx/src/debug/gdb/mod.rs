@@ -0,0 +1,468 @@
+//! GDB remote stub
+//!
+//! This implements the GDB remote serial protocol over SIO1. Call [`init`]
+//! with a [`Serial`] connected to the host, then [`breakpoint`] to stop and
+//! wait for GDB to attach with `target remote`. GDB can't interrupt a running
+//! program, so it only gets control back on breakpoints and faults.
+//!
+//! [`breakpoint`]: super::breakpoint
+use super::{set_exception_handler, Context, BREAK};
+use crate::hw::{cop0, Register};
+use crate::sio::Serial;
+use crate::sys::kernel;
+use core::ptr::{addr_of_mut, read_volatile, write_volatile};
+use packet::{checksum, hex_byte, parse_byte, parse_hex, parse_word, split, Response, PACKET_SIZE};
+
+mod packet;
+mod step;
+
+/// The maximum number of software breakpoints.
+pub const MAX_BREAKPOINTS: usize = 32;
+
+/// The number of registers GDB expects for the R3000.
+const GDB_REGS: usize = 72;
+
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+const SIGFPE: u8 = 8;
+const SIGBUS: u8 = 10;
+
+const BREAK_EXCODE: u32 = 9;
+
+const RAM: (u32, u32) = (0x0000_0000, 0x0020_0000);
+const SCRATCHPAD: (u32, u32) = (0x1F80_0000, 0x1F80_0400);
+const BIOS: (u32, u32) = (0x1FC0_0000, 0x1FC8_0000);
+
+#[derive(Clone, Copy, Debug)]
+struct Breakpoint {
+    addr: u32,
+    instr: u32,
+}
+
+impl Breakpoint {
+    fn insert(addr: u32) -> Option<Self> {
+        if addr % 4 != 0 || !accessible(addr, 4, true) {
+            return None
+        }
+        // SAFETY: The address is aligned and in RAM
+        let instr = unsafe { read_volatile(addr as *const u32) };
+        unsafe { write_volatile(addr as *mut u32, BREAK) };
+        Some(Breakpoint { addr, instr })
+    }
+
+    fn remove(self) {
+        // SAFETY: The address was checked when the breakpoint was inserted
+        unsafe { write_volatile(self.addr as *mut u32, self.instr) };
+    }
+}
+
+#[derive(Debug)]
+struct Breakpoints {
+    user: [Option<Breakpoint>; MAX_BREAKPOINTS],
+    step: [Option<Breakpoint>; 2],
+}
+
+impl Breakpoints {
+    const fn new() -> Self {
+        Breakpoints {
+            user: [None; MAX_BREAKPOINTS],
+            step: [None; 2],
+        }
+    }
+
+    fn contains(&self, addr: u32) -> bool {
+        self.user.iter().flatten().any(|bp| bp.addr == addr)
+    }
+
+    fn insert(&mut self, addr: u32) -> bool {
+        if self.contains(addr) {
+            return true
+        }
+        let Some(slot) = self.user.iter_mut().find(|bp| bp.is_none()) else {
+            return false
+        };
+        *slot = Breakpoint::insert(addr);
+        slot.is_some()
+    }
+
+    fn remove(&mut self, addr: u32) -> bool {
+        for slot in &mut self.user {
+            if slot.is_some_and(|bp| bp.addr == addr) {
+                if let Some(bp) = slot.take() {
+                    bp.remove();
+                }
+                return true
+            }
+        }
+        false
+    }
+
+    fn remove_all(&mut self) {
+        for bp in self.user.iter_mut().filter_map(Option::take) {
+            bp.remove();
+        }
+    }
+
+    fn insert_step(&mut self, addr: u32) {
+        // A user breakpoint at the same address already stops execution
+        if self.contains(addr) || self.step.iter().flatten().any(|bp| bp.addr == addr) {
+            return
+        }
+        if let Some(slot) = self.step.iter_mut().find(|bp| bp.is_none()) {
+            *slot = Breakpoint::insert(addr);
+        }
+    }
+
+    /// Removes the single-step breakpoints, returning true if any were set.
+    fn remove_step(&mut self) -> bool {
+        let mut stepped = false;
+        for bp in self.step.iter_mut().filter_map(Option::take) {
+            bp.remove();
+            stepped = true;
+        }
+        stepped
+    }
+}
+
+/// What to do after handling a packet.
+enum Action {
+    Reply,
+    Continue,
+    Step,
+    Detach,
+    Kill,
+}
+
+struct State {
+    serial: Serial,
+    breakpoints: Breakpoints,
+    attached: bool,
+    packet: [u8; PACKET_SIZE],
+    response: Response,
+}
+
+static mut STATE: Option<State> = None;
+
+/// Starts the GDB stub on `serial`.
+///
+/// This calls [`set_exception_handler`], replacing any previous handler.
+pub fn init(serial: Serial) {
+    let mut serial = Some(serial);
+    cop0::Status::new().critical_section(|_| {
+        // SAFETY: We're in a critical section so the exception handler can't
+        // access the state
+        unsafe {
+            *addr_of_mut!(STATE) = serial.take().map(|serial| State {
+                serial,
+                breakpoints: Breakpoints::new(),
+                attached: false,
+                packet: [0; PACKET_SIZE],
+                response: Response::new(),
+            });
+        }
+    });
+    set_exception_handler(on_exception);
+}
+
+fn flush_cache() {
+    // SAFETY: Flushing the instruction cache has no side effects on the
+    // program's state
+    unsafe { kernel::psx_flush_cache() }
+}
+
+/// Checks if `len` bytes at `addr` may be accessed by GDB.
+fn accessible(addr: u32, len: u32, write: bool) -> bool {
+    // Only allow KUSEG, KSEG0 and KSEG1
+    if addr >= 0xC000_0000 {
+        return false
+    }
+    let start = addr & 0x1FFF_FFFF;
+    let Some(end) = start.checked_add(len) else {
+        return false
+    };
+    let within = |(lo, hi): (u32, u32)| start >= lo && end <= hi;
+    within(RAM) || within(SCRATCHPAD) || (!write && within(BIOS))
+}
+
+fn signal(ctx: &Context) -> u8 {
+    match ctx.excode_bits() {
+        // Address and bus errors
+        4..=7 => SIGBUS,
+        // Reserved instruction and coprocessor unusable
+        10 | 11 => SIGILL,
+        // Arithmetic overflow
+        12 => SIGFPE,
+        _ => SIGTRAP,
+    }
+}
+
+fn on_exception(ctx: &mut Context) {
+    // SAFETY: The exception handler runs with interrupts disabled and `init`
+    // only modifies the state in a critical section
+    let Some(state) = (unsafe { (*addr_of_mut!(STATE)).as_mut() }) else {
+        return
+    };
+    let stepped = state.breakpoints.remove_step();
+    flush_cache();
+
+    // The exception happened in the delay slot after the branch at `epc`
+    let epc = ctx.pc;
+    let delay_slot = ctx.branch_delay_slot();
+    if delay_slot {
+        ctx.pc = epc.wrapping_add(4);
+    }
+    let signal = signal(ctx);
+    let is_break = ctx.excode_bits() == BREAK_EXCODE;
+    if is_break && !stepped && !delay_slot && !state.breakpoints.contains(ctx.pc) {
+        // Skip `break` instructions compiled into the program
+        ctx.pc = ctx.pc.wrapping_add(4);
+    }
+
+    if state.attached {
+        state.response.clear().push(b"S").push_byte(signal);
+        state.send();
+    }
+    let action = loop {
+        let len = state.receive();
+        state.attached = true;
+        let packet = &state.packet[..len];
+        let response = state.response.clear();
+        match handle(packet, ctx, &mut state.breakpoints, response, signal) {
+            Action::Reply => {
+                if state.response.overflowed() {
+                    state.response.clear().push(b"E01");
+                }
+                state.send()
+            },
+            action => break action,
+        }
+    };
+    if let Action::Detach = action {
+        state.response.clear().push(b"OK");
+        state.send();
+    }
+    if let Action::Detach | Action::Kill = action {
+        state.breakpoints.remove_all();
+        state.attached = false;
+    }
+
+    // Resume at the branch if the delay slot wasn't skipped
+    if delay_slot && ctx.pc == epc.wrapping_add(4) {
+        ctx.pc = epc;
+    }
+    if matches!(action, Action::Step) && accessible(ctx.pc, 4, false) {
+        // SAFETY: The address was checked
+        let instr = unsafe { read_volatile(ctx.pc as *const u32) };
+        let next = step::next_pcs(instr, ctx.pc, |reg| ctx.gpr[reg as usize]);
+        for addr in next.into_iter().flatten() {
+            state.breakpoints.insert_step(addr);
+        }
+    }
+    flush_cache();
+}
+
+/// Handles a packet, writing its response to `res`.
+fn handle(
+    packet: &[u8], ctx: &mut Context, breakpoints: &mut Breakpoints, res: &mut Response, signal: u8,
+) -> Action {
+    let Some((&cmd, args)) = packet.split_first() else {
+        return Action::Reply
+    };
+    match cmd {
+        b'?' => {
+            res.push(b"S").push_byte(signal);
+        },
+        b'g' => {
+            for idx in 0..GDB_REGS {
+                match ctx.get(idx) {
+                    Some(value) => res.push_word(value),
+                    None => res.push(b"xxxxxxxx"),
+                };
+            }
+        },
+        b'G' => {
+            let ok = args.len() % 8 == 0 && args.chunks(8).all(|word| parse_word(word).is_some());
+            if ok {
+                // Registers which aren't available are ignored
+                for (idx, word) in args.chunks(8).enumerate() {
+                    ctx.set(idx, parse_word(word).unwrap_or(0));
+                }
+            }
+            res.push(if ok { b"OK" } else { b"E01" });
+        },
+        b'p' => match parse_hex(args).map(|idx| idx as usize) {
+            Some(idx) if idx < GDB_REGS => {
+                match ctx.get(idx) {
+                    Some(value) => res.push_word(value),
+                    None => res.push(b"xxxxxxxx"),
+                };
+            },
+            _ => {
+                res.push(b"E01");
+            },
+        },
+        b'P' => {
+            let ok = split(args, b'=')
+                .and_then(|(idx, value)| Some((parse_hex(idx)? as usize, parse_word(value)?)))
+                .map_or(false, |(idx, value)| ctx.set(idx, value) || idx < GDB_REGS);
+            res.push(if ok { b"OK" } else { b"E01" });
+        },
+        b'm' => match parse_range(args) {
+            Some((addr, len))
+                if len as usize <= PACKET_SIZE / 2 && accessible(addr, len, false) =>
+            {
+                for i in 0..len {
+                    // SAFETY: The range was checked
+                    res.push_byte(unsafe { read_volatile((addr + i) as *const u8) });
+                }
+            },
+            _ => {
+                res.push(b"E01");
+            },
+        },
+        b'M' => {
+            let ok = split(args, b':').map_or(false, |(range, data)| match parse_range(range) {
+                Some((addr, len))
+                    if data.len() == 2 * len as usize && accessible(addr, len, true) =>
+                {
+                    let bytes = data.chunks(2).map(parse_byte);
+                    if bytes.clone().any(|byte| byte.is_none()) {
+                        return false
+                    }
+                    for (i, byte) in bytes.flatten().enumerate() {
+                        // SAFETY: The range was checked
+                        unsafe { write_volatile((addr + i as u32) as *mut u8, byte) };
+                    }
+                    flush_cache();
+                    true
+                },
+                _ => false,
+            });
+            res.push(if ok { b"OK" } else { b"E01" });
+        },
+        b'Z' | b'z' => {
+            // Only software breakpoints are supported
+            if let Some(rest) = args.strip_prefix(b"0,") {
+                let addr = split(rest, b',').and_then(|(addr, _kind)| parse_hex(addr));
+                let ok = addr.map_or(false, |addr| {
+                    if cmd == b'Z' {
+                        breakpoints.insert(addr)
+                    } else {
+                        breakpoints.remove(addr)
+                    }
+                });
+                res.push(if ok { b"OK" } else { b"E01" });
+            }
+        },
+        b'c' | b's' => {
+            if let Some(addr) = parse_hex(args) {
+                ctx.pc = addr;
+            }
+            return if cmd == b'c' {
+                Action::Continue
+            } else {
+                Action::Step
+            }
+        },
+        b'D' => return Action::Detach,
+        b'k' => return Action::Kill,
+        b'H' => {
+            res.push(b"OK");
+        },
+        b'q' => {
+            if args.starts_with(b"Supported") {
+                // The size is in hex
+                res.push(b"PacketSize=").push_hex(PACKET_SIZE as u32);
+            } else if args == b"Attached" {
+                res.push(b"1");
+            }
+        },
+        _ => {},
+    }
+    Action::Reply
+}
+
+/// Parses an `addr,length` pair.
+fn parse_range(args: &[u8]) -> Option<(u32, u32)> {
+    let (addr, len) = split(args, b',')?;
+    Some((parse_hex(addr)?, parse_hex(len)?))
+}
+
+fn read_byte(serial: &mut Serial) -> u8 {
+    loop {
+        // Errors mean a byte was lost, which the checksum catches
+        if let Ok(byte) = serial.read_byte() {
+            return byte
+        }
+    }
+}
+
+impl State {
+    /// Receives a packet into `self.packet`, returning its length.
+    fn receive(&mut self) -> usize {
+        loop {
+            while read_byte(&mut self.serial) != b'$' {}
+            let mut len = 0;
+            let mut overflow = false;
+            loop {
+                match read_byte(&mut self.serial) {
+                    b'#' => break,
+                    // Restart if the packet was interrupted by another one
+                    b'$' => {
+                        len = 0;
+                        overflow = false;
+                    },
+                    byte if len < PACKET_SIZE => {
+                        self.packet[len] = byte;
+                        len += 1;
+                    },
+                    _ => overflow = true,
+                }
+            }
+            let sum = [read_byte(&mut self.serial), read_byte(&mut self.serial)];
+            if !overflow && parse_byte(&sum) == Some(checksum(&self.packet[..len])) {
+                self.serial.write_byte(b'+');
+                return len
+            }
+            self.serial.write_byte(b'-');
+        }
+    }
+
+    /// Sends `self.response`, resending it until GDB acknowledges it.
+    fn send(&mut self) {
+        let data = self.response.data();
+        let sum = hex_byte(checksum(data));
+        loop {
+            self.serial.write_byte(b'$');
+            self.serial.write(data);
+            self.serial.write_byte(b'#');
+            self.serial.write(&sum);
+            self.serial.flush();
+            loop {
+                match read_byte(&mut self.serial) {
+                    b'+' => return,
+                    b'-' => break,
+                    _ => {},
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::accessible;
+
+    #[test_case]
+    fn memory_ranges() {
+        assert!(accessible(0x8001_0000, 4, true));
+        assert!(accessible(0xA01F_FFFC, 4, true));
+        assert!(!accessible(0x801F_FFFE, 4, false));
+        assert!(accessible(0x1F80_0000, 0x400, true));
+        assert!(!accessible(0x1F80_0400, 1, false));
+        assert!(accessible(0xBFC0_0000, 4, false));
+        assert!(!accessible(0xBFC0_0000, 4, true));
+        assert!(!accessible(0xFFFE_0130, 4, false));
+        assert!(!accessible(0x8000_0000, u32::MAX, false));
+    }
+}
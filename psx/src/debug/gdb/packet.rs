@@ -0,0 +1,187 @@
+use super::GDB_REGS;
+
+/// The size of the packet buffers in bytes, which fits a `G` packet with every
+/// register.
+pub const PACKET_SIZE: usize = (1 + GDB_REGS * 8).next_multiple_of(0x40);
+
+/// Computes the modulo 256 checksum of a packet's data.
+pub fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, &byte| sum.wrapping_add(byte))
+}
+
+/// Gets the value of a hex digit.
+pub fn hex_digit(digit: u8) -> Option<u8> {
+    match digit {
+        b'0'..=b'9' => Some(digit - b'0'),
+        b'a'..=b'f' => Some(digit - b'a' + 10),
+        b'A'..=b'F' => Some(digit - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Parses a big-endian hex number of up to 8 digits.
+pub fn parse_hex(digits: &[u8]) -> Option<u32> {
+    if digits.is_empty() || digits.len() > 8 {
+        return None
+    }
+    digits
+        .iter()
+        .try_fold(0, |acc, &d| Some(acc << 4 | hex_digit(d)? as u32))
+}
+
+/// Parses a hex-encoded byte.
+pub fn parse_byte(digits: &[u8]) -> Option<u8> {
+    match digits {
+        &[hi, lo] => Some(hex_digit(hi)? << 4 | hex_digit(lo)?),
+        _ => None,
+    }
+}
+
+/// Parses a register value encoded as 8 hex digits in little-endian byte
+/// order.
+pub fn parse_word(digits: &[u8]) -> Option<u32> {
+    if digits.len() != 8 {
+        return None
+    }
+    let mut bytes = [0; 4];
+    for (byte, pair) in bytes.iter_mut().zip(digits.chunks(2)) {
+        *byte = parse_byte(pair)?;
+    }
+    Some(u32::from_le_bytes(bytes))
+}
+
+/// Splits `data` at the first `sep`, returning `None` if it's missing.
+pub fn split(data: &[u8], sep: u8) -> Option<(&[u8], &[u8])> {
+    let idx = data.iter().position(|&b| b == sep)?;
+    Some((&data[..idx], &data[idx + 1..]))
+}
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Encodes a byte as two hex digits.
+pub fn hex_byte(byte: u8) -> [u8; 2] {
+    [
+        HEX_DIGITS[(byte >> 4) as usize],
+        HEX_DIGITS[(byte & 0xF) as usize],
+    ]
+}
+
+/// A buffer for building a packet's data.
+#[derive(Debug)]
+pub struct Response {
+    buf: [u8; PACKET_SIZE],
+    len: usize,
+    overflow: bool,
+}
+
+impl Response {
+    /// Creates an empty response.
+    pub const fn new() -> Self {
+        Response {
+            buf: [0; PACKET_SIZE],
+            len: 0,
+            overflow: false,
+        }
+    }
+
+    /// Gets the response's data.
+    pub fn data(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Checks if bytes were dropped because the buffer was full.
+    pub fn overflowed(&self) -> bool {
+        self.overflow
+    }
+
+    /// Empties the response.
+    pub fn clear(&mut self) -> &mut Self {
+        self.len = 0;
+        self.overflow = false;
+        self
+    }
+
+    /// Appends raw bytes, dropping them and marking the response as
+    /// overflowed if they don't fit.
+    pub fn push(&mut self, bytes: &[u8]) -> &mut Self {
+        match self.buf.get_mut(self.len..self.len + bytes.len()) {
+            Some(dst) => {
+                dst.copy_from_slice(bytes);
+                self.len += bytes.len();
+            },
+            None => self.overflow = true,
+        }
+        self
+    }
+
+    /// Appends a byte as two hex digits.
+    pub fn push_byte(&mut self, byte: u8) -> &mut Self {
+        self.push(&hex_byte(byte))
+    }
+
+    /// Appends a big-endian hex number without leading zeros.
+    pub fn push_hex(&mut self, value: u32) -> &mut Self {
+        let digits = (32 - value.leading_zeros()).div_ceil(4).max(1);
+        for i in (0..digits).rev() {
+            self.push(&[HEX_DIGITS[((value >> (4 * i)) & 0xF) as usize]]);
+        }
+        self
+    }
+
+    /// Appends a register value as 8 hex digits in little-endian byte order.
+    pub fn push_word(&mut self, word: u32) -> &mut Self {
+        for byte in word.to_le_bytes() {
+            self.push_byte(byte);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{checksum, parse_byte, parse_hex, parse_word, split, Response, GDB_REGS,
+                PACKET_SIZE};
+
+    #[test_case]
+    fn parsing() {
+        assert!(checksum(b"OK") == 0x9A);
+        assert!(checksum(b"") == 0);
+        assert!(parse_hex(b"80010000") == Some(0x8001_0000));
+        assert!(parse_hex(b"1F") == Some(0x1F));
+        assert!(parse_hex(b"").is_none());
+        assert!(parse_hex(b"123456789").is_none());
+        assert!(parse_hex(b"12g4").is_none());
+        assert!(parse_byte(b"a5") == Some(0xA5));
+        assert!(parse_word(b"78563412") == Some(0x1234_5678));
+        assert!(split(b"80010000,4", b',') == Some((&b"80010000"[..], &b"4"[..])));
+        assert!(split(b"80010000", b',').is_none());
+    }
+
+    #[test_case]
+    fn responses() {
+        let mut res = Response::new();
+        res.push(b"S").push_byte(5);
+        assert!(res.data() == b"S05");
+        res.clear().push_word(0x1234_5678);
+        assert!(res.data() == b"78563412");
+        fuzz!(|word: u32| {
+            res.clear().push_word(word);
+            assert!(parse_word(res.data()) == Some(word));
+        });
+        res.clear().push_hex(0x280).push(b",").push_hex(0);
+        assert!(res.data() == b"280,0");
+    }
+
+    #[test_case]
+    fn overflow() {
+        let mut res = Response::new();
+        for _ in 0..GDB_REGS {
+            res.push_word(0);
+        }
+        assert!(!res.overflowed());
+        res.push(&[0; PACKET_SIZE]);
+        assert!(res.overflowed());
+        assert!(res.data().len() == GDB_REGS * 8);
+        assert!(!res.clear().overflowed());
+    }
+}
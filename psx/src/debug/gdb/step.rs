@@ -0,0 +1,70 @@
+/// Gets the addresses the instruction at `pc` may continue execution at.
+///
+/// `reg` gets the value of a general purpose register. Jumps and branches
+/// return the instruction after their delay slot or the target.
+pub fn next_pcs(instr: u32, pc: u32, reg: impl Fn(u32) -> u32) -> [Option<u32>; 2] {
+    let opcode = instr >> 26;
+    let rs = (instr >> 21) & 0x1F;
+    let offset = (instr as i16 as i32 as u32) << 2;
+    let branch_target = pc.wrapping_add(4).wrapping_add(offset);
+    let after_delay_slot = pc.wrapping_add(8);
+    match opcode {
+        // SPECIAL
+        0x00 => match instr & 0x3F {
+            // JR, JALR
+            0x08 | 0x09 => [Some(reg(rs)), None],
+            _ => [Some(pc.wrapping_add(4)), None],
+        },
+        // J, JAL
+        0x02 | 0x03 => {
+            let target = (pc.wrapping_add(4) & 0xF000_0000) | ((instr & 0x03FF_FFFF) << 2);
+            [Some(target), None]
+        },
+        // REGIMM, BEQ, BNE, BLEZ, BGTZ
+        0x01 | 0x04..=0x07 => [Some(after_delay_slot), Some(branch_target)],
+        // BCzF, BCzT
+        0x10..=0x13 if rs == 0x08 => [Some(after_delay_slot), Some(branch_target)],
+        _ => [Some(pc.wrapping_add(4)), None],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::next_pcs;
+
+    const PC: u32 = 0x8001_0000;
+
+    fn reg(idx: u32) -> u32 {
+        idx * 0x100
+    }
+
+    #[test_case]
+    fn sequential() {
+        // addiu $v0, $zero, 1
+        assert!(next_pcs(0x2402_0001, PC, reg) == [Some(PC + 4), None]);
+        // nop
+        assert!(next_pcs(0, PC, reg) == [Some(PC + 4), None]);
+    }
+
+    #[test_case]
+    fn jumps() {
+        // j 0x80020000
+        assert!(next_pcs(0x0800_8000, PC, reg) == [Some(0x8002_0000), None]);
+        // jal 0x80020000
+        assert!(next_pcs(0x0C00_8000, PC, reg) == [Some(0x8002_0000), None]);
+        // jr $ra
+        assert!(next_pcs(0x03E0_0008, PC, reg) == [Some(reg(31)), None]);
+        // jalr $t0
+        assert!(next_pcs(0x0100_F809, PC, reg) == [Some(reg(8)), None]);
+    }
+
+    #[test_case]
+    fn branches() {
+        // beq $zero, $zero, -1
+        assert!(next_pcs(0x1000_FFFF, PC, reg) == [Some(PC + 8), Some(PC)]);
+        // bne $a0, $a1, 4
+        assert!(next_pcs(0x1485_0004, PC, reg) == [Some(PC + 8), Some(PC + 20)]);
+        // bgez $a0, 2
+        assert!(next_pcs(0x0481_0002, PC, reg) == [Some(PC + 8), Some(PC + 12)]);
+    }
+}
@@ -0,0 +1,291 @@
+//! Debugging support
+//!
+//! [`set_exception_handler`] hooks the general exception vector to call a
+//! function with the full register [`Context`] on breakpoints and faults.
+//! Interrupts and syscalls are still handled by the BIOS.
+use crate::hw::cop0::{Cause, Excode};
+use crate::hw::{cop0, Register};
+use crate::sys::kernel;
+use core::arch::{asm, global_asm};
+use core::mem::size_of;
+use core::ptr::{addr_of_mut, read_volatile, write_volatile};
+
+pub mod gdb;
+
+/// The encoding of the `break` instruction.
+pub const BREAK: u32 = 0x0000_000D;
+
+/// The address of the general exception vector.
+const EXCEPTION_VECTOR: usize = 0x8000_0080;
+/// The number of instructions in the exception vector.
+const VECTOR_LEN: usize = 4;
+/// The size of the stack used by the exception handler in words.
+const STACK_WORDS: usize = 1024;
+
+// lui $k0, 0
+const LUI_K0: u32 = 0x3C1A_0000;
+// ori $k0, $k0, 0
+const ORI_K0: u32 = 0x375A_0000;
+// jr $k0
+const JR_K0: u32 = 0x0340_0008;
+const NOP: u32 = 0;
+
+/// The registers saved by the exception handler.
+///
+/// This is laid out like the registers in the GDB remote protocol. Changes
+/// made by the handler to the general purpose registers, `lo`, `hi` and `pc`
+/// take effect when it returns.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Context {
+    /// The general purpose registers. `k0` and `k1` are clobbered by the
+    /// exception handler so they aren't saved.
+    pub gpr: [u32; 32],
+    /// The cop0 status register.
+    pub sr: u32,
+    /// The multiply/divide low result register.
+    pub lo: u32,
+    /// The multiply/divide high result register.
+    pub hi: u32,
+    /// The cop0 bad virtual address register.
+    pub bad_vaddr: u32,
+    /// The cop0 cause register.
+    pub cause: u32,
+    /// The address execution resumes at.
+    pub pc: u32,
+}
+
+/// The number of registers in a [`Context`].
+pub const NUM_REGS: usize = size_of::<Context>() / size_of::<u32>();
+
+impl Context {
+    /// Gets the exception cause code.
+    pub fn excode(&self) -> Excode {
+        Cause::skip_load().assign(self.cause).excode()
+    }
+
+    /// Gets the raw exception cause code.
+    pub fn excode_bits(&self) -> u32 {
+        (self.cause >> 2) & 0x1F
+    }
+
+    /// Checks if the exception happened in a branch delay slot.
+    ///
+    /// In that case `pc` initially points to the branch.
+    pub fn branch_delay_slot(&self) -> bool {
+        Cause::skip_load().assign(self.cause).branch_delay_slot()
+    }
+
+    /// Gets a register by its index in the GDB remote protocol.
+    pub fn get(&self, idx: usize) -> Option<u32> {
+        self.as_words().get(idx).copied()
+    }
+
+    /// Sets a register by its index in the GDB remote protocol, returning
+    /// false if the index is invalid. Writes to `r0` are ignored.
+    pub fn set(&mut self, idx: usize, value: u32) -> bool {
+        if idx >= NUM_REGS {
+            return false
+        }
+        if idx != 0 {
+            self.as_words_mut()[idx] = value;
+        }
+        true
+    }
+
+    fn as_words(&self) -> &[u32; NUM_REGS] {
+        // SAFETY: Context is repr(C) and only contains u32s
+        unsafe { &*(self as *const Context).cast() }
+    }
+
+    fn as_words_mut(&mut self) -> &mut [u32; NUM_REGS] {
+        // SAFETY: Context is repr(C) and only contains u32s
+        unsafe { &mut *(self as *mut Context).cast() }
+    }
+}
+
+static mut CONTEXT: Context = Context {
+    gpr: [0; 32],
+    sr: 0,
+    lo: 0,
+    hi: 0,
+    bad_vaddr: 0,
+    cause: 0,
+    pc: 0,
+};
+static mut STACK: [u32; STACK_WORDS] = [0; STACK_WORDS];
+static mut BIOS_VECTOR: [u32; VECTOR_LEN] = [NOP; VECTOR_LEN];
+static mut HANDLER: Option<fn(&mut Context)> = None;
+static mut INSTALLED: bool = false;
+
+// Interrupts (excode 0) and syscalls (excode 8) go to the BIOS's handler
+// through the copy of its vector. Everything else saves the registers in
+// `CONTEXT`, switches to `STACK` and calls `handle_exception`.
+global_asm! {
+    ".section .text.psx_debug_exception
+     .globl psx_debug_exception
+     .set noreorder
+     .set noat
+     psx_debug_exception:
+         mfc0 $k0, $13
+         nop
+         andi $k0, $k0, 0x7C
+         beqz $k0, 1f
+         addiu $k0, $k0, -0x20
+         bnez $k0, 2f
+         nop
+     1:
+         la $k0, {bios_vector}
+         jr $k0
+         nop
+     2:
+         la $k0, {context}
+         sw $1, 4($k0)
+         sw $2, 8($k0)
+         sw $3, 12($k0)
+         sw $4, 16($k0)
+         sw $5, 20($k0)
+         sw $6, 24($k0)
+         sw $7, 28($k0)
+         sw $8, 32($k0)
+         sw $9, 36($k0)
+         sw $10, 40($k0)
+         sw $11, 44($k0)
+         sw $12, 48($k0)
+         sw $13, 52($k0)
+         sw $14, 56($k0)
+         sw $15, 60($k0)
+         sw $16, 64($k0)
+         sw $17, 68($k0)
+         sw $18, 72($k0)
+         sw $19, 76($k0)
+         sw $20, 80($k0)
+         sw $21, 84($k0)
+         sw $22, 88($k0)
+         sw $23, 92($k0)
+         sw $24, 96($k0)
+         sw $25, 100($k0)
+         sw $28, 112($k0)
+         sw $29, 116($k0)
+         sw $30, 120($k0)
+         sw $31, 124($k0)
+         mfc0 $8, $12
+         nop
+         sw $8, 128($k0)
+         mflo $8
+         sw $8, 132($k0)
+         mfhi $8
+         sw $8, 136($k0)
+         mfc0 $8, $8
+         nop
+         sw $8, 140($k0)
+         mfc0 $8, $13
+         nop
+         sw $8, 144($k0)
+         mfc0 $8, $14
+         nop
+         sw $8, 148($k0)
+
+         la $sp, {stack}
+         addiu $sp, $sp, {stack_size}
+         jal {handler}
+         move $a0, $k0
+
+         la $k0, {context}
+         lw $8, 132($k0)
+         nop
+         mtlo $8
+         lw $8, 136($k0)
+         nop
+         mthi $8
+         lw $1, 4($k0)
+         lw $2, 8($k0)
+         lw $3, 12($k0)
+         lw $4, 16($k0)
+         lw $5, 20($k0)
+         lw $6, 24($k0)
+         lw $7, 28($k0)
+         lw $8, 32($k0)
+         lw $9, 36($k0)
+         lw $10, 40($k0)
+         lw $11, 44($k0)
+         lw $12, 48($k0)
+         lw $13, 52($k0)
+         lw $14, 56($k0)
+         lw $15, 60($k0)
+         lw $16, 64($k0)
+         lw $17, 68($k0)
+         lw $18, 72($k0)
+         lw $19, 76($k0)
+         lw $20, 80($k0)
+         lw $21, 84($k0)
+         lw $22, 88($k0)
+         lw $23, 92($k0)
+         lw $24, 96($k0)
+         lw $25, 100($k0)
+         lw $28, 112($k0)
+         lw $29, 116($k0)
+         lw $30, 120($k0)
+         lw $31, 124($k0)
+         lw $k1, 148($k0)
+         nop
+         jr $k1
+         .long 0x42000010 # rfe
+     .set at
+     .set reorder",
+    bios_vector = sym BIOS_VECTOR,
+    context = sym CONTEXT,
+    stack = sym STACK,
+    // Leave room for the argument save area
+    stack_size = const STACK_WORDS * 4 - 16,
+    handler = sym handle_exception,
+}
+
+extern "C" {
+    fn psx_debug_exception();
+}
+
+extern "C" fn handle_exception(ctx: &mut Context) {
+    // SAFETY: The handler is only modified in critical sections
+    if let Some(handler) = unsafe { HANDLER } {
+        handler(ctx);
+    }
+}
+
+/// Calls `handler` with the saved registers on exceptions other than
+/// interrupts and syscalls.
+///
+/// The first call hooks the general exception vector. `handler` runs with
+/// interrupts disabled on a separate stack and execution resumes at the
+/// context's `pc` when it returns.
+pub fn set_exception_handler(handler: fn(&mut Context)) {
+    cop0::Status::new().critical_section(|_| {
+        // SAFETY: We're in a critical section so the exception handler can't
+        // run while the vector is being modified
+        unsafe {
+            HANDLER = Some(handler);
+            if INSTALLED {
+                return
+            }
+            INSTALLED = true;
+            let vector = EXCEPTION_VECTOR as *mut u32;
+            let bios_vector = addr_of_mut!(BIOS_VECTOR).cast::<u32>();
+            for i in 0..VECTOR_LEN {
+                write_volatile(bios_vector.add(i), read_volatile(vector.add(i)));
+            }
+            let addr = psx_debug_exception as usize as u32;
+            let jump = [LUI_K0 | addr >> 16, ORI_K0 | addr & 0xFFFF, JR_K0, NOP];
+            for (i, instr) in jump.into_iter().enumerate() {
+                write_volatile(vector.add(i), instr);
+            }
+            kernel::psx_flush_cache();
+        }
+    });
+}
+
+/// Executes a `break` instruction to stop in the exception handler.
+#[inline(always)]
+pub fn breakpoint() {
+    // SAFETY: `break` only raises an exception
+    unsafe { asm!("break") }
+}
@@ -145,6 +145,21 @@ impl<A: MemoryAddress, B: BlockControl, C: ChannelControl> Channel<A, B, C> {
     /// This blocks if the function `f` returns before the transfer completes.
     /// Returns `f`'s return value or `None` if the buffer is too large.
     pub fn send_and<F: FnOnce() -> R, R>(&mut self, block: &[u32], f: F) -> Result<R> {
+        self.transfer_and(block, Direction::FromMemory, f)
+    }
+
+    /// Receives a buffer through a DMA channel in single-block mode and call
+    /// `f` while the transfer completes.
+    ///
+    /// This blocks if the function `f` returns before the transfer completes.
+    /// Returns `f`'s return value or an error if the buffer is too large.
+    pub fn receive_and<F: FnOnce() -> R, R>(&mut self, block: &mut [u32], f: F) -> Result<R> {
+        self.transfer_and(block, Direction::ToMemory, f)
+    }
+
+    fn transfer_and<F: FnOnce() -> R, R>(
+        &mut self, block: &[u32], direction: Direction, f: F,
+    ) -> Result<R> {
         // If the block is empty, just call `f` and return
         let addr = match self.block_address(block) {
             Some(addr) => addr,
@@ -155,6 +170,7 @@ impl<A: MemoryAddress, B: BlockControl, C: ChannelControl> Channel<A, B, C> {
         self.bcr.set_block(block.len())?.store();
         // Start the DMA transfer
         self.control
+            .set_direction(direction)
             .set_mode(TransferMode::Immediate)
             .start()
             .store();
@@ -178,6 +194,23 @@ impl<A: MemoryAddress, B: BlockControl, C: ChannelControl> Channel<A, B, C> {
     /// Returns `f`'s return value or `None` if the buffer is too large.
     pub fn send_blocks_and<F: FnOnce() -> R, R>(
         &mut self, block: &[u32], size: usize, f: F,
+    ) -> Result<R> {
+        self.transfer_blocks_and(block, size, Direction::FromMemory, f)
+    }
+
+    /// Receives a buffer through a DMA channel in multi-block mode and call
+    /// `f` while the transfer completes.
+    ///
+    /// This blocks if the function `f` returns before the transfer completes.
+    /// Returns `f`'s return value or an error if the buffer is too large.
+    pub fn receive_blocks_and<F: FnOnce() -> R, R>(
+        &mut self, block: &mut [u32], size: usize, f: F,
+    ) -> Result<R> {
+        self.transfer_blocks_and(block, size, Direction::ToMemory, f)
+    }
+
+    fn transfer_blocks_and<F: FnOnce() -> R, R>(
+        &mut self, block: &[u32], size: usize, direction: Direction, f: F,
     ) -> Result<R> {
         // If the block is empty, just call `f` and return
         let addr = match self.block_address(block) {
@@ -200,7 +233,11 @@ impl<A: MemoryAddress, B: BlockControl, C: ChannelControl> Channel<A, B, C> {
         };
         // This will never fail
         self.bcr.set_block(block_len)?.store();
-        self.control.start().store();
+        self.control
+            .set_direction(direction)
+            .set_mode(TransferMode::Request)
+            .start()
+            .store();
         // This acts like a compiler fence
         unsafe {
             asm!("nop");
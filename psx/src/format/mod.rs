@@ -1,3 +1,4 @@
 //! Support for parsing various file formats
 pub mod obj;
 pub mod tim;
+pub mod vag;
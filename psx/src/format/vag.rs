@@ -0,0 +1,156 @@
+//! VAG file parsing
+//!
+//! VAG files contain a single mono sample encoded as SPU ADPCM data with a
+//! big-endian header.
+
+#[doc(hidden)]
+pub const MAGIC: [u8; 4] = *b"VAGp";
+/// The size of a VAG file's header in bytes.
+pub const HEADER_SIZE: usize = 0x30;
+/// The size of an SPU ADPCM block in bytes.
+pub const BLOCK_SIZE: usize = 0x10;
+/// The highest sample rate the SPU can play back at.
+const MAX_SAMPLE_RATE: u32 = 4 * 44_100;
+
+const DATA_SIZE_OFFSET: usize = 0x0C;
+const SAMPLE_RATE_OFFSET: usize = 0x10;
+const NAME_OFFSET: usize = 0x20;
+const NAME_LEN: usize = 16;
+
+/// Error for VAG file parsing.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error {
+    /// The file is smaller than the VAG header.
+    MissingHeader,
+    /// The file doesn't start with the VAG magic bytes.
+    InvalidMagic,
+    /// The sample rate is zero or too high for the SPU.
+    InvalidSampleRate,
+    /// The data size isn't a non-zero multiple of the ADPCM block size or is
+    /// larger than the file.
+    InvalidSize,
+}
+
+/// Validates and includes a [`VAG`][`crate::format::vag::VAG`] file.
+///
+/// The ADPCM data is stored word-aligned so it can be uploaded to SPU RAM
+/// through DMA.
+#[macro_export]
+macro_rules! include_vag {
+    ($file:literal) => {{
+        use core::mem::transmute;
+        use core::slice::from_raw_parts;
+        use $crate::format::vag::{Error, VAG};
+
+        const VAG_FILE: VAG<'static> = match VAG::parse(include_bytes!($file)) {
+            Ok(vag) => vag,
+            Err(Error::MissingHeader) => panic!("VAG file is missing its header"),
+            Err(Error::InvalidMagic) => panic!("VAG file has invalid magic bytes"),
+            Err(Error::InvalidSampleRate) => panic!("VAG file has invalid sample rate"),
+            Err(Error::InvalidSize) => panic!("VAG file has invalid data size"),
+        };
+        const DATA_SIZE: usize = VAG_FILE.data.len();
+        static DATA: [u32; DATA_SIZE / 4] = {
+            let mut data = [0u8; DATA_SIZE];
+            let mut i = 0;
+            while i < DATA_SIZE {
+                data[i] = VAG_FILE.data[i];
+                i += 1;
+            }
+            unsafe { transmute(data) }
+        };
+        VAG {
+            sample_rate: VAG_FILE.sample_rate,
+            name: VAG_FILE.name,
+            data: unsafe { from_raw_parts(DATA.as_ptr().cast::<u8>(), DATA_SIZE) },
+        }
+    }};
+}
+
+/// A reference to a VAG file in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VAG<'a> {
+    /// The sample rate in Hz.
+    pub sample_rate: u32,
+    /// The sample's name padded with null bytes.
+    pub name: [u8; NAME_LEN],
+    /// The SPU ADPCM data.
+    pub data: &'a [u8],
+}
+
+const fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+impl<'a> VAG<'a> {
+    /// Parses a VAG file.
+    pub const fn parse(bytes: &'a [u8]) -> Result<Self, Error> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(Error::MissingHeader)
+        }
+        if read_u32(bytes, 0) != u32::from_be_bytes(MAGIC) {
+            return Err(Error::InvalidMagic)
+        }
+        let sample_rate = read_u32(bytes, SAMPLE_RATE_OFFSET);
+        if sample_rate == 0 || sample_rate > MAX_SAMPLE_RATE {
+            return Err(Error::InvalidSampleRate)
+        }
+        let size = read_u32(bytes, DATA_SIZE_OFFSET) as usize;
+        if size == 0 || size % BLOCK_SIZE != 0 || size > bytes.len() - HEADER_SIZE {
+            return Err(Error::InvalidSize)
+        }
+        let mut name = [0; NAME_LEN];
+        let mut i = 0;
+        while i < NAME_LEN {
+            name[i] = bytes[NAME_OFFSET + i];
+            i += 1;
+        }
+        let (_, data) = bytes.split_at(HEADER_SIZE);
+        let (data, _) = data.split_at(size);
+        Ok(VAG {
+            sample_rate,
+            name,
+            data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, HEADER_SIZE, VAG};
+
+    const BEEP: &[u8] = include_bytes!("../../test_files/beep.vag");
+
+    #[test_case]
+    fn include_beep() {
+        let beep = include_vag!("../../test_files/beep.vag");
+        assert!(beep.sample_rate == 22_050);
+        assert!(beep.name.starts_with(b"beep\0"));
+        assert!(beep.data.len() == 0x40);
+        assert!(beep.data.as_ptr() as usize % 4 == 0);
+        assert!(beep.data == &BEEP[HEADER_SIZE..]);
+    }
+
+    #[test_case]
+    fn parse_errors() {
+        assert!(VAG::parse(&BEEP[..HEADER_SIZE - 1]) == Err(Error::MissingHeader));
+        assert!(VAG::parse(&BEEP[..HEADER_SIZE + 0x30]) == Err(Error::InvalidSize));
+        let mut file = [0; 0x70];
+        file.copy_from_slice(BEEP);
+        file[0] = b'X';
+        assert!(VAG::parse(&file) == Err(Error::InvalidMagic));
+        file[0] = b'V';
+        file[0x10..0x14].copy_from_slice(&0u32.to_be_bytes());
+        assert!(VAG::parse(&file) == Err(Error::InvalidSampleRate));
+        file[0x10..0x14].copy_from_slice(&44_100u32.to_be_bytes());
+        file[0x0C..0x10].copy_from_slice(&0x18u32.to_be_bytes());
+        assert!(VAG::parse(&file) == Err(Error::InvalidSize));
+        file[0x0C..0x10].copy_from_slice(&0x20u32.to_be_bytes());
+        assert!(VAG::parse(&file).unwrap().data.len() == 0x20);
+    }
+}
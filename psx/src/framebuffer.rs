@@ -35,6 +35,7 @@ pub struct Framebuffer {
     pub irq_status: irq::Status,
     disp_envs: [DispEnv; 2],
     draw_envs: [Packet<DrawEnv>; 2],
+    res: Vertex,
     swapped: bool,
 }
 
@@ -72,6 +73,7 @@ impl Framebuffer {
                 Packet::new(DrawEnv::new(buf1, res, bg_color)?),
                 Packet::new(DrawEnv::new(buf0, res, bg_color)?),
             ],
+            res: Vertex::new(res),
             swapped: false,
         };
         GP1::skip_load()
@@ -92,6 +94,17 @@ impl Framebuffer {
         }
     }
 
+    /// Gets the framebuffers' resolution.
+    pub fn resolution(&self) -> Vertex {
+        self.res
+    }
+
+    /// Gets the offset in VRAM of the buffer which is drawn to and displayed
+    /// after the next swap.
+    pub fn draw_offset(&self) -> Vertex {
+        self.disp_envs[!self.swapped as usize].offset.into()
+    }
+
     /// Swaps the framebuffers using only GPU I/O ports.
     pub fn swap(&mut self) {
         self.swapped = !self.swapped;
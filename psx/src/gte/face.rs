@@ -0,0 +1,163 @@
+use crate::gpu::Vertex;
+use crate::gte::{load, unpack_xy, Flags, Gte, FRAC};
+use crate::hw::gte::{self, MAC0, OTZ, SXY0, SXY1, SXY2, VXY0, VXY1, VXY2, VZ0, VZ1, VZ2, ZSF3,
+                     ZSF4};
+use crate::hw::Register;
+
+/// A face projected and culled by [`Gte::project_tri`] or
+/// [`Gte::project_quad`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Face<const N: usize> {
+    /// The screen coordinates of the vertices.
+    pub xy: [Vertex; N],
+    /// The scaled average Z value computed by AVSZ3 or AVSZ4.
+    pub otz: u16,
+    /// The ordering table index which draws the face back to front.
+    pub ot: usize,
+    /// The errors from projecting the vertices.
+    pub flags: Flags,
+}
+
+/// Computes the Z scale factor which maps an average Z of `max_z` to `len`.
+fn z_scale(len: usize, max_z: u16, vertices: usize) -> i16 {
+    let scale = ((len as u64) << FRAC) / (vertices as u64 * max_z.max(1) as u64);
+    scale.min(i16::MAX as u64) as i16
+}
+
+/// Reads the screen coordinate FIFO from oldest to newest.
+fn screen_fifo() -> [Vertex; 3] {
+    [
+        SXY0::new().to_bits(),
+        SXY1::new().to_bits(),
+        SXY2::new().to_bits(),
+    ]
+    .map(unpack_xy)
+}
+
+impl Gte {
+    /// Sets the Z scale factors used by AVSZ3 and AVSZ4.
+    ///
+    /// The average Z is `ZSF3 * (SZ1 + SZ2 + SZ3) >> 12` for triangles and
+    /// `ZSF4 * (SZ0 + SZ1 + SZ2 + SZ3) >> 12` for quads.
+    pub fn set_z_scale(&mut self, zsf3: i16, zsf4: i16) -> &mut Self {
+        ZSF3::skip_load().assign(zsf3).store();
+        ZSF4::skip_load().assign(zsf4).store();
+        self
+    }
+
+    /// Sets the Z scale factors so faces between the camera and `max_z` are
+    /// spread over an ordering table of `len` entries.
+    ///
+    /// Faces further away than `max_z` are put in the first entry.
+    pub fn set_ordering_table(&mut self, len: usize, max_z: u16) -> &mut Self {
+        self.ot_len = len.max(1);
+        self.set_z_scale(z_scale(len, max_z, 3), z_scale(len, max_z, 4))
+    }
+
+    /// Converts an average Z value to an ordering table index.
+    ///
+    /// Nearer faces get larger indices so they're drawn last.
+    pub fn ot_index(&self, otz: u16) -> usize {
+        let last = self.ot_len - 1;
+        last - (otz as usize).min(last)
+    }
+
+    /// Computes the winding of the last three projected vertices with NCLIP.
+    ///
+    /// This is positive if they're clockwise on the screen, negative if
+    /// they're counterclockwise and zero if they're on a line.
+    pub fn nclip(&mut self) -> i32 {
+        gte::nclip();
+        MAC0::new().to_bits()
+    }
+
+    /// Computes the winding of three screen coordinates with NCLIP.
+    pub fn winding(&mut self, [a, b, c]: [Vertex; 3]) -> i32 {
+        SXY0::skip_load().assign(u32::from(a)).store();
+        SXY1::skip_load().assign(u32::from(b)).store();
+        SXY2::skip_load().assign(u32::from(c)).store();
+        self.nclip()
+    }
+
+    /// Averages the Z values of the last three projected vertices with AVSZ3.
+    pub fn average_z3(&mut self) -> u16 {
+        gte::avsz3();
+        OTZ::new().to_bits()
+    }
+
+    /// Averages the Z values of the last four projected vertices with AVSZ4.
+    pub fn average_z4(&mut self) -> u16 {
+        gte::avsz4();
+        OTZ::new().to_bits()
+    }
+
+    /// Projects a triangle, returning `None` if it's a back face.
+    ///
+    /// Front faces are clockwise on the screen. Faces which are
+    /// counterclockwise or edge-on are culled.
+    pub fn project_tri(&mut self, [v0, v1, v2]: [[i16; 3]; 3]) -> Option<Face<3>> {
+        load::<VXY0, VZ0>(v0);
+        load::<VXY1, VZ1>(v1);
+        load::<VXY2, VZ2>(v2);
+        gte::rtpt();
+        // Each command resets the flags
+        let flags = Flags(gte::FLAG::new().to_bits());
+        if self.nclip() <= 0 {
+            return None
+        }
+        let xy = screen_fifo();
+        let otz = self.average_z3();
+        Some(Face {
+            xy,
+            otz,
+            ot: self.ot_index(otz),
+            flags,
+        })
+    }
+
+    /// Projects a planar quad, returning `None` if it's a back face.
+    ///
+    /// The vertices are in the order the GPU draws quads in, so the first
+    /// three are clockwise on the screen for front faces.
+    pub fn project_quad(&mut self, [v0, v1, v2, v3]: [[i16; 3]; 4]) -> Option<Face<4>> {
+        load::<VXY0, VZ0>(v0);
+        load::<VXY1, VZ1>(v1);
+        load::<VXY2, VZ2>(v2);
+        gte::rtpt();
+        let flags = gte::FLAG::new().to_bits();
+        if self.nclip() <= 0 {
+            return None
+        }
+        let first = unpack_xy(SXY0::new().to_bits());
+        // This pushes the last vertex into the screen coordinate and Z FIFOs
+        load::<VXY0, VZ0>(v3);
+        gte::rtps();
+        let flags = Flags(flags | gte::FLAG::new().to_bits());
+        let [a, b, c] = screen_fifo();
+        let otz = self.average_z4();
+        Some(Face {
+            xy: [first, a, b, c],
+            otz,
+            ot: self.ot_index(otz),
+            flags,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::z_scale;
+    use crate::gte::Gte;
+
+    #[test_case]
+    fn z_scales() {
+        // The SDK's defaults for a 0x4000 entry table
+        assert!(z_scale(0x4000, 0xFFFF, 3) == 0x155);
+        assert!(z_scale(0x4000, 0xFFFF, 4) == 0x100);
+        assert!(z_scale(0x10000, 1, 3) == i16::MAX);
+        let gte = Gte { ot_len: 256 };
+        assert!(gte.ot_index(0) == 255);
+        assert!(gte.ot_index(255) == 0);
+        assert!(gte.ot_index(1000) == 0);
+    }
+}
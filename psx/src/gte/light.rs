@@ -0,0 +1,252 @@
+use crate::gpu::Color;
+use crate::gte::{load, Gte, Matrix, FRAC};
+use crate::hw::gte::{self, BBK, BFC, DQA, DQB, GBK, GFC, IR0, L11_12, L13_21, L22_23, L31_32, L33,
+                     LR11_12, LR13_21, LR22_23, LR31_32, LR33, RBK, RFC, RGB0, RGB1, RGB2, RGBC,
+                     VXY0, VXY1, VXY2, VZ0, VZ1, VZ2};
+use crate::hw::Register;
+
+// Colors are 8-bit in RGBC and the RGB FIFO but have 4 more fractional bits
+// in the GTE's matrices and color vectors, so 0xFF is almost 1.0
+const COLOR_FRAC: u32 = FRAC - 8;
+
+/// Scales a color component to the GTE's fixed-point color format.
+fn scale_color(x: u8) -> i16 {
+    (x as i16) << COLOR_FRAC
+}
+
+/// Unpacks a color from the RGB FIFO, ignoring the GPU command byte.
+fn unpack_color(rgb: u32) -> Color {
+    Color::new(rgb as u8, (rgb >> 8) as u8, (rgb >> 16) as u8)
+}
+
+/// Computes the depth cueing coefficient and offset which fade from none at
+/// `near` to the far color at `far`.
+fn depth_cue(near: u16, far: u16, distance: u16) -> (i16, i32) {
+    let (near, far) = (near.max(1) as i64, far as i64);
+    let range = (far - near).max(1);
+    // IR0 = (DQB + DQA * (H << 16) / SZ) >> 12 so DQA has 8 fractional bits
+    // and DQB has 24
+    let dqa = -((near * far) << 8) / (distance.max(1) as i64 * range);
+    let dqb = (far << 24) / range;
+    (
+        dqa.clamp(i16::MIN as i64, i16::MAX as i64) as i16,
+        dqb.clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+    )
+}
+
+impl Matrix {
+    /// Creates a light color matrix with a column for each light's color.
+    pub fn light_colors(colors: [Color; 3]) -> Self {
+        let [a, b, c] = colors.map(|c| [c.red, c.green, c.blue].map(scale_color));
+        Matrix([[a[0], b[0], c[0]], [a[1], b[1], c[1]], [a[2], b[2], c[2]]])
+    }
+}
+
+impl Gte {
+    /// Loads the light matrix, which has a row for the direction from the
+    /// surface toward each light.
+    ///
+    /// Normals aren't rotated before lighting, so for lights fixed in the
+    /// world this should be the lights' directions times the rotation
+    /// matrix.
+    pub fn set_light_matrix(&mut self, matrix: &Matrix) -> &mut Self {
+        let [m0, m1, m2, m3, m4] = matrix.pack();
+        L11_12::skip_load().assign(m0).store();
+        L13_21::skip_load().assign(m1).store();
+        L22_23::skip_load().assign(m2).store();
+        L31_32::skip_load().assign(m3).store();
+        L33::skip_load().assign(m4 as i16).store();
+        self
+    }
+
+    /// Loads the light color matrix, which has a column for each light's
+    /// color. See [`Matrix::light_colors`].
+    pub fn set_light_colors(&mut self, matrix: &Matrix) -> &mut Self {
+        let [m0, m1, m2, m3, m4] = matrix.pack();
+        LR11_12::skip_load().assign(m0).store();
+        LR13_21::skip_load().assign(m1).store();
+        LR22_23::skip_load().assign(m2).store();
+        LR31_32::skip_load().assign(m3).store();
+        LR33::skip_load().assign(m4 as i16).store();
+        self
+    }
+
+    /// Sets the ambient color, which is added to the lights' colors.
+    pub fn set_background_color(&mut self, color: Color) -> &mut Self {
+        RBK::skip_load()
+            .assign(scale_color(color.red) as i32)
+            .store();
+        GBK::skip_load()
+            .assign(scale_color(color.green) as i32)
+            .store();
+        BBK::skip_load()
+            .assign(scale_color(color.blue) as i32)
+            .store();
+        self
+    }
+
+    /// Sets the far color which depth cueing fades toward.
+    pub fn set_far_color(&mut self, color: Color) -> &mut Self {
+        RFC::skip_load()
+            .assign(scale_color(color.red) as i32)
+            .store();
+        GFC::skip_load()
+            .assign(scale_color(color.green) as i32)
+            .store();
+        BFC::skip_load()
+            .assign(scale_color(color.blue) as i32)
+            .store();
+        self
+    }
+
+    /// Sets the depth cueing coefficient and offset.
+    ///
+    /// Projecting a vertex sets the interpolation factor IR0 to
+    /// `(dqb + dqa * (H << 16) / z) >> 12`, where [`ONE`][crate::gte::ONE]
+    /// is entirely the far color.
+    pub fn set_depth_cue(&mut self, dqa: i16, dqb: i32) -> &mut Self {
+        DQA::skip_load().assign(dqa).store();
+        DQB::skip_load().assign(dqb).store();
+        self
+    }
+
+    /// Sets depth cueing to fade from none at a distance of `near` to the far
+    /// color at `far`.
+    ///
+    /// This depends on the projection plane distance, so it must be called
+    /// after [`Gte::set_projection`].
+    pub fn set_fog(&mut self, near: u16, far: u16) -> &mut Self {
+        let distance = gte::H::new().to_bits();
+        let (dqa, dqb) = depth_cue(near, far, distance);
+        self.set_depth_cue(dqa, dqb)
+    }
+
+    /// Computes the color of a normal lit by the light sources with NCS.
+    pub fn light(&mut self, normal: [i16; 3]) -> Color {
+        load::<VXY0, VZ0>(normal);
+        gte::ncs();
+        unpack_color(RGB2::new().to_bits())
+    }
+
+    /// Computes the colors of three normals lit by the light sources with
+    /// NCT.
+    pub fn light3(&mut self, normals: [[i16; 3]; 3]) -> [Color; 3] {
+        load_normals(normals);
+        gte::nct();
+        rgb_fifo()
+    }
+
+    /// Computes the color of a normal lit by the light sources and
+    /// multiplied by `color` with NCCS.
+    pub fn light_colored(&mut self, normal: [i16; 3], color: Color) -> Color {
+        set_color(color);
+        load::<VXY0, VZ0>(normal);
+        gte::nccs();
+        unpack_color(RGB2::new().to_bits())
+    }
+
+    /// Computes the colors of three normals lit by the light sources and
+    /// multiplied by `color` with NCCT.
+    pub fn light_colored3(&mut self, normals: [[i16; 3]; 3], color: Color) -> [Color; 3] {
+        set_color(color);
+        load_normals(normals);
+        gte::ncct();
+        rgb_fifo()
+    }
+
+    /// Computes the color of a normal lit by the light sources, multiplied
+    /// by `color` and depth cued toward the far color with NCDS.
+    ///
+    /// This uses the depth cueing factor from the last projected vertex.
+    pub fn light_fogged(&mut self, normal: [i16; 3], color: Color) -> Color {
+        set_color(color);
+        load::<VXY0, VZ0>(normal);
+        gte::ncds();
+        unpack_color(RGB2::new().to_bits())
+    }
+
+    /// Computes the colors of three normals lit by the light sources,
+    /// multiplied by `color` and depth cued toward the far color with NCDT.
+    ///
+    /// This uses the depth cueing factor from the last projected vertex for
+    /// all three normals.
+    pub fn light_fogged3(&mut self, normals: [[i16; 3]; 3], color: Color) -> [Color; 3] {
+        set_color(color);
+        load_normals(normals);
+        gte::ncdt();
+        rgb_fifo()
+    }
+
+    /// Depth cues a color toward the far color with DPCS, using the depth
+    /// cueing factor from the last projected vertex.
+    pub fn fog(&mut self, color: Color) -> Color {
+        set_color(color);
+        gte::dpcs();
+        unpack_color(RGB2::new().to_bits())
+    }
+
+    /// Depth cues a color toward the far color with DPCS, where `factor`
+    /// ranges from `0` to [`ONE`][crate::gte::ONE].
+    pub fn fog_by(&mut self, color: Color, factor: i16) -> Color {
+        IR0::skip_load().assign(factor).store();
+        self.fog(color)
+    }
+}
+
+/// Loads a color into RGBC with a zero GPU command byte.
+fn set_color(color: Color) {
+    RGBC::skip_load().assign(u32::from(color)).store();
+}
+
+/// Loads three normals into V0, V1 and V2.
+fn load_normals([n0, n1, n2]: [[i16; 3]; 3]) {
+    load::<VXY0, VZ0>(n0);
+    load::<VXY1, VZ1>(n1);
+    load::<VXY2, VZ2>(n2);
+}
+
+/// Reads the color FIFO from oldest to newest.
+fn rgb_fifo() -> [Color; 3] {
+    [
+        RGB0::new().to_bits(),
+        RGB1::new().to_bits(),
+        RGB2::new().to_bits(),
+    ]
+    .map(unpack_color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{depth_cue, unpack_color};
+    use crate::gpu::Color;
+    use crate::gte::{Matrix, ONE};
+
+    // The depth cueing factor IR0 the GTE computes for a vertex at `z`
+    fn factor((dqa, dqb): (i16, i32), distance: u16, z: u16) -> i64 {
+        let ratio = ((distance as i64) << 16) / z as i64;
+        ((dqb as i64 + dqa as i64 * ratio) >> 12).clamp(0, ONE as i64)
+    }
+
+    #[test_case]
+    fn colors() {
+        let color = Color::new(0x12, 0x34, 0xFF);
+        assert!(unpack_color(u32::from(color) | 0x2C << 24) == color);
+        let m = Matrix::light_colors([
+            Color::new(0xFF, 0, 0),
+            Color::new(0, 0x80, 0),
+            Color::new(0, 0, 0x10),
+        ]);
+        assert!(m == Matrix([[0xFF0, 0, 0], [0, 0x800, 0], [0, 0, 0x100]]));
+    }
+
+    #[test_case]
+    fn fog() {
+        let cue = depth_cue(1000, 4000, 160);
+        assert!(factor(cue, 160, 1000).abs() <= 1);
+        assert!(factor(cue, 160, 4000) >= ONE as i64 - 1);
+        assert!(factor(cue, 160, 500) == 0);
+        assert!(factor(cue, 160, 8000) == ONE as i64);
+        let mid = factor(cue, 160, 2000);
+        assert!(mid > 0 && mid < ONE as i64);
+    }
+}
@@ -0,0 +1,342 @@
+//! Geometry transformation routines
+//!
+//! [`Gte`] loads a rotation matrix, translation vector and projection into
+//! the GTE, then transforms and projects vertices in hardware. Vertices are
+//! projected three at a time with RTPT. Matrices and vectors use the GTE's
+//! fixed-point formats, where [`ONE`] is 1.0 in matrix entries.
+//!
+//! Meshes can be drawn in a single pass with [`Gte::project_tri`] and
+//! [`Gte::project_quad`], which cull back faces with NCLIP and compute
+//! ordering table indices with AVSZ3 and AVSZ4. Their vertices can be lit
+//! with [`Gte::light3`] and its variants, which compute [`Color`]s from
+//! normals and fade them toward the far color with depth cueing.
+//!
+//! [`Color`]: crate::gpu::Color
+use crate::gpu::Vertex;
+use crate::hw::gte::{self, MAC1, MAC2, MAC3, OFX, OFY, RT11_12, RT13_21, RT22_23, RT31_32, RT33,
+                     SXY0, SXY1, SXY2, SZ1, SZ2, SZ3, TRX, TRY, TRZ, VXY0, VXY1, VXY2, VZ0, VZ1,
+                     VZ2};
+use crate::hw::{cop0, Register};
+use crate::math::{cos, sin, Rad};
+use core::ops::Mul;
+
+mod face;
+mod light;
+
+pub use face::Face;
+
+/// The value 1.0 in a [`Matrix`] entry, which has 12 fractional bits.
+pub const ONE: i16 = 1 << FRAC;
+
+const FRAC: u32 = 12;
+
+// FLAG register bits
+const ERROR: u32 = 31;
+const IR1_SATURATED: u32 = 24;
+const IR2_SATURATED: u32 = 23;
+const IR3_SATURATED: u32 = 22;
+const Z_SATURATED: u32 = 18;
+const DIVIDE_OVERFLOW: u32 = 17;
+const SX_SATURATED: u32 = 14;
+const SY_SATURATED: u32 = 13;
+
+/// A 3x3 matrix with 12 fractional bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Matrix(pub [[i16; 3]; 3]);
+
+impl Default for Matrix {
+    fn default() -> Self {
+        Matrix::IDENTITY
+    }
+}
+
+/// Converts a vector with the 8 fractional bits of `f16` to 12, like the
+/// normals parsed by [`include_obj!`][crate::include_obj].
+pub fn to_fixed(v: [crate::math::f16; 3]) -> [i16; 3] {
+    v.map(to_gte)
+}
+
+/// Converts the 8 fractional bits of `f16` to 12.
+fn to_gte(x: crate::math::f16) -> i16 {
+    x.0 << (FRAC - crate::math::f16::FRAC as u32)
+}
+
+impl Matrix {
+    /// The identity matrix.
+    pub const IDENTITY: Matrix = Matrix([[ONE, 0, 0], [0, ONE, 0], [0, 0, ONE]]);
+
+    /// Creates a rotation by `theta` radians about the x axis.
+    pub fn rotation_x(theta: Rad) -> Self {
+        let (s, c) = (to_gte(sin(theta)), to_gte(cos(theta)));
+        Matrix([[ONE, 0, 0], [0, c, -s], [0, s, c]])
+    }
+
+    /// Creates a rotation by `theta` radians about the y axis.
+    pub fn rotation_y(theta: Rad) -> Self {
+        let (s, c) = (to_gte(sin(theta)), to_gte(cos(theta)));
+        Matrix([[c, 0, s], [0, ONE, 0], [-s, 0, c]])
+    }
+
+    /// Creates a rotation by `theta` radians about the z axis.
+    pub fn rotation_z(theta: Rad) -> Self {
+        let (s, c) = (to_gte(sin(theta)), to_gte(cos(theta)));
+        Matrix([[c, -s, 0], [s, c, 0], [0, 0, ONE]])
+    }
+
+    /// Creates a rotation about the y axis, then the x axis and then the z
+    /// axis.
+    pub fn rotation(x: Rad, y: Rad, z: Rad) -> Self {
+        Matrix::rotation_z(z) * Matrix::rotation_x(x) * Matrix::rotation_y(y)
+    }
+
+    /// Gets the transposed matrix, which is the inverse of a rotation.
+    pub fn transpose(&self) -> Self {
+        let m = &self.0;
+        Matrix([
+            [m[0][0], m[1][0], m[2][0]],
+            [m[0][1], m[1][1], m[2][1]],
+            [m[0][2], m[1][2], m[2][2]],
+        ])
+    }
+
+    /// Packs the entries into the five registers the GTE stores matrices in.
+    pub(crate) fn pack(&self) -> [u32; 5] {
+        let [[m11, m12, m13], [m21, m22, m23], [m31, m32, m33]] = self.0;
+        let pair = |a: i16, b: i16| a as u16 as u32 | (b as u16 as u32) << 16;
+        [
+            pair(m11, m12),
+            pair(m13, m21),
+            pair(m22, m23),
+            pair(m31, m32),
+            m33 as u16 as u32,
+        ]
+    }
+}
+
+/// Multiplies and sums the entries of a row and a column.
+fn dot(row: [i16; 3], col: [i16; 3]) -> i16 {
+    let sum: i32 = (0..3).map(|k| row[k] as i32 * col[k] as i32).sum();
+    (sum >> FRAC) as i16
+}
+
+impl Mul for Matrix {
+    type Output = Matrix;
+    fn mul(self, other: Matrix) -> Matrix {
+        let cols = other.transpose().0;
+        Matrix(self.0.map(|row| cols.map(|col| dot(row, col))))
+    }
+}
+
+/// The calculation errors from the FLAG register.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Flags(pub u32);
+
+impl Flags {
+    /// Checks if any error which sets the FLAG register's summary bit
+    /// occurred.
+    pub fn error(&self) -> bool {
+        self.0 & (1 << ERROR) != 0
+    }
+
+    /// Checks if a screen coordinate was saturated to -0x400..=0x3FF.
+    pub fn screen_saturated(&self) -> bool {
+        self.0 & ((1 << SX_SATURATED) | (1 << SY_SATURATED)) != 0
+    }
+
+    /// Checks if a Z value was saturated to 0..=0xFFFF.
+    pub fn z_saturated(&self) -> bool {
+        self.0 & (1 << Z_SATURATED) != 0
+    }
+
+    /// Checks if a vertex was too close to the camera, which overflows the
+    /// perspective division.
+    pub fn divide_overflow(&self) -> bool {
+        self.0 & (1 << DIVIDE_OVERFLOW) != 0
+    }
+
+    /// Checks if IR1, IR2 or IR3 were saturated.
+    pub fn ir_saturated(&self) -> bool {
+        self.0 & ((1 << IR1_SATURATED) | (1 << IR2_SATURATED) | (1 << IR3_SATURATED)) != 0
+    }
+
+    /// Checks if a vertex was projected outside the range the GTE can
+    /// represent, so it shouldn't be drawn.
+    pub fn out_of_range(&self) -> bool {
+        self.screen_saturated() || self.divide_overflow()
+    }
+}
+
+/// A vertex projected onto the screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Projected {
+    /// The screen coordinates.
+    pub xy: Vertex,
+    /// The distance from the camera.
+    pub z: u16,
+    /// The errors from the command which projected the vertex.
+    pub flags: Flags,
+}
+
+/// Packs a vertex's X and Y coordinates for VXY registers.
+fn pack_xy([x, y, _]: [i16; 3]) -> u32 {
+    x as u16 as u32 | (y as u16 as u32) << 16
+}
+
+/// Loads a vertex into V0, V1 or V2.
+fn load<VXY: Register<u32>, VZ: Register<i16>>(vertex: [i16; 3]) {
+    VXY::skip_load().assign(pack_xy(vertex)).store();
+    VZ::skip_load().assign(vertex[2]).store();
+}
+
+/// Unpacks the screen coordinates from a SXY register.
+fn unpack_xy(xy: u32) -> Vertex {
+    Vertex(xy as i16, (xy >> 16) as i16)
+}
+
+/// A handle to the GTE.
+#[derive(Debug)]
+pub struct Gte {
+    // The length of the ordering table faces are sorted into
+    ot_len: usize,
+}
+
+impl Default for Gte {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Gte {
+    /// Enables the GTE in cop0.
+    pub fn new() -> Self {
+        cop0::Status::new().enable_gte().store();
+        Gte { ot_len: 1 }
+    }
+
+    /// Loads the rotation matrix.
+    pub fn set_rotation(&mut self, matrix: &Matrix) -> &mut Self {
+        let [m0, m1, m2, m3, m4] = matrix.pack();
+        RT11_12::skip_load().assign(m0).store();
+        RT13_21::skip_load().assign(m1).store();
+        RT22_23::skip_load().assign(m2).store();
+        RT31_32::skip_load().assign(m3).store();
+        RT33::skip_load().assign(m4 as i16).store();
+        self
+    }
+
+    /// Loads the translation vector, which is added after rotating.
+    pub fn set_translation(&mut self, [x, y, z]: [i32; 3]) -> &mut Self {
+        TRX::skip_load().assign(x).store();
+        TRY::skip_load().assign(y).store();
+        TRZ::skip_load().assign(z).store();
+        self
+    }
+
+    /// Sets the screen coordinates of the center of the projection and the
+    /// distance from the camera to the projection plane.
+    ///
+    /// Larger distances give a narrower field of view. The distance is
+    /// usually about half the screen's width.
+    pub fn set_projection(&mut self, center: Vertex, distance: u16) -> &mut Self {
+        // The offsets have 16 fractional bits
+        OFX::skip_load().assign((center.0 as i32) << 16).store();
+        OFY::skip_load().assign((center.1 as i32) << 16).store();
+        gte::H::skip_load().assign(distance).store();
+        self
+    }
+
+    /// Rotates and translates a vertex without projecting it.
+    pub fn transform(&mut self, vertex: [i16; 3]) -> [i32; 3] {
+        VXY0::skip_load().assign(pack_xy(vertex)).store();
+        VZ0::skip_load().assign(vertex[2]).store();
+        gte::mvmva::<true, false, 0, 0, 0>();
+        [
+            MAC1::new().to_bits(),
+            MAC2::new().to_bits(),
+            MAC3::new().to_bits(),
+        ]
+    }
+
+    /// Transforms and projects a vertex with RTPS.
+    pub fn project(&mut self, vertex: [i16; 3]) -> Projected {
+        VXY0::skip_load().assign(pack_xy(vertex)).store();
+        VZ0::skip_load().assign(vertex[2]).store();
+        gte::rtps();
+        Projected {
+            xy: unpack_xy(SXY2::new().to_bits()),
+            z: SZ3::new().to_bits(),
+            flags: Flags(gte::FLAG::new().to_bits()),
+        }
+    }
+
+    /// Transforms and projects three vertices with RTPT.
+    ///
+    /// The flags are shared by the three vertices.
+    pub fn project3(&mut self, [v0, v1, v2]: [[i16; 3]; 3]) -> [Projected; 3] {
+        VXY0::skip_load().assign(pack_xy(v0)).store();
+        VZ0::skip_load().assign(v0[2]).store();
+        VXY1::skip_load().assign(pack_xy(v1)).store();
+        VZ1::skip_load().assign(v1[2]).store();
+        VXY2::skip_load().assign(pack_xy(v2)).store();
+        VZ2::skip_load().assign(v2[2]).store();
+        gte::rtpt();
+        let flags = Flags(gte::FLAG::new().to_bits());
+        [
+            (SXY0::new().to_bits(), SZ1::new().to_bits()),
+            (SXY1::new().to_bits(), SZ2::new().to_bits()),
+            (SXY2::new().to_bits(), SZ3::new().to_bits()),
+        ]
+        .map(|(xy, z)| Projected {
+            xy: unpack_xy(xy),
+            z,
+            flags,
+        })
+    }
+
+    /// Transforms and projects `vertices` into `out`, three at a time.
+    ///
+    /// Only as many vertices as fit in `out` are projected.
+    pub fn project_all(&mut self, vertices: &[[i16; 3]], out: &mut [Projected]) {
+        let len = vertices.len().min(out.len());
+        let (vertices, out) = (&vertices[..len], &mut out[..len]);
+        let mut batches = vertices.chunks_exact(3);
+        let mut outs = out.chunks_exact_mut(3);
+        for (batch, out) in (&mut batches).zip(&mut outs) {
+            out.copy_from_slice(&self.project3([batch[0], batch[1], batch[2]]));
+        }
+        for (&vertex, out) in batches.remainder().iter().zip(outs.into_remainder()) {
+            *out = self.project(vertex);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pack_xy, unpack_xy, Flags, Matrix, ONE};
+    use crate::gpu::Vertex;
+    use crate::math::{Rad, FRAC_PI_2};
+
+    #[test_case]
+    fn matrices() {
+        assert!(Matrix::rotation_x(Rad(0)) == Matrix::IDENTITY);
+        let rz = Matrix::rotation_z(FRAC_PI_2);
+        assert!(rz.0[0][1] == -ONE);
+        assert!(rz.0[1][0] == ONE);
+        assert!(rz * Matrix::IDENTITY == rz);
+        assert!(rz * rz.transpose() == Matrix::IDENTITY);
+        let m = Matrix([[1, 2, 3], [4, 5, 6], [7, 8, -9]]);
+        assert!(m.pack() == [0x0002_0001, 0x0004_0003, 0x0006_0005, 0x0008_0007, 0xFFF7]);
+    }
+
+    #[test_case]
+    fn coordinates() {
+        assert!(pack_xy([-1, 2, 3]) == 0x0002_FFFF);
+        assert!(unpack_xy(0xFFFE_0010) == Vertex(16, -2));
+        let flags = Flags(1 << 31 | 1 << 14);
+        assert!(flags.error());
+        assert!(flags.screen_saturated());
+        assert!(flags.out_of_range());
+        assert!(!flags.z_saturated());
+        assert!(!Flags(1 << 22).out_of_range());
+    }
+}
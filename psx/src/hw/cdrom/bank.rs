@@ -0,0 +1,199 @@
+use crate::hw::cdrom::{Bank, Bank0, Bank1, Bank2, Bank3, Command, Idx, IntCause, Selector};
+use crate::hw::cdrom::{Status, ALL_INTERRUPTS, INT_CAUSE_MASK};
+use crate::hw::Register;
+use core::marker::PhantomData;
+use core::ptr::{read_volatile, write_volatile};
+
+const INDEX: u32 = 0x1F80_1800;
+const PORT1: u32 = 0x1F80_1801;
+const PORT2: u32 = 0x1F80_1802;
+const PORT3: u32 = 0x1F80_1803;
+
+// Request register bits
+const SMEN: u8 = 5;
+const BFRD: u8 = 7;
+
+// Interrupt flag register bits
+const CLRPRM: u8 = 6;
+
+// Audio volume apply register bits
+const ADPMUTE: u8 = 0;
+const CHNGATV: u8 = 5;
+
+fn read(addr: u32) -> u8 {
+    // SAFETY: `addr` is one of the controller's ports
+    unsafe { read_volatile(addr as *const u8) }
+}
+
+fn write(addr: u32, value: u8) {
+    // SAFETY: `addr` is one of the controller's ports
+    unsafe { write_volatile(addr as *mut u8, value) }
+}
+
+macro_rules! impl_bank {
+    ($bank:ident, $idx:ident) => {
+        impl<'a> Bank<'a> for $bank<'a> {
+            const IDX: Idx = Idx::$idx;
+
+            fn select(_selector: &'a mut Selector) -> Self {
+                write(INDEX, Self::IDX as u8);
+                $bank(PhantomData)
+            }
+
+            fn read_response(&mut self) -> Option<u8> {
+                if Status::new().response_fifo_empty() {
+                    None
+                } else {
+                    Some(read(PORT1))
+                }
+            }
+
+            fn read_data(&mut self) -> u8 {
+                read(PORT2)
+            }
+
+            fn read_data_u16(&mut self) -> u16 {
+                // SAFETY: The data FIFO supports 16-bit reads
+                unsafe { read_volatile(PORT2 as *const u16) }
+            }
+        }
+    };
+}
+
+impl_bank!(Bank0, Idx0);
+impl_bank!(Bank1, Idx1);
+impl_bank!(Bank2, Idx2);
+impl_bank!(Bank3, Idx3);
+
+impl Bank0<'_> {
+    /// Sends a command using the parameters in the parameter FIFO.
+    pub fn send_command(&mut self, cmd: Command) -> &mut Self {
+        write(PORT1, cmd as u8);
+        self
+    }
+
+    /// Pushes a command parameter into the parameter FIFO.
+    pub fn push_parameter(&mut self, param: u8) -> &mut Self {
+        write(PORT2, param);
+        self
+    }
+
+    /// Pushes command parameters into the parameter FIFO.
+    pub fn push_parameters(&mut self, params: &[u8]) -> &mut Self {
+        for &param in params {
+            self.push_parameter(param);
+        }
+        self
+    }
+
+    /// Requests loading the current sector into the data FIFO or clears the
+    /// data FIFO. `command_start` enables the command start interrupt.
+    pub fn request_data(&mut self, load: bool, command_start: bool) -> &mut Self {
+        write(PORT3, (load as u8) << BFRD | (command_start as u8) << SMEN);
+        self
+    }
+
+    /// Gets the enabled interrupts.
+    pub fn interrupt_enable(&mut self) -> u8 {
+        read(PORT3) & ALL_INTERRUPTS
+    }
+}
+
+impl Bank1<'_> {
+    /// Writes a byte of XA-ADPCM sound map data.
+    pub fn write_sound_map(&mut self, data: u8) -> &mut Self {
+        write(PORT1, data);
+        self
+    }
+
+    /// Sets the enabled interrupts.
+    pub fn set_interrupt_enable(&mut self, mask: u8) -> &mut Self {
+        write(PORT2, mask);
+        self
+    }
+
+    /// Gets the pending interrupt flags.
+    pub fn interrupt_flags(&mut self) -> u8 {
+        read(PORT3) & ALL_INTERRUPTS
+    }
+
+    /// Gets the type of the pending interrupt, if any.
+    pub fn int_cause(&mut self) -> Option<IntCause> {
+        match read(PORT3) & INT_CAUSE_MASK {
+            1 => Some(IntCause::DataReady),
+            2 => Some(IntCause::Complete),
+            3 => Some(IntCause::Acknowledge),
+            4 => Some(IntCause::DataEnd),
+            5 => Some(IntCause::DiscError),
+            _ => None,
+        }
+    }
+
+    /// Acknowledges the interrupts set in `flags`.
+    ///
+    /// The next queued interrupt and its response are only delivered after
+    /// the current one is acknowledged.
+    pub fn ack_interrupts(&mut self, flags: u8) -> &mut Self {
+        write(PORT3, flags);
+        self
+    }
+
+    /// Clears the parameter FIFO.
+    pub fn clear_parameters(&mut self) -> &mut Self {
+        write(PORT3, 1 << CLRPRM);
+        self
+    }
+}
+
+impl Bank2<'_> {
+    /// Writes a byte of XA-ADPCM sound map coding info.
+    pub fn write_sound_map_info(&mut self, info: u8) -> &mut Self {
+        write(PORT1, info);
+        self
+    }
+
+    /// Sets the volume of the left CD audio output to the left SPU input.
+    ///
+    /// `0x80` is normal volume. Changes take effect after
+    /// [`Bank3::apply_volume`].
+    pub fn set_left_to_left(&mut self, volume: u8) -> &mut Self {
+        write(PORT2, volume);
+        self
+    }
+
+    /// Sets the volume of the left CD audio output to the right SPU input.
+    pub fn set_left_to_right(&mut self, volume: u8) -> &mut Self {
+        write(PORT3, volume);
+        self
+    }
+
+    /// Gets the enabled interrupts.
+    pub fn interrupt_enable(&mut self) -> u8 {
+        read(PORT3) & ALL_INTERRUPTS
+    }
+}
+
+impl Bank3<'_> {
+    /// Sets the volume of the right CD audio output to the right SPU input.
+    pub fn set_right_to_right(&mut self, volume: u8) -> &mut Self {
+        write(PORT1, volume);
+        self
+    }
+
+    /// Sets the volume of the right CD audio output to the left SPU input.
+    pub fn set_right_to_left(&mut self, volume: u8) -> &mut Self {
+        write(PORT2, volume);
+        self
+    }
+
+    /// Applies the volume changes and mutes or unmutes XA-ADPCM.
+    pub fn apply_volume(&mut self, mute_adpcm: bool) -> &mut Self {
+        write(PORT3, 1 << CHNGATV | (mute_adpcm as u8) << ADPMUTE);
+        self
+    }
+
+    /// Gets the pending interrupt flags.
+    pub fn interrupt_flags(&mut self) -> u8 {
+        read(PORT3) & ALL_INTERRUPTS
+    }
+}
@@ -2,13 +2,15 @@ use crate::hw::cdrom::{Command, Controller, Parameter};
 use crate::hw::Register;
 
 impl Controller {
+    /// Sets the command to send.
     pub fn send_cmd(&mut self, cmd: Command) -> &mut Self {
         self.assign(cmd as u8)
     }
 }
 
 impl Parameter {
+    /// Sets the parameter to push into the parameter FIFO.
     pub fn set_param(&mut self, param: u8) -> &mut Self {
-        self.assign(param as u8)
+        self.assign(param)
     }
 }
@@ -1,65 +1,218 @@
 //! CDROM controller registers
-
-// This is a WIP so I don't care about docs rn
-#![allow(missing_docs)]
+//!
+//! Most of the controller's registers are banked by the index in the
+//! [`Status`] register. Selecting a bank with [`Bank::select`] returns a
+//! handle which only exposes the registers available with that index. The
+//! handle borrows the unique [`Selector`], so only one bank can be used at a
+//! time. The response FIFO and data FIFO may be read with any index selected.
 
 use crate::hw::MemRegister;
+use core::marker::PhantomData;
 
+mod bank;
 mod controller;
 mod status;
 
+/// The register bank selected by the index in [`Status`].
 #[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Idx {
+    /// Command, parameter FIFO and request registers.
     Idx0 = 0,
+    /// Interrupt enable and interrupt flag registers.
     Idx1,
+    /// Left CD audio output volume registers.
     Idx2,
+    /// Right CD audio output volume registers.
     Idx3,
 }
 
+/// A CDROM controller command.
 #[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Command {
+    /// Gets the drive status.
     GetStat = 0x1,
+    /// Sets the seek target from BCD minute, second and sector parameters.
     Setloc = 0x2,
+    /// Plays CD audio, optionally starting at a track given as a parameter.
     Play = 0x3,
+    /// Fast forwards while playing CD audio.
     Forward = 0x04,
+    /// Rewinds while playing CD audio.
     Backward = 0x05,
+    /// Reads data sectors with retries starting at the seek target.
     ReadN = 0x06,
+    /// Starts the spindle motor.
     MotorOn = 0x07,
+    /// Stops the spindle motor.
     Stop = 0x08,
+    /// Pauses reading or playing.
     Pause = 0x09,
+    /// Resets the mode and stops reading.
     Init = 0x0A,
+    /// Mutes CD audio output.
     Mute = 0x0B,
+    /// Unmutes CD audio output.
     Demute = 0x0C,
+    /// Sets the file and channel for XA-ADPCM filtering.
     Setfilter = 0x0D,
+    /// Sets the mode from a parameter.
     Setmode = 0x0E,
+    /// Gets the mode and filter.
     Getparam = 0x0F,
+    /// Gets the header of the last data sector read.
     GetlocL = 0x10,
+    /// Gets the subchannel Q position of the drive head.
     GetlocP = 0x11,
+    /// Selects a session on multi-session discs.
     SetSession = 0x12,
+    /// Gets the first and last track numbers.
     GetTN = 0x13,
+    /// Gets the start of a track, or the end of the disc for track 0.
     GetTD = 0x14,
+    /// Seeks to the seek target in data mode.
     SeekL = 0x15,
+    /// Seeks to the seek target in audio mode.
     SeekP = 0x16,
+    /// Runs a test subfunction given as a parameter.
     Test = 0x19,
+    /// Gets the disc's identification and region.
     GetID = 0x1A,
+    /// Reads data sectors without retries starting at the seek target.
     ReadS = 0x1B,
+    /// Resets the drive.
     Reset = 0x1C,
+    /// Reads subchannel Q data.
     GetQ = 0x1D,
+    /// Rereads the table of contents.
     ReadTOC = 0x1E,
+    /// Video CD command on the SCPH-5903.
     VideoCD = 0x1F,
+    /// Secret unlock command 1.
     Secret1 = 0x50,
+    /// Secret unlock command 2.
     Secret2 = 0x51,
+    /// Secret unlock command 3.
     Secret3 = 0x52,
+    /// Secret unlock command 4.
     Secret4 = 0x53,
+    /// Secret unlock command 5.
     Secret5 = 0x54,
+    /// Secret unlock command 6.
     Secret6 = 0x55,
+    /// Secret unlock command 7.
     Secret7 = 0x56,
+    /// Locks the drive again after the secret unlock commands.
     SecretLock = 0x57,
 }
 
+/// The type of interrupt raised by the CDROM controller.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntCause {
+    /// INT1: A sector is ready to be read or a report is available.
+    DataReady = 1,
+    /// INT2: The second response of a command is available.
+    Complete,
+    /// INT3: The first response of a command is available.
+    Acknowledge,
+    /// INT4: The end of the disc or track was reached.
+    DataEnd,
+    /// INT5: A command failed.
+    DiscError,
+}
+
+/// The interrupt types enabled or pending in the interrupt registers.
+pub const INT_CAUSE_MASK: u8 = 0x07;
+/// The INT8 bit in the interrupt registers.
+pub const INT8: u8 = 1 << 3;
+/// The command start interrupt (INT10) bit in the interrupt registers.
+pub const INT_COMMAND_START: u8 = 1 << 4;
+/// All interrupt bits in the interrupt registers.
+pub const ALL_INTERRUPTS: u8 = INT_CAUSE_MASK | INT8 | INT_COMMAND_START;
+
+/// The index/status register.
 pub type Status = MemRegister<u8, 0x1F80_1800>;
+/// The command register (index 0, write-only).
 pub type Controller = MemRegister<u8, 0x1F80_1801>;
+/// The parameter FIFO (index 0, write) and 8-bit data FIFO (read).
 pub type Parameter = MemRegister<u8, 0x1F80_1802>;
+/// The 16-bit data FIFO (read-only).
 pub type Data = MemRegister<u16, 0x1F80_1802>;
+/// The request register (index 0, write-only).
 pub type Request = MemRegister<u8, 0x1F80_1803>;
+/// The interrupt enable or flag register, depending on the index.
 pub type Interrupt = MemRegister<u8, 0x1F80_1803>;
+
+/// The owner of the index in the [`Status`] register.
+///
+/// Selecting a [`Bank`] mutably borrows the selector for as long as the bank's
+/// handle lives, so a handle can't outlive the selection.
+#[derive(Debug)]
+pub struct Selector(());
+
+impl Selector {
+    /// Creates the bank selector.
+    ///
+    /// # Safety
+    ///
+    /// Only one selector may exist at a time.
+    pub const unsafe fn new() -> Self {
+        Selector(())
+    }
+}
+
+/// A bank of registers selected by an [`Idx`].
+pub trait Bank<'a>: private::Sealed + Sized {
+    /// The index which selects this bank.
+    const IDX: Idx;
+
+    /// Selects this bank in the [`Status`] register.
+    fn select(selector: &'a mut Selector) -> Self;
+
+    /// Reads a byte from the response FIFO if it's not empty.
+    fn read_response(&mut self) -> Option<u8>;
+
+    /// Reads the response FIFO into `buf` until it's empty or `buf` is full,
+    /// returning the number of bytes read.
+    fn read_responses(&mut self, buf: &mut [u8]) -> usize {
+        for (i, byte) in buf.iter_mut().enumerate() {
+            match self.read_response() {
+                Some(res) => *byte = res,
+                None => return i,
+            }
+        }
+        buf.len()
+    }
+
+    /// Reads a byte from the data FIFO.
+    fn read_data(&mut self) -> u8;
+
+    /// Reads two bytes from the data FIFO.
+    fn read_data_u16(&mut self) -> u16;
+}
+
+/// The registers available with [`Idx::Idx0`] selected.
+#[derive(Debug)]
+pub struct Bank0<'a>(PhantomData<&'a mut Selector>);
+
+/// The registers available with [`Idx::Idx1`] selected.
+#[derive(Debug)]
+pub struct Bank1<'a>(PhantomData<&'a mut Selector>);
+
+/// The registers available with [`Idx::Idx2`] selected.
+#[derive(Debug)]
+pub struct Bank2<'a>(PhantomData<&'a mut Selector>);
+
+/// The registers available with [`Idx::Idx3`] selected.
+#[derive(Debug)]
+pub struct Bank3<'a>(PhantomData<&'a mut Selector>);
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for super::Bank0<'_> {}
+    impl Sealed for super::Bank1<'_> {}
+    impl Sealed for super::Bank2<'_> {}
+    impl Sealed for super::Bank3<'_> {}
+}
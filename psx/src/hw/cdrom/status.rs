@@ -11,6 +11,7 @@ const BUSYSTS: u8 = 7;
 
 // TODO: Add a better Debug impl for this
 impl Status {
+    /// Gets the selected register bank.
     pub fn get_idx(&self) -> Idx {
         match self.to_bits() & 0b11 {
             0 => Idx::Idx0,
@@ -22,30 +23,40 @@ impl Status {
         }
     }
 
+    /// Selects a register bank.
+    ///
+    /// [`Bank::select`][crate::hw::cdrom::Bank::select] returns a handle to
+    /// the selected bank's registers.
     pub fn set_idx(&mut self, idx: Idx) -> &mut Self {
         self.clear_bits(0b11).set_bits(idx as u8)
     }
 
+    /// Checks if XA-ADPCM isn't being played.
     pub fn xa_adpcm_empty(&self) -> bool {
         self.all_clear(1 << XA_ADPCM)
     }
 
+    /// Checks if the parameter FIFO is empty.
     pub fn param_fifo_empty(&self) -> bool {
         self.all_set(1 << PRMEMPT)
     }
 
+    /// Checks if the parameter FIFO is full.
     pub fn param_fifo_full(&self) -> bool {
         self.all_clear(1 << PRMWRDY)
     }
 
+    /// Checks if the response FIFO is empty.
     pub fn response_fifo_empty(&self) -> bool {
         self.all_clear(1 << RSLRRDY)
     }
 
+    /// Checks if the data FIFO is empty.
     pub fn data_fifo_empty(&self) -> bool {
         self.all_clear(1 << DRQSTS)
     }
 
+    /// Checks if the controller is busy transmitting a command.
     pub fn busy(&self) -> bool {
         self.all_set(1 << BUSYSTS)
     }
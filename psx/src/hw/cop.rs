@@ -1,6 +1,18 @@
 //! Coprocessor register definitions
 use crate::hw::private::Primitive;
 
+/// The `rs` field of `cfc` instructions.
+pub(crate) const CF: u32 = 2;
+/// The `rs` field of `ctc` instructions.
+pub(crate) const CT: u32 = 6;
+/// The general purpose register used by hand-encoded moves.
+pub(crate) const MOVE_REG: u32 = 8;
+
+/// Encodes a coprocessor move instruction between `$t0` and `rd`.
+pub(crate) const fn encode_move(rs: u32, cop: u32, rd: u32) -> u32 {
+    0x4000_0000 | (cop << 26) | (rs << 21) | (MOVE_REG << 16) | (rd << 11)
+}
+
 /// A coprocessor register
 #[repr(C)]
 pub struct CopRegister<T: Primitive, const COP: u32, const REG: u32> {
@@ -23,6 +35,43 @@ macro_rules! define_cop {
     ($(#[$($meta:meta)*])* $name:ident <$ty:ty>; COP: $cop:expr; R: $reg:expr $(,)?) => {
         define_cop!($(#[$($meta)*])* $name<$ty>; COP: $cop; R: $reg; "m");
     };
+    // LLVM doesn't support `cfc` and `ctc` yet (#7), so control registers are
+    // moved through `$t0` with hand-encoded instructions
+    ($(#[$($meta:meta)*])* $name:ident <$ty:ty>; COP: $cop:expr; R: $reg:expr; "c" $(,)?) => {
+        $(#[$($meta)*])*
+        pub type $name = crate::hw::cop::CopRegister<$ty, $cop, $reg>;
+
+        impl Register<$ty> for crate::hw::cop::CopRegister<$ty, $cop, $reg> {
+            fn skip_load() -> Self {
+                Self { value: 0 }
+            }
+            fn load(&mut self) -> &mut Self {
+                unsafe {
+                    core::arch::asm! {
+                        ".word {cfc}",
+                        // Load delay slot
+                        "nop",
+                        cfc = const crate::hw::cop::encode_move(crate::hw::cop::CF, $cop, $reg - 32),
+                        out("$8") self.value,
+                        options(nomem, nostack)
+                    }
+                }
+                self
+            }
+
+            fn store(&mut self) -> &mut Self {
+                unsafe {
+                    core::arch::asm! {
+                        ".word {ctc}",
+                        ctc = const crate::hw::cop::encode_move(crate::hw::cop::CT, $cop, $reg - 32),
+                        in("$8") self.value,
+                        options(nomem, nostack)
+                    }
+                }
+                self
+            }
+        }
+    };
     ($(#[$($meta:meta)*])* $name:ident <$ty:ty>; COP: $cop:expr; R: $reg:expr; $cop_ty:literal $(,)?) => {
         $(#[$($meta)*])*
         pub type $name = crate::hw::cop::CopRegister<$ty, $cop, $reg>;
@@ -58,7 +107,7 @@ macro_rules! define_cop {
             }
         }
     };
-    ($(#[$($meta:meta)*])* $name:ident <$ty:ty>; COP: $cop:expr; R: $reg:expr $(;$cop_ty:literal)?, $($others:tt)*) => {
+    ($(#[$($meta:meta)*])* $name:ident <$ty:ty>; COP: $cop:expr; R: $reg:expr $(;$cop_ty:tt)?, $($others:tt)*) => {
         define_cop!($(#[$($meta)*])* $name<$ty>; COP: $cop; R: $reg $(;$cop_ty)*);
         define_cop!($($others)*);
     };
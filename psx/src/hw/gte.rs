@@ -1,6 +1,8 @@
 //! Geometry Transformation Engine Coprocessor
 //!
 //! This module provides access to GTE, or cop2, registers and instructions.
+//! LLVM doesn't support `cfc2`, `ctc2` or the GTE commands yet (#7), so
+//! these are emitted as pre-encoded instruction words.
 
 use crate::hw::Register;
 
@@ -17,10 +19,48 @@ define_cop! {
     VXY2<u32>; COP: 2; R: 4,
     /// The 16-bit VZ2 vector
     VZ2<i16>;  COP: 2; R: 5,
+    /// The color and GPU command code
+    RGBC<u32>; COP: 2; R: 6,
 
     /// Ordering table average Z value
     OTZ<u16>;  COP: 2; R: 7,
 
+    /// The 16-bit interpolation factor
+    IR0<i16>;  COP: 2; R: 8,
+    /// The first component of the 16-bit vector accumulator
+    IR1<i16>;  COP: 2; R: 9,
+    /// The second component of the 16-bit vector accumulator
+    IR2<i16>;  COP: 2; R: 10,
+    /// The third component of the 16-bit vector accumulator
+    IR3<i16>;  COP: 2; R: 11,
+
+    /// The oldest screen coordinates in the FIFO
+    SXY0<u32>; COP: 2; R: 12,
+    /// The second screen coordinates in the FIFO
+    SXY1<u32>; COP: 2; R: 13,
+    /// The newest screen coordinates in the FIFO
+    SXY2<u32>; COP: 2; R: 14,
+    /// Mirrors SXY2 and pushes to the FIFO when written
+    SXYP<u32>; COP: 2; R: 15,
+
+    /// The oldest screen Z value in the FIFO
+    SZ0<u16>;  COP: 2; R: 16,
+    /// The second screen Z value in the FIFO
+    SZ1<u16>;  COP: 2; R: 17,
+    /// The third screen Z value in the FIFO
+    SZ2<u16>;  COP: 2; R: 18,
+    /// The newest screen Z value in the FIFO
+    SZ3<u16>;  COP: 2; R: 19,
+
+    /// The oldest color in the FIFO
+    RGB0<u32>; COP: 2; R: 20,
+    /// The second color in the FIFO
+    RGB1<u32>; COP: 2; R: 21,
+    /// The newest color in the FIFO
+    RGB2<u32>; COP: 2; R: 22,
+    /// Reserved
+    RES1<u32>; COP: 2; R: 23,
+
     /// Scalar math accumulator
     MAC0<i32>; COP: 2; R: 24,
 
@@ -31,44 +71,289 @@ define_cop! {
     /// The third component of the vector math accumulator
     MAC3<i32>; COP: 2; R: 27,
 
+    /// IR1, IR2 and IR3 packed into a 15-bit color
+    IRGB<u32>; COP: 2; R: 28,
+    /// IR1, IR2 and IR3 saturated and packed into a 15-bit color
+    ORGB<u32>; COP: 2; R: 29,
+
     /// Leading zeros count source
     LZCS<u32>; COP: 2; R: 30,
     /// Leading zeros count result
     LZCR<u32>; COP: 2; R: 31,
 
-    /*
-    TODO: LLVM doesn't support these coprocessor instructions yet (#7).
     /// Rotation matrix entries RT11 and RT12
     RT11_12<u32>; COP: 2; R: 32; "c",
     /// Rotation matrix entries RT13 and RT21
-    RT13_21<u32>; COP: 2; R: 33,
+    RT13_21<u32>; COP: 2; R: 33; "c",
     /// Rotation matrix entries RT22 and RT23
-    RT22_23<u32>; COP: 2; R: 34,
+    RT22_23<u32>; COP: 2; R: 34; "c",
     /// Rotation matrix entries RT31 and RT32
-    RT31_32<u32>; COP: 2; R: 35,
+    RT31_32<u32>; COP: 2; R: 35; "c",
     /// Rotation matrix entry RT33
-    RT33<i16>;    COP: 2; R: 36,
+    RT33<i16>;    COP: 2; R: 36; "c",
+
+    /// The first component of the translation vector
+    TRX<i32>; COP: 2; R: 37; "c",
+    /// The second component of the translation vector
+    TRY<i32>; COP: 2; R: 38; "c",
+    /// The third component of the translation vector
+    TRZ<i32>; COP: 2; R: 39; "c",
 
     /// Light matrix entries L11 and L12
-    L11_12<u32>; COP: 2; R: 40,
+    L11_12<u32>; COP: 2; R: 40; "c",
     /// Light matrix entries L13 and L21
-    L13_21<u32>; COP: 2; R: 41,
+    L13_21<u32>; COP: 2; R: 41; "c",
     /// Light matrix entries L22 and L23
-    L22_23<u32>; COP: 2; R: 42,
+    L22_23<u32>; COP: 2; R: 42; "c",
     /// Light matrix entries L31 and L32
-    L31_32<u32>; COP: 2; R: 43,
+    L31_32<u32>; COP: 2; R: 43; "c",
     /// Light matrix entry L33
-    L33<i16>;    COP: 2; R: 44,
+    L33<i16>;    COP: 2; R: 44; "c",
+
+    /// The red component of the background color
+    RBK<i32>; COP: 2; R: 45; "c",
+    /// The green component of the background color
+    GBK<i32>; COP: 2; R: 46; "c",
+    /// The blue component of the background color
+    BBK<i32>; COP: 2; R: 47; "c",
 
     /// Light color matrix entries LR11 and LR12
-    LR11_12<u32>; COP: 2; R: 48,
+    LR11_12<u32>; COP: 2; R: 48; "c",
     /// Light color matrix entries LR13 and LR21
-    LR13_21<u32>; COP: 2; R: 49,
+    LR13_21<u32>; COP: 2; R: 49; "c",
     /// Light color matrix entries LR22 and LR23
-    LR22_23<u32>; COP: 2; R: 50,
+    LR22_23<u32>; COP: 2; R: 50; "c",
     /// Light color matrix entries LR31 and LR32
-    LR31_32<u32>; COP: 2; R: 51,
+    LR31_32<u32>; COP: 2; R: 51; "c",
     /// Light color matrix entry LR33
-    LR33<i16>; COP: 2; R: 52,
-    */
+    LR33<i16>;    COP: 2; R: 52; "c",
+
+    /// The red component of the far color
+    RFC<i32>; COP: 2; R: 53; "c",
+    /// The green component of the far color
+    GFC<i32>; COP: 2; R: 54; "c",
+    /// The blue component of the far color
+    BFC<i32>; COP: 2; R: 55; "c",
+
+    /// The screen X offset
+    OFX<i32>; COP: 2; R: 56; "c",
+    /// The screen Y offset
+    OFY<i32>; COP: 2; R: 57; "c",
+    /// The projection plane distance
+    H<u16>;   COP: 2; R: 58; "c",
+    /// The depth cueing coefficient
+    DQA<i16>; COP: 2; R: 59; "c",
+    /// The depth cueing offset
+    DQB<i32>; COP: 2; R: 60; "c",
+
+    /// The Z scale factor used by AVSZ3
+    ZSF3<i16>; COP: 2; R: 61; "c",
+    /// The Z scale factor used by AVSZ4
+    ZSF4<i16>; COP: 2; R: 62; "c",
+    /// The calculation error flags
+    FLAG<u32>; COP: 2; R: 63; "c",
+}
+
+/// The opcode of GTE commands.
+const COP2: u32 = 0x4A00_0000;
+/// Shifts results right by 12 bits.
+const SF: u32 = 1 << 19;
+/// Saturates IR1, IR2 and IR3 to 0 instead of -0x8000.
+const LM: u32 = 1 << 10;
+
+// The commands' function numbers including the bits ignored by the GTE
+const RTPS: u32 = 0x010_0001;
+const NCLIP: u32 = 0x140_0006;
+const OP: u32 = 0x170_000C;
+const DPCS: u32 = 0x070_0010;
+const INTPL: u32 = 0x090_0011;
+const MVMVA: u32 = 0x040_0012;
+const NCDS: u32 = 0x0E0_0013;
+const CDP: u32 = 0x120_0014;
+const NCDT: u32 = 0x0F0_0016;
+const NCCS: u32 = 0x100_001B;
+const CC: u32 = 0x130_001C;
+const NCS: u32 = 0x0C0_001E;
+const NCT: u32 = 0x0D0_0020;
+const SQR: u32 = 0x0A0_0028;
+const DCPL: u32 = 0x060_0029;
+const DPCT: u32 = 0x0F0_002A;
+const AVSZ3: u32 = 0x150_002D;
+const AVSZ4: u32 = 0x160_002E;
+const RTPT: u32 = 0x020_0030;
+const GPF: u32 = 0x190_003D;
+const GPL: u32 = 0x1A0_003E;
+const NCCT: u32 = 0x110_003F;
+
+/// Encodes a GTE command instruction.
+const fn command(cmd: u32, sf: bool, lm: bool) -> u32 {
+    COP2 | cmd | (if sf { SF } else { 0 }) | (if lm { LM } else { 0 })
+}
+
+/// Encodes an MVMVA instruction.
+const fn mvmva_command(sf: bool, lm: bool, mx: u32, v: u32, cv: u32) -> u32 {
+    command(MVMVA | (mx << 17) | (v << 15) | (cv << 13), sf, lm)
+}
+
+/// Runs a GTE command.
+///
+/// The GTE applies writes to its registers two cycles late, so the command
+/// is preceded by two `nop`s. Reading a result register stalls until the
+/// command completes.
+macro_rules! run {
+    ($cmd:expr) => {
+        unsafe {
+            core::arch::asm! {
+                "nop",
+                "nop",
+                ".word {cmd}",
+                cmd = const $cmd,
+                options(nomem, nostack)
+            }
+        }
+    };
+}
+
+/// Perspective transformation of V0 into SXY2 and SZ3.
+pub fn rtps() {
+    run!(command(RTPS, true, false))
+}
+
+/// Perspective transformation of V0, V1 and V2 into the screen coordinate
+/// and Z FIFOs.
+pub fn rtpt() {
+    run!(command(RTPT, true, false))
+}
+
+/// Normal clipping, which stores the cross product of the screen
+/// coordinates in MAC0.
+pub fn nclip() {
+    run!(command(NCLIP, false, false))
+}
+
+/// Outer product of the rotation matrix's diagonal and IR1, IR2 and IR3.
+pub fn op<const SF: bool>() {
+    run!(command(OP, SF, false))
+}
+
+/// Depth cueing of RGBC toward the far color.
+pub fn dpcs() {
+    run!(command(DPCS, true, false))
+}
+
+/// Depth cueing of the three colors in the RGB FIFO.
+pub fn dpct() {
+    run!(command(DPCT, true, false))
+}
+
+/// Interpolation of IR1, IR2 and IR3 toward the far color.
+pub fn intpl() {
+    run!(command(INTPL, true, false))
+}
+
+/// Multiplies a vector by a matrix and adds a translation vector.
+///
+/// `MX` selects the rotation (0), light (1) or light color (2) matrix. `V`
+/// selects V0 to V2 (0 to 2) or IR1, IR2 and IR3 (3). `CV` selects the
+/// translation (0), background color (1) or far color (2) vector, or no
+/// vector (3).
+pub fn mvmva<const SF: bool, const LM: bool, const MX: u32, const V: u32, const CV: u32>() {
+    run!(mvmva_command(SF, LM, MX, V, CV))
+}
+
+/// Normal color depth cue of V0.
+pub fn ncds() {
+    run!(command(NCDS, true, true))
+}
+
+/// Normal color depth cue of V0, V1 and V2.
+pub fn ncdt() {
+    run!(command(NCDT, true, true))
+}
+
+/// Normal color color of V0.
+pub fn nccs() {
+    run!(command(NCCS, true, true))
+}
+
+/// Normal color color of V0, V1 and V2.
+pub fn ncct() {
+    run!(command(NCCT, true, true))
+}
+
+/// Normal color of V0.
+pub fn ncs() {
+    run!(command(NCS, true, true))
+}
+
+/// Normal color of V0, V1 and V2.
+pub fn nct() {
+    run!(command(NCT, true, true))
+}
+
+/// Color depth cue of IR1, IR2 and IR3.
+pub fn cdp() {
+    run!(command(CDP, true, true))
+}
+
+/// Color color of IR1, IR2 and IR3.
+pub fn cc() {
+    run!(command(CC, true, true))
+}
+
+/// Squares IR1, IR2 and IR3.
+pub fn sqr<const SF: bool>() {
+    run!(command(SQR, SF, true))
+}
+
+/// Depth cueing of RGBC multiplied by IR1, IR2 and IR3.
+pub fn dcpl() {
+    run!(command(DCPL, true, false))
+}
+
+/// Averages SZ1, SZ2 and SZ3 scaled by ZSF3 into OTZ.
+pub fn avsz3() {
+    run!(command(AVSZ3, true, false))
+}
+
+/// Averages SZ0, SZ1, SZ2 and SZ3 scaled by ZSF4 into OTZ.
+pub fn avsz4() {
+    run!(command(AVSZ4, true, false))
+}
+
+/// General purpose interpolation of IR1, IR2 and IR3 by IR0.
+pub fn gpf<const SF: bool>() {
+    run!(command(GPF, SF, false))
+}
+
+/// General purpose interpolation of IR1, IR2 and IR3 by IR0 with the MAC
+/// registers as the base.
+pub fn gpl<const SF: bool>() {
+    run!(command(GPL, SF, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{command, mvmva_command, AVSZ3, NCDS, NCLIP, OP, RTPS, RTPT, SQR};
+    use crate::hw::cop::{encode_move, CF, CT};
+
+    #[test_case]
+    fn commands() {
+        assert!(command(RTPS, true, false) == 0x4A18_0001);
+        assert!(command(RTPT, true, false) == 0x4A28_0030);
+        assert!(command(NCLIP, false, false) == 0x4B40_0006);
+        assert!(command(OP, true, false) == 0x4B78_000C);
+        assert!(command(NCDS, true, true) == 0x4AE8_0413);
+        assert!(command(SQR, false, true) == 0x4AA0_0428);
+        assert!(command(AVSZ3, true, false) == 0x4B58_002D);
+        assert!(mvmva_command(true, false, 0, 0, 0) == 0x4A48_0012);
+        assert!(mvmva_command(true, true, 1, 3, 3) == 0x4A4B_E412);
+    }
+
+    #[test_case]
+    fn moves() {
+        // cfc2 $t0, $29 and ctc2 $t0, $0
+        assert!(encode_move(CF, 2, 29) == 0x4848_E800);
+        assert!(encode_move(CT, 2, 0) == 0x48C8_0000);
+    }
 }
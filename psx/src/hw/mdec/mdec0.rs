@@ -0,0 +1,48 @@
+use crate::hw::mdec::{pack_quant_table, pack_scale_table, Command, Status, MDEC0, TABLE_LEN};
+use crate::hw::Register;
+
+impl MDEC0 {
+    /// Sends a command.
+    ///
+    /// Its parameters must then be sent with [`MDEC0::send_parameters`] or
+    /// through DMA.
+    pub fn send_command(&mut self, cmd: Command) -> &mut Self {
+        self.assign(cmd.bits()).store()
+    }
+
+    /// Sends parameter words, waiting while the data-in FIFO is full.
+    pub fn send_parameters(&mut self, params: &[u32]) -> &mut Self {
+        let mut status = Status::new();
+        for &param in params {
+            while status.data_in_full() {
+                status.load();
+            }
+            self.assign(param).store();
+        }
+        self
+    }
+
+    /// Sets the luminance quantization table and the color one if it's
+    /// given.
+    pub fn set_quant_tables(
+        &mut self, luminance: &[u8; TABLE_LEN], color: Option<&[u8; TABLE_LEN]>,
+    ) -> &mut Self {
+        self.send_command(Command::set_quant_table(color.is_some()))
+            .send_parameters(&pack_quant_table(luminance));
+        if let Some(color) = color {
+            self.send_parameters(&pack_quant_table(color));
+        }
+        self
+    }
+
+    /// Sets the IDCT scale table.
+    pub fn set_scale_table(&mut self, table: &[i16; TABLE_LEN]) -> &mut Self {
+        self.send_command(Command::set_scale_table())
+            .send_parameters(&pack_scale_table(table))
+    }
+
+    /// Reads a word of decoded data.
+    pub fn read_data(&mut self) -> u32 {
+        self.load().to_bits()
+    }
+}
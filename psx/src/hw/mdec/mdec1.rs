@@ -0,0 +1,21 @@
+use crate::hw::mdec::MDEC1;
+use crate::hw::Register;
+
+const RESET: u32 = 31;
+const DATA_IN_REQUEST: u32 = 30;
+const DATA_OUT_REQUEST: u32 = 29;
+
+impl MDEC1 {
+    /// Resets the MDEC.
+    ///
+    /// Aborts the current command and disables DMA requests.
+    pub fn reset(&mut self) -> &mut Self {
+        self.assign(1 << RESET).store()
+    }
+
+    /// Enables DMA requests for the data-in and data-out channels.
+    pub fn enable_dma(&mut self, data_in: bool, data_out: bool) -> &mut Self {
+        self.assign((data_in as u32) << DATA_IN_REQUEST | (data_out as u32) << DATA_OUT_REQUEST)
+            .store()
+    }
+}
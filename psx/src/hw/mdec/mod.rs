@@ -0,0 +1,180 @@
+//! MDEC registers
+//!
+//! The macroblock decoder decompresses run-length encoded DCT macroblocks
+//! into 4, 8, 15 or 24-bit pixels. Commands and their parameters are written
+//! to [`MDEC0`], usually through the [`MDECIn`][crate::dma::MDECIn] DMA
+//! channel, and the decoded pixels are read from it through
+//! [`MDECOut`][crate::dma::MDECOut].
+use crate::hw::MemRegister;
+
+mod mdec0;
+mod mdec1;
+mod status;
+
+/// The command and parameter port, which returns decoded data when read.
+pub type MDEC0 = MemRegister<u32, 0x1F80_1820>;
+/// The control port used to reset the MDEC and enable DMA requests.
+pub type MDEC1 = MemRegister<u32, 0x1F80_1824>;
+// This is a struct rather than a type to allow overriding the derived Debug
+// impl.
+/// The MDEC status register.
+pub struct Status(MemRegister<u32, 0x1F80_1824>);
+
+/// The number of entries in a quantization or scale table.
+pub const TABLE_LEN: usize = 64;
+
+const OPCODE: u32 = 29;
+const DEPTH: u32 = 27;
+const SIGNED: u32 = 26;
+const SET_BIT15: u32 = 25;
+const COLOR: u32 = 0;
+
+const DECODE: u32 = 1;
+const SET_QUANT_TABLE: u32 = 2;
+const SET_SCALE_TABLE: u32 = 3;
+
+/// The depth of the pixels output by the decode macroblock command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Depth {
+    /// 4-bit monochrome
+    Bits4 = 0,
+    /// 8-bit monochrome
+    Bits8,
+    /// 24-bit color
+    Bits24,
+    /// 15-bit color
+    Bits15,
+}
+
+/// The block of a macroblock being decoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Block {
+    /// The top left luminance block
+    Y1 = 0,
+    /// The top right luminance block
+    Y2,
+    /// The bottom left luminance block
+    Y3,
+    /// The bottom right luminance block
+    Y4,
+    /// The red chrominance block or the only block in monochrome modes
+    Cr,
+    /// The blue chrominance block
+    Cb,
+}
+
+/// An MDEC command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Command(u32);
+
+impl Command {
+    /// Creates a command which decodes macroblocks from `words` words of run
+    /// length encoded data.
+    ///
+    /// The output defaults to unsigned 4-bit pixels.
+    pub const fn decode(words: u16) -> Self {
+        Command(DECODE << OPCODE | words as u32)
+    }
+
+    /// Creates a command which sets the luminance quantization table and
+    /// the color one if `color` is set.
+    ///
+    /// Each table is sent as 16 parameter words.
+    pub const fn set_quant_table(color: bool) -> Self {
+        Command(SET_QUANT_TABLE << OPCODE | (color as u32) << COLOR)
+    }
+
+    /// Creates a command which sets the IDCT scale table.
+    ///
+    /// The table is sent as 32 parameter words.
+    pub const fn set_scale_table() -> Self {
+        Command(SET_SCALE_TABLE << OPCODE)
+    }
+
+    /// Gets the command's bits.
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Gets the number of parameter words following the command.
+    pub const fn parameter_words(&self) -> usize {
+        match self.0 >> OPCODE {
+            DECODE => (self.0 & 0xFFFF) as usize,
+            SET_QUANT_TABLE => 16 << (self.0 >> COLOR & 1),
+            SET_SCALE_TABLE => 32,
+            _ => 0,
+        }
+    }
+
+    /// Sets the depth of the decoded pixels.
+    pub fn set_depth(&mut self, depth: Depth) -> &mut Self {
+        self.0 = (self.0 & !(0b11 << DEPTH)) | (depth as u32) << DEPTH;
+        self
+    }
+
+    /// Outputs signed pixels from -128 to 127 instead of unsigned ones from
+    /// 0 to 255. This only applies to 4 and 8-bit pixels.
+    pub fn set_signed(&mut self, signed: bool) -> &mut Self {
+        self.0 = (self.0 & !(1 << SIGNED)) | (signed as u32) << SIGNED;
+        self
+    }
+
+    /// Sets bit 15 of 15-bit pixels, which makes them semi-transparent or
+    /// masked when drawn.
+    pub fn set_bit15(&mut self, bit15: bool) -> &mut Self {
+        self.0 = (self.0 & !(1 << SET_BIT15)) | (bit15 as u32) << SET_BIT15;
+        self
+    }
+}
+
+/// Packs a quantization table in zigzag order into parameter words.
+pub fn pack_quant_table(table: &[u8; TABLE_LEN]) -> [u32; TABLE_LEN / 4] {
+    let mut words = [0; TABLE_LEN / 4];
+    for (word, bytes) in words.iter_mut().zip(table.chunks_exact(4)) {
+        *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    words
+}
+
+/// Packs a scale table into parameter words.
+pub fn pack_scale_table(table: &[i16; TABLE_LEN]) -> [u32; TABLE_LEN / 2] {
+    let mut words = [0; TABLE_LEN / 2];
+    for (word, halves) in words.iter_mut().zip(table.chunks_exact(2)) {
+        *word = (halves[0] as u16 as u32) | (halves[1] as u16 as u32) << 16;
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pack_quant_table, pack_scale_table, Command, Depth, TABLE_LEN};
+
+    #[test_case]
+    fn commands() {
+        let mut cmd = Command::decode(0x1234);
+        assert!(cmd.bits() == 0x2000_1234);
+        assert!(cmd.parameter_words() == 0x1234);
+        cmd.set_depth(Depth::Bits15).set_bit15(true);
+        assert!(cmd.bits() == 0x3A00_1234);
+        cmd.set_depth(Depth::Bits8)
+            .set_bit15(false)
+            .set_signed(true);
+        assert!(cmd.bits() == 0x2C00_1234);
+        assert!(Command::set_quant_table(false).bits() == 0x4000_0000);
+        assert!(Command::set_quant_table(false).parameter_words() == 16);
+        assert!(Command::set_quant_table(true).bits() == 0x4000_0001);
+        assert!(Command::set_quant_table(true).parameter_words() == 32);
+        assert!(Command::set_scale_table().bits() == 0x6000_0000);
+        assert!(Command::set_scale_table().parameter_words() == 32);
+    }
+
+    #[test_case]
+    fn tables() {
+        let mut quant = [0; TABLE_LEN];
+        quant[..4].copy_from_slice(&[1, 2, 3, 4]);
+        assert!(pack_quant_table(&quant)[0] == 0x0403_0201);
+        let mut scale = [0; TABLE_LEN];
+        scale[..2].copy_from_slice(&[0x5A82, -0x5A83]);
+        assert!(pack_scale_table(&scale)[0] == 0xA57D_5A82);
+    }
+}
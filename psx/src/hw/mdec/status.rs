@@ -0,0 +1,122 @@
+use crate::hw::mdec::{Block, Depth, Status};
+use crate::hw::{MemRegister, Register};
+use core::fmt;
+use core::fmt::{Debug, Formatter};
+
+const DATA_OUT_EMPTY: u32 = 31;
+const DATA_IN_FULL: u32 = 30;
+const BUSY: u32 = 29;
+const DATA_IN_REQUEST: u32 = 28;
+const DATA_OUT_REQUEST: u32 = 27;
+const DEPTH: u32 = 25;
+const SIGNED: u32 = 24;
+const BIT15: u32 = 23;
+const BLOCK: u32 = 16;
+
+impl Status {
+    /// Creates a new handle and immediately reads the register's value.
+    ///
+    /// This does a single volatile read.
+    pub fn new() -> Self {
+        Status(MemRegister::new())
+    }
+
+    /// Load the register's value into a cache.
+    ///
+    /// This does a single volatile read.
+    pub fn load(&mut self) -> &mut Self {
+        self.0.load();
+        self
+    }
+
+    /// Checks if the data-out FIFO is empty.
+    pub fn data_out_empty(&self) -> bool {
+        self.0.all_set(1 << DATA_OUT_EMPTY)
+    }
+
+    /// Checks if the data-in FIFO is full.
+    pub fn data_in_full(&self) -> bool {
+        self.0.all_set(1 << DATA_IN_FULL)
+    }
+
+    /// Checks if a command is being executed.
+    pub fn busy(&self) -> bool {
+        self.0.all_set(1 << BUSY)
+    }
+
+    /// Checks if the MDEC is requesting data-in DMA.
+    pub fn data_in_request(&self) -> bool {
+        self.0.all_set(1 << DATA_IN_REQUEST)
+    }
+
+    /// Checks if the MDEC is requesting data-out DMA.
+    pub fn data_out_request(&self) -> bool {
+        self.0.all_set(1 << DATA_OUT_REQUEST)
+    }
+
+    /// Gets the output depth of the current decode command.
+    pub fn depth(&self) -> Depth {
+        match (self.0.to_bits() >> DEPTH) & 0b11 {
+            0 => Depth::Bits4,
+            1 => Depth::Bits8,
+            2 => Depth::Bits24,
+            _ => Depth::Bits15,
+        }
+    }
+
+    /// Checks if the current decode command outputs signed pixels.
+    pub fn signed(&self) -> bool {
+        self.0.all_set(1 << SIGNED)
+    }
+
+    /// Checks if the current decode command sets bit 15 of 15-bit pixels.
+    pub fn bit15(&self) -> bool {
+        self.0.all_set(1 << BIT15)
+    }
+
+    /// Gets the block being decoded.
+    pub fn current_block(&self) -> Option<Block> {
+        match (self.0.to_bits() >> BLOCK) & 0b111 {
+            0 => Some(Block::Y1),
+            1 => Some(Block::Y2),
+            2 => Some(Block::Y3),
+            3 => Some(Block::Y4),
+            4 => Some(Block::Cr),
+            5 => Some(Block::Cb),
+            _ => None,
+        }
+    }
+
+    /// Gets the number of parameter words the current command still needs.
+    pub fn remaining_parameters(&self) -> usize {
+        // This field holds the number of words minus 1
+        (self.0.to_bits().wrapping_add(1) & 0xFFFF) as usize
+    }
+
+    /// Waits until the current command finishes. This loops and reloads
+    /// the register until it's done waiting.
+    pub fn wait_idle(&mut self) -> &mut Self {
+        while self.busy() {
+            self.0.load();
+        }
+        self
+    }
+}
+
+impl Debug for Status {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MDEC1")
+            .field("bits", &self.0.to_bits())
+            .field("data_out_empty", &self.data_out_empty())
+            .field("data_in_full", &self.data_in_full())
+            .field("busy", &self.busy())
+            .field("data_in_request", &self.data_in_request())
+            .field("data_out_request", &self.data_out_request())
+            .field("depth", &self.depth())
+            .field("signed", &self.signed())
+            .field("bit15", &self.bit15())
+            .field("current_block", &self.current_block())
+            .field("remaining_parameters", &self.remaining_parameters())
+            .finish()
+    }
+}
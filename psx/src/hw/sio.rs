@@ -0,0 +1,419 @@
+//! Serial port (SIO1) registers
+//!
+//! Writing [`Control::ack`] resets the error and interrupt request bits in
+//! [`Status`].
+use crate::hw::{MemRegister, Register};
+
+const TX_READY: u32 = 0;
+const RX_READY: u32 = 1;
+const TX_IDLE: u32 = 2;
+const PARITY_ERROR: u32 = 3;
+const RX_OVERRUN: u32 = 4;
+const FRAMING_ERROR: u32 = 5;
+const RX_LEVEL: u32 = 6;
+const DSR: u32 = 7;
+const CTS: u32 = 8;
+const IRQ_REQUEST: u32 = 9;
+const BAUD_TIMER: u32 = 11;
+const BAUD_TIMER_MASK: u32 = 0x7FFF;
+
+const BAUD_FACTOR: u16 = 0;
+const CHAR_LENGTH: u16 = 2;
+const PARITY_ENABLE: u16 = 4;
+const PARITY_ODD: u16 = 5;
+const STOP_BITS: u16 = 6;
+
+const TX_ENABLE: u16 = 0;
+const DTR: u16 = 1;
+const RX_ENABLE: u16 = 2;
+const TX_BREAK: u16 = 3;
+const ACK: u16 = 4;
+const RTS: u16 = 5;
+const RESET: u16 = 6;
+const RX_IRQ_MODE: u16 = 8;
+const TX_IRQ: u16 = 10;
+const RX_IRQ: u16 = 11;
+const DSR_IRQ: u16 = 12;
+
+/// Serial port TX/RX data register.
+pub type Data = MemRegister<u8, 0x1F80_1050>;
+/// Serial port status register.
+pub type Status = MemRegister<u32, 0x1F80_1054>;
+/// Serial port mode register.
+pub type Mode = MemRegister<u16, 0x1F80_1058>;
+/// Serial port control register.
+pub type Control = MemRegister<u16, 0x1F80_105A>;
+/// Serial port baud rate reload register.
+pub type Baud = MemRegister<u16, 0x1F80_105E>;
+
+/// The multiplier applied to the [`Baud`] reload value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BaudFactor {
+    /// Stop the baud rate timer.
+    Stop = 0,
+    /// Multiply the reload value by 1.
+    Mul1,
+    /// Multiply the reload value by 16.
+    Mul16,
+    /// Multiply the reload value by 64.
+    Mul64,
+}
+
+impl BaudFactor {
+    /// Gets the factor's multiplier or zero for [`BaudFactor::Stop`].
+    pub const fn multiplier(self) -> u32 {
+        match self {
+            BaudFactor::Stop => 0,
+            BaudFactor::Mul1 => 1,
+            BaudFactor::Mul16 => 16,
+            BaudFactor::Mul64 => 64,
+        }
+    }
+}
+
+/// The number of data bits per character.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CharLength {
+    /// 5 data bits.
+    Bits5 = 0,
+    /// 6 data bits.
+    Bits6,
+    /// 7 data bits.
+    Bits7,
+    /// 8 data bits.
+    Bits8,
+}
+
+/// The parity bit type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parity {
+    /// Even parity.
+    Even = 0,
+    /// Odd parity.
+    Odd,
+}
+
+/// The number of stop bits per character.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopBits {
+    /// 1 stop bit.
+    One = 1,
+    /// 1.5 stop bits.
+    OneAndHalf,
+    /// 2 stop bits.
+    Two,
+}
+
+/// The number of bytes in the RX FIFO that triggers an RX interrupt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RxIRQMode {
+    /// Request an interrupt after each byte.
+    Bytes1 = 0,
+    /// Request an interrupt after 2 bytes.
+    Bytes2,
+    /// Request an interrupt after 4 bytes.
+    Bytes4,
+    /// Request an interrupt after 8 bytes.
+    Bytes8,
+}
+
+impl Data {
+    /// Gets the byte at the front of the RX FIFO.
+    pub fn get(&self) -> u8 {
+        self.to_bits()
+    }
+
+    /// Sets the byte to push to the TX FIFO.
+    pub fn set(&mut self, byte: u8) -> &mut Self {
+        self.assign(byte)
+    }
+}
+
+impl Status {
+    /// Checks if the TX FIFO can take another byte.
+    pub fn tx_ready(&self) -> bool {
+        self.all_set(1 << TX_READY)
+    }
+
+    /// Checks if the RX FIFO contains at least one byte.
+    pub fn rx_ready(&self) -> bool {
+        self.all_set(1 << RX_READY)
+    }
+
+    /// Checks if all bytes have been sent.
+    pub fn tx_idle(&self) -> bool {
+        self.all_set(1 << TX_IDLE)
+    }
+
+    /// Checks if a byte with the wrong parity was received.
+    pub fn parity_error(&self) -> bool {
+        self.all_set(1 << PARITY_ERROR)
+    }
+
+    /// Checks if a byte was received while the RX FIFO was full.
+    pub fn rx_overrun(&self) -> bool {
+        self.all_set(1 << RX_OVERRUN)
+    }
+
+    /// Checks if a byte with a bad stop bit was received.
+    pub fn framing_error(&self) -> bool {
+        self.all_set(1 << FRAMING_ERROR)
+    }
+
+    /// Gets the current level of the RXD input.
+    pub fn rx_level(&self) -> bool {
+        self.all_set(1 << RX_LEVEL)
+    }
+
+    /// Checks if the DSR input is asserted.
+    pub fn dsr(&self) -> bool {
+        self.all_set(1 << DSR)
+    }
+
+    /// Checks if the CTS input is asserted.
+    pub fn cts(&self) -> bool {
+        self.all_set(1 << CTS)
+    }
+
+    /// Checks if the serial port is requesting an interrupt.
+    pub fn irq_requested(&self) -> bool {
+        self.all_set(1 << IRQ_REQUEST)
+    }
+
+    /// Gets the baud rate timer's current value.
+    pub fn baud_timer(&self) -> u16 {
+        ((self.to_bits() >> BAUD_TIMER) & BAUD_TIMER_MASK) as u16
+    }
+
+    /// Waits until the TX FIFO can take another byte. This loops and reloads
+    /// the status register until it's done waiting.
+    pub fn wait_tx_ready(&mut self) -> &mut Self {
+        while !self.tx_ready() {
+            self.load();
+        }
+        self
+    }
+
+    /// Waits until all bytes have been sent. This loops and reloads the status
+    /// register until it's done waiting.
+    pub fn wait_tx_idle(&mut self) -> &mut Self {
+        while !self.tx_idle() {
+            self.load();
+        }
+        self
+    }
+
+    /// Waits until the RX FIFO contains a byte. This loops and reloads the
+    /// status register until it's done waiting.
+    pub fn wait_rx_ready(&mut self) -> &mut Self {
+        while !self.rx_ready() {
+            self.load();
+        }
+        self
+    }
+}
+
+impl Mode {
+    /// Gets the baud rate reload value multiplier.
+    pub fn get_baud_factor(&self) -> BaudFactor {
+        match (self.to_bits() >> BAUD_FACTOR) & 0b11 {
+            0 => BaudFactor::Stop,
+            1 => BaudFactor::Mul1,
+            2 => BaudFactor::Mul16,
+            _ => BaudFactor::Mul64,
+        }
+    }
+
+    /// Sets the baud rate reload value multiplier.
+    pub fn set_baud_factor(&mut self, factor: BaudFactor) -> &mut Self {
+        self.clear_bits(0b11 << BAUD_FACTOR)
+            .set_bits((factor as u16) << BAUD_FACTOR)
+    }
+
+    /// Gets the number of data bits per character.
+    pub fn get_char_length(&self) -> CharLength {
+        match (self.to_bits() >> CHAR_LENGTH) & 0b11 {
+            0 => CharLength::Bits5,
+            1 => CharLength::Bits6,
+            2 => CharLength::Bits7,
+            _ => CharLength::Bits8,
+        }
+    }
+
+    /// Sets the number of data bits per character.
+    pub fn set_char_length(&mut self, length: CharLength) -> &mut Self {
+        self.clear_bits(0b11 << CHAR_LENGTH)
+            .set_bits((length as u16) << CHAR_LENGTH)
+    }
+
+    /// Gets the parity type or `None` if parity is disabled.
+    pub fn get_parity(&self) -> Option<Parity> {
+        if self.all_clear(1 << PARITY_ENABLE) {
+            None
+        } else if self.all_set(1 << PARITY_ODD) {
+            Some(Parity::Odd)
+        } else {
+            Some(Parity::Even)
+        }
+    }
+
+    /// Sets the parity type or disables parity if `parity` is `None`.
+    pub fn set_parity(&mut self, parity: Option<Parity>) -> &mut Self {
+        self.clear_bits(1 << PARITY_ENABLE | 1 << PARITY_ODD);
+        match parity {
+            Some(parity) => self.set_bits(1 << PARITY_ENABLE | (parity as u16) << PARITY_ODD),
+            None => self,
+        }
+    }
+
+    /// Gets the number of stop bits per character.
+    pub fn get_stop_bits(&self) -> StopBits {
+        match (self.to_bits() >> STOP_BITS) & 0b11 {
+            2 => StopBits::OneAndHalf,
+            3 => StopBits::Two,
+            _ => StopBits::One,
+        }
+    }
+
+    /// Sets the number of stop bits per character.
+    pub fn set_stop_bits(&mut self, stop_bits: StopBits) -> &mut Self {
+        self.clear_bits(0b11 << STOP_BITS)
+            .set_bits((stop_bits as u16) << STOP_BITS)
+    }
+}
+
+impl Control {
+    fn set_bit(&mut self, bit: u16, set: bool) -> &mut Self {
+        if set {
+            self.set_bits(1 << bit)
+        } else {
+            self.clear_bits(1 << bit)
+        }
+    }
+
+    /// Checks if the transmitter is enabled.
+    pub fn tx_enabled(&self) -> bool {
+        self.all_set(1 << TX_ENABLE)
+    }
+
+    /// Enables or disables the transmitter.
+    ///
+    /// The transmitter only sends bytes while the CTS input is asserted.
+    pub fn enable_tx(&mut self, enable: bool) -> &mut Self {
+        self.set_bit(TX_ENABLE, enable)
+    }
+
+    /// Checks if the DTR output is asserted.
+    pub fn dtr(&self) -> bool {
+        self.all_set(1 << DTR)
+    }
+
+    /// Asserts or deasserts the DTR output.
+    pub fn set_dtr(&mut self, assert: bool) -> &mut Self {
+        self.set_bit(DTR, assert)
+    }
+
+    /// Checks if the receiver is enabled.
+    pub fn rx_enabled(&self) -> bool {
+        self.all_set(1 << RX_ENABLE)
+    }
+
+    /// Enables or disables the receiver.
+    pub fn enable_rx(&mut self, enable: bool) -> &mut Self {
+        self.set_bit(RX_ENABLE, enable)
+    }
+
+    /// Checks if the TXD output is forced low.
+    pub fn tx_break(&self) -> bool {
+        self.all_set(1 << TX_BREAK)
+    }
+
+    /// Forces the TXD output low to send a break or releases it.
+    pub fn set_tx_break(&mut self, brk: bool) -> &mut Self {
+        self.set_bit(TX_BREAK, brk)
+    }
+
+    /// Acknowledges the error and interrupt request bits in [`Status`] when
+    /// stored.
+    pub fn ack(&mut self) -> &mut Self {
+        self.set_bits(1 << ACK)
+    }
+
+    /// Checks if the RTS output is asserted.
+    pub fn rts(&self) -> bool {
+        self.all_set(1 << RTS)
+    }
+
+    /// Asserts or deasserts the RTS output.
+    pub fn set_rts(&mut self, assert: bool) -> &mut Self {
+        self.set_bit(RTS, assert)
+    }
+
+    /// Resets the serial port registers when stored.
+    pub fn reset(&mut self) -> &mut Self {
+        self.set_bits(1 << RESET)
+    }
+
+    /// Gets the number of received bytes which triggers an RX interrupt.
+    pub fn get_rx_irq_mode(&self) -> RxIRQMode {
+        match (self.to_bits() >> RX_IRQ_MODE) & 0b11 {
+            0 => RxIRQMode::Bytes1,
+            1 => RxIRQMode::Bytes2,
+            2 => RxIRQMode::Bytes4,
+            _ => RxIRQMode::Bytes8,
+        }
+    }
+
+    /// Sets the number of received bytes which triggers an RX interrupt.
+    pub fn set_rx_irq_mode(&mut self, mode: RxIRQMode) -> &mut Self {
+        self.clear_bits(0b11 << RX_IRQ_MODE)
+            .set_bits((mode as u16) << RX_IRQ_MODE)
+    }
+
+    /// Checks if an interrupt is requested when the TX FIFO is ready or idle.
+    pub fn tx_irq_enabled(&self) -> bool {
+        self.all_set(1 << TX_IRQ)
+    }
+
+    /// Enables or disables requesting an interrupt when the TX FIFO is ready
+    /// or idle.
+    pub fn tx_irq(&mut self, enable: bool) -> &mut Self {
+        self.set_bit(TX_IRQ, enable)
+    }
+
+    /// Checks if an interrupt is requested when bytes are received.
+    pub fn rx_irq_enabled(&self) -> bool {
+        self.all_set(1 << RX_IRQ)
+    }
+
+    /// Enables or disables requesting an interrupt when bytes are received.
+    pub fn rx_irq(&mut self, enable: bool) -> &mut Self {
+        self.set_bit(RX_IRQ, enable)
+    }
+
+    /// Checks if an interrupt is requested when the DSR input is asserted.
+    pub fn dsr_irq_enabled(&self) -> bool {
+        self.all_set(1 << DSR_IRQ)
+    }
+
+    /// Enables or disables requesting an interrupt when the DSR input is
+    /// asserted.
+    pub fn dsr_irq(&mut self, enable: bool) -> &mut Self {
+        self.set_bit(DSR_IRQ, enable)
+    }
+}
+
+impl Baud {
+    /// Gets the baud rate timer reload value.
+    pub fn get_reload(&self) -> u16 {
+        self.to_bits()
+    }
+
+    /// Sets the baud rate timer reload value.
+    ///
+    /// The baud rate is the system clock divided by the reload value and the
+    /// [`BaudFactor`] multiplier.
+    pub fn set_reload(&mut self, reload: u16) -> &mut Self {
+        self.assign(reload)
+    }
+}
@@ -0,0 +1,117 @@
+use crate::hw::spu::{Control, TransferMode};
+use crate::hw::Register;
+
+const CD_AUDIO: u16 = 0;
+const EXTERNAL_AUDIO: u16 = 1;
+const CD_AUDIO_REVERB: u16 = 2;
+const EXTERNAL_AUDIO_REVERB: u16 = 3;
+const TRANSFER_MODE: u16 = 4;
+const IRQ: u16 = 6;
+const REVERB: u16 = 7;
+const NOISE_STEP: u16 = 8;
+const NOISE_SHIFT: u16 = 10;
+const UNMUTE: u16 = 14;
+const ENABLE: u16 = 15;
+
+impl Control {
+    fn set_bit(&mut self, bit: u16, set: bool) -> &mut Self {
+        if set {
+            self.set_bits(1 << bit)
+        } else {
+            self.clear_bits(1 << bit)
+        }
+    }
+
+    /// Checks if the SPU is enabled.
+    pub fn enabled(&self) -> bool {
+        self.all_set(1 << ENABLE)
+    }
+
+    /// Enables or disables the SPU.
+    ///
+    /// Disabling the SPU stops all voices and the reverb unit, but doesn't
+    /// affect CD audio.
+    pub fn enable(&mut self, enable: bool) -> &mut Self {
+        self.set_bit(ENABLE, enable)
+    }
+
+    /// Checks if the SPU output is muted.
+    pub fn muted(&self) -> bool {
+        self.all_clear(1 << UNMUTE)
+    }
+
+    /// Mutes or unmutes the SPU output.
+    ///
+    /// Muting doesn't affect CD audio.
+    pub fn mute(&mut self, mute: bool) -> &mut Self {
+        self.set_bit(UNMUTE, !mute)
+    }
+
+    /// Sets the noise generator's frequency `shift` from `0` to `0xF` and
+    /// `step` from `0` to `3`.
+    pub fn set_noise(&mut self, shift: u8, step: u8) -> &mut Self {
+        self.clear_bits(0xF << NOISE_SHIFT | 0b11 << NOISE_STEP)
+            .set_bits(((shift as u16) & 0xF) << NOISE_SHIFT | ((step as u16) & 0b11) << NOISE_STEP)
+    }
+
+    /// Checks if the reverb unit is enabled.
+    pub fn reverb_enabled(&self) -> bool {
+        self.all_set(1 << REVERB)
+    }
+
+    /// Enables or disables the reverb unit.
+    ///
+    /// When disabled the reverb unit stops writing to its work area in SPU
+    /// RAM.
+    pub fn enable_reverb(&mut self, enable: bool) -> &mut Self {
+        self.set_bit(REVERB, enable)
+    }
+
+    /// Checks if the SPU RAM IRQ is enabled.
+    pub fn irq_enabled(&self) -> bool {
+        self.all_set(1 << IRQ)
+    }
+
+    /// Enables or disables the SPU RAM IRQ.
+    ///
+    /// Disabling the IRQ also acknowledges it.
+    pub fn enable_irq(&mut self, enable: bool) -> &mut Self {
+        self.set_bit(IRQ, enable)
+    }
+
+    /// Gets the SPU RAM transfer mode.
+    pub fn get_transfer_mode(&self) -> TransferMode {
+        match (self.to_bits() >> TRANSFER_MODE) & 0b11 {
+            0 => TransferMode::Stop,
+            1 => TransferMode::ManualWrite,
+            2 => TransferMode::DMAWrite,
+            _ => TransferMode::DMARead,
+        }
+    }
+
+    /// Sets the SPU RAM transfer mode.
+    pub fn set_transfer_mode(&mut self, mode: TransferMode) -> &mut Self {
+        self.clear_bits(0b11 << TRANSFER_MODE)
+            .set_bits((mode as u16) << TRANSFER_MODE)
+    }
+
+    /// Enables or disables CD audio input.
+    pub fn enable_cd_audio(&mut self, enable: bool) -> &mut Self {
+        self.set_bit(CD_AUDIO, enable)
+    }
+
+    /// Enables or disables reverb for CD audio input.
+    pub fn enable_cd_audio_reverb(&mut self, enable: bool) -> &mut Self {
+        self.set_bit(CD_AUDIO_REVERB, enable)
+    }
+
+    /// Enables or disables external audio input.
+    pub fn enable_external_audio(&mut self, enable: bool) -> &mut Self {
+        self.set_bit(EXTERNAL_AUDIO, enable)
+    }
+
+    /// Enables or disables reverb for external audio input.
+    pub fn enable_external_audio_reverb(&mut self, enable: bool) -> &mut Self {
+        self.set_bit(EXTERNAL_AUDIO_REVERB, enable)
+    }
+}
@@ -0,0 +1,377 @@
+//! Sound processing unit registers
+//!
+//! The SPU is connected to a 16-bit bus so 32-bit accesses to the registers in
+//! this module are split into two 16-bit accesses by the hardware.
+use crate::hw::private::Primitive;
+use crate::hw::{MemRegister, Register};
+use core::ptr::{read_volatile, write_volatile};
+
+mod control;
+pub mod reverb;
+mod status;
+mod voice;
+
+/// The number of SPU voices.
+pub const NUM_VOICES: usize = 24;
+
+/// All voices in a [`VoiceMask`] register.
+pub const ALL_VOICES: u32 = (1 << NUM_VOICES) - 1;
+
+/// The start of the voice register blocks.
+const VOICE_BASE: u32 = 0x1F80_1C00;
+/// The size of each voice register block in bytes.
+const VOICE_STRIDE: u32 = 0x10;
+
+/// SPU RAM addresses are stored in 8-byte units.
+const ADDRESS_SHIFT: u32 = 3;
+
+/// A register in one of the voice register blocks.
+///
+/// Unlike [`MemRegister`] the voice is usually only known at runtime so it's
+/// stored in the handle rather than in the type. This means voice registers
+/// are created with [`VoiceRegister::new`] or [`VoiceRegister::skip_load`]
+/// instead of through [`Register`], but otherwise work the same way.
+#[repr(C)]
+pub struct VoiceRegister<T: Primitive, const OFFSET: u32> {
+    value: T,
+    voice: u8,
+}
+
+impl<T: Primitive, const OFFSET: u32> AsRef<T> for VoiceRegister<T, OFFSET> {
+    fn as_ref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: Primitive, const OFFSET: u32> AsMut<T> for VoiceRegister<T, OFFSET> {
+    fn as_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: Primitive, const OFFSET: u32> VoiceRegister<T, OFFSET> {
+    /// Creates a new handle for `voice` without reading the register's value.
+    ///
+    /// This should not do any volatile reads. Panics if `voice` is not less
+    /// than [`NUM_VOICES`].
+    pub fn skip_load(voice: usize) -> Self {
+        assert!(voice < NUM_VOICES, "Invalid SPU voice");
+        Self {
+            value: T::from(0),
+            voice: voice as u8,
+        }
+    }
+
+    /// Creates a new handle for `voice` and immediately reads the register's
+    /// value.
+    ///
+    /// This does a single volatile read.
+    pub fn new(voice: usize) -> Self {
+        let mut reg = Self::skip_load(voice);
+        reg.load();
+        reg
+    }
+
+    /// Gets the voice this register belongs to.
+    pub fn voice(&self) -> usize {
+        self.voice as usize
+    }
+
+    fn address(&self) -> u32 {
+        VOICE_BASE + (self.voice as u32 * VOICE_STRIDE) + OFFSET
+    }
+
+    /// Load the register's value into a cache.
+    ///
+    /// This does a single volatile read.
+    pub fn load(&mut self) -> &mut Self {
+        self.value = unsafe { read_volatile(self.address() as *const T) };
+        self
+    }
+
+    /// Store the cached value in the register.
+    ///
+    /// This does a single volatile write.
+    pub fn store(&mut self) -> &mut Self {
+        unsafe { write_volatile(self.address() as *mut T, self.value) }
+        self
+    }
+
+    /// Gets the cached value.
+    pub fn to_bits(&self) -> T {
+        self.value
+    }
+
+    /// Sets the cached value to `bits`.
+    pub fn assign(&mut self, bits: T) -> &mut Self {
+        self.value = bits;
+        self
+    }
+}
+
+/// A voice's left volume register.
+pub type VolumeLeft = VoiceRegister<u16, 0x0>;
+/// A voice's right volume register.
+pub type VolumeRight = VoiceRegister<u16, 0x2>;
+/// A voice's ADPCM sample rate register.
+///
+/// A value of `0x1000` plays samples back at 44100 Hz.
+pub type Pitch = VoiceRegister<u16, 0x4>;
+/// A voice's ADPCM start address register.
+pub type StartAddress = VoiceRegister<u16, 0x6>;
+/// A voice's attack, decay, sustain and release register.
+///
+/// The lower half contains the attack, decay and sustain level and the upper
+/// half contains the sustain and release.
+pub type ADSR = VoiceRegister<u32, 0x8>;
+/// A voice's current ADSR volume register.
+pub type CurrentVolume = VoiceRegister<i16, 0xC>;
+/// A voice's ADPCM repeat address register.
+pub type RepeatAddress = VoiceRegister<u16, 0xE>;
+
+/// Main volume left register
+pub type MainVolumeLeft = MemRegister<u16, 0x1F80_1D80>;
+/// Main volume right register
+pub type MainVolumeRight = MemRegister<u16, 0x1F80_1D82>;
+/// Reverb output volume left register
+pub type ReverbVolumeLeft = MemRegister<u16, 0x1F80_1D84>;
+/// Reverb output volume right register
+pub type ReverbVolumeRight = MemRegister<u16, 0x1F80_1D86>;
+
+/// Voice key on register
+///
+/// Setting a voice's bit starts its attack, decay and sustain phases.
+pub type KeyOn = MemRegister<u32, 0x1F80_1D88>;
+/// Voice key off register
+///
+/// Setting a voice's bit starts its release phase.
+pub type KeyOff = MemRegister<u32, 0x1F80_1D8C>;
+/// Voice pitch modulation enable register
+///
+/// Voice 0 can't be pitch modulated.
+pub type PitchModulation = MemRegister<u32, 0x1F80_1D90>;
+/// Voice noise mode enable register
+pub type Noise = MemRegister<u32, 0x1F80_1D94>;
+/// Voice reverb enable register
+pub type ReverbEnable = MemRegister<u32, 0x1F80_1D98>;
+/// Voice ended register
+///
+/// A voice's bit is set when it reaches an ADPCM block with the loop end flag
+/// and cleared when it's keyed on.
+pub type VoiceStatus = MemRegister<u32, 0x1F80_1D9C>;
+
+/// SPU RAM IRQ address register
+pub type IRQAddress = MemRegister<u16, 0x1F80_1DA4>;
+/// SPU RAM data transfer address register
+pub type TransferAddress = MemRegister<u16, 0x1F80_1DA6>;
+/// SPU RAM data transfer FIFO
+pub type TransferFifo = MemRegister<u16, 0x1F80_1DA8>;
+/// SPU control register (SPUCNT)
+pub type Control = MemRegister<u16, 0x1F80_1DAA>;
+/// SPU RAM data transfer control register
+pub type TransferControl = MemRegister<u16, 0x1F80_1DAC>;
+// This is a struct rather than a type to allow overriding the derived Debug
+// impl.
+/// SPU status register (SPUSTAT)
+pub struct Status(MemRegister<u16, 0x1F80_1DAE>);
+
+/// CD audio input volume left register
+pub type CDVolumeLeft = MemRegister<u16, 0x1F80_1DB0>;
+/// CD audio input volume right register
+pub type CDVolumeRight = MemRegister<u16, 0x1F80_1DB2>;
+/// External audio input volume left register
+pub type ExternalVolumeLeft = MemRegister<u16, 0x1F80_1DB4>;
+/// External audio input volume right register
+pub type ExternalVolumeRight = MemRegister<u16, 0x1F80_1DB6>;
+
+/// The SPU RAM transfer mode in [`Control`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferMode {
+    /// No transfer.
+    Stop = 0,
+    /// Manual writes through [`TransferFifo`].
+    ManualWrite,
+    /// Writes through the [`dma::SPU`][`crate::dma::SPU`] channel.
+    DMAWrite,
+    /// Reads through the [`dma::SPU`][`crate::dma::SPU`] channel.
+    DMARead,
+}
+
+/// The rate at which a volume sweep changes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SweepMode {
+    /// The volume changes linearly.
+    Linear = 0,
+    /// The volume changes exponentially.
+    Exponential,
+}
+
+/// The direction a volume sweep changes in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SweepDirection {
+    /// The volume increases.
+    Increase = 0,
+    /// The volume decreases.
+    Decrease,
+}
+
+/// A volume sweep envelope.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sweep {
+    /// The sweep mode.
+    pub mode: SweepMode,
+    /// The sweep direction.
+    pub direction: SweepDirection,
+    /// Inverts the phase of the output if set.
+    pub negative_phase: bool,
+    /// The sweep shift from `0` (fastest) to `0x1F` (slowest).
+    pub shift: u8,
+    /// The sweep step from `0` to `3` which is added to `7`, or subtracted
+    /// from `-8` when decreasing.
+    pub step: u8,
+}
+
+const SWEEP: u16 = 15;
+const SWEEP_MODE: u16 = 14;
+const SWEEP_DIRECTION: u16 = 13;
+const SWEEP_PHASE: u16 = 12;
+const SWEEP_SHIFT: u16 = 2;
+
+/// A volume register which may be set to either a fixed volume or a sweep.
+///
+/// This is implemented for voice volumes, the main volume and the reverb, CD
+/// audio and external audio volumes. Note that only the voice and main volumes
+/// support sweeps.
+pub trait Volume: AsRef<u16> + AsMut<u16> {
+    /// Gets the fixed volume, returning `None` if the register is in sweep
+    /// mode.
+    fn get_fixed(&self) -> Option<i16> {
+        let bits = *self.as_ref();
+        if bits & (1 << SWEEP) != 0 {
+            None
+        } else {
+            // Shifting out the sweep bit sign-extends the 15-bit volume
+            Some((bits << 1) as i16)
+        }
+    }
+
+    /// Sets a fixed volume ranging from `-0x8000` to `0x7FFF`.
+    ///
+    /// The volume register only holds 15 bits so the least significant bit is
+    /// dropped.
+    fn set_fixed(&mut self, volume: i16) -> &mut Self {
+        *self.as_mut() = (volume >> 1) as u16 & !(1 << SWEEP);
+        self
+    }
+
+    /// Gets the volume sweep, returning `None` if the register is set to a
+    /// fixed volume.
+    fn get_sweep(&self) -> Option<Sweep> {
+        let bits = *self.as_ref();
+        if bits & (1 << SWEEP) == 0 {
+            return None
+        }
+        let mode = if bits & (1 << SWEEP_MODE) != 0 {
+            SweepMode::Exponential
+        } else {
+            SweepMode::Linear
+        };
+        let direction = if bits & (1 << SWEEP_DIRECTION) != 0 {
+            SweepDirection::Decrease
+        } else {
+            SweepDirection::Increase
+        };
+        Some(Sweep {
+            mode,
+            direction,
+            negative_phase: bits & (1 << SWEEP_PHASE) != 0,
+            shift: ((bits >> SWEEP_SHIFT) & 0x1F) as u8,
+            step: (bits & 0b11) as u8,
+        })
+    }
+
+    /// Sets a volume sweep.
+    fn set_sweep(&mut self, sweep: Sweep) -> &mut Self {
+        *self.as_mut() = 1 << SWEEP |
+            (sweep.mode as u16) << SWEEP_MODE |
+            (sweep.direction as u16) << SWEEP_DIRECTION |
+            (sweep.negative_phase as u16) << SWEEP_PHASE |
+            ((sweep.shift as u16) & 0x1F) << SWEEP_SHIFT |
+            (sweep.step as u16) & 0b11;
+        self
+    }
+}
+
+impl Volume for VolumeLeft {}
+impl Volume for VolumeRight {}
+impl Volume for MainVolumeLeft {}
+impl Volume for MainVolumeRight {}
+impl Volume for ReverbVolumeLeft {}
+impl Volume for ReverbVolumeRight {}
+impl Volume for CDVolumeLeft {}
+impl Volume for CDVolumeRight {}
+impl Volume for ExternalVolumeLeft {}
+impl Volume for ExternalVolumeRight {}
+
+/// A register with one bit for each voice.
+pub trait VoiceMask: Register<u32> {
+    /// Checks if the voice's bit is set.
+    fn contains(&self, voice: usize) -> bool {
+        self.all_set(1 << voice)
+    }
+
+    /// Sets the voice's bit.
+    fn add_voice(&mut self, voice: usize) -> &mut Self {
+        self.set_bits(1 << voice)
+    }
+
+    /// Clears the voice's bit.
+    fn remove_voice(&mut self, voice: usize) -> &mut Self {
+        self.clear_bits(1 << voice)
+    }
+
+    /// Sets the bits for all voices.
+    fn add_all(&mut self) -> &mut Self {
+        self.set_bits(ALL_VOICES)
+    }
+
+    /// Clears the bits for all voices.
+    fn remove_all(&mut self) -> &mut Self {
+        self.clear_bits(ALL_VOICES)
+    }
+}
+
+impl VoiceMask for KeyOn {}
+impl VoiceMask for KeyOff {}
+impl VoiceMask for PitchModulation {}
+impl VoiceMask for Noise {}
+impl VoiceMask for ReverbEnable {}
+impl VoiceMask for VoiceStatus {}
+
+/// A register containing an address in SPU RAM.
+///
+/// Addresses are stored in 8-byte units so the lower 3 bits are dropped.
+pub trait RAMAddress: AsRef<u16> + AsMut<u16> {
+    /// Gets the address in bytes.
+    fn get_address(&self) -> u32 {
+        (*self.as_ref() as u32) << ADDRESS_SHIFT
+    }
+
+    /// Sets the address in bytes.
+    fn set_address(&mut self, address: u32) -> &mut Self {
+        *self.as_mut() = (address >> ADDRESS_SHIFT) as u16;
+        self
+    }
+}
+
+impl RAMAddress for StartAddress {}
+impl RAMAddress for RepeatAddress {}
+impl RAMAddress for IRQAddress {}
+impl RAMAddress for TransferAddress {}
+
+impl TransferControl {
+    /// Sets the normal transfer type used for DMA and manual writes.
+    pub fn set_normal(&mut self) -> &mut Self {
+        self.assign(0x0004)
+    }
+}
@@ -0,0 +1,76 @@
+//! Reverb unit registers
+//!
+//! Names in brackets are the ones used in the nocash PSX specs. Addresses are
+//! in 8-byte units relative to the start of the work area and volumes are
+//! signed.
+use crate::hw::spu::RAMAddress;
+use crate::hw::MemRegister;
+
+/// Reverb work area start address register (mBASE)
+pub type WorkArea = MemRegister<u16, 0x1F80_1DA2>;
+impl RAMAddress for WorkArea {}
+
+/// All-pass filter 1 offset register (dAPF1)
+pub type APFOffset1 = MemRegister<u16, 0x1F80_1DC0>;
+/// All-pass filter 2 offset register (dAPF2)
+pub type APFOffset2 = MemRegister<u16, 0x1F80_1DC2>;
+/// Reflection volume 1 register (vIIR)
+pub type ReflectionVolume1 = MemRegister<u16, 0x1F80_1DC4>;
+/// Comb volume 1 register (vCOMB1)
+pub type CombVolume1 = MemRegister<u16, 0x1F80_1DC6>;
+/// Comb volume 2 register (vCOMB2)
+pub type CombVolume2 = MemRegister<u16, 0x1F80_1DC8>;
+/// Comb volume 3 register (vCOMB3)
+pub type CombVolume3 = MemRegister<u16, 0x1F80_1DCA>;
+/// Comb volume 4 register (vCOMB4)
+pub type CombVolume4 = MemRegister<u16, 0x1F80_1DCC>;
+/// Reflection volume 2 register (vWALL)
+pub type ReflectionVolume2 = MemRegister<u16, 0x1F80_1DCE>;
+/// All-pass filter 1 volume register (vAPF1)
+pub type APFVolume1 = MemRegister<u16, 0x1F80_1DD0>;
+/// All-pass filter 2 volume register (vAPF2)
+pub type APFVolume2 = MemRegister<u16, 0x1F80_1DD2>;
+/// Same side reflection 1 address left register (mLSAME)
+pub type SameReflection1Left = MemRegister<u16, 0x1F80_1DD4>;
+/// Same side reflection 1 address right register (mRSAME)
+pub type SameReflection1Right = MemRegister<u16, 0x1F80_1DD6>;
+/// Comb 1 address left register (mLCOMB1)
+pub type Comb1Left = MemRegister<u16, 0x1F80_1DD8>;
+/// Comb 1 address right register (mRCOMB1)
+pub type Comb1Right = MemRegister<u16, 0x1F80_1DDA>;
+/// Comb 2 address left register (mLCOMB2)
+pub type Comb2Left = MemRegister<u16, 0x1F80_1DDC>;
+/// Comb 2 address right register (mRCOMB2)
+pub type Comb2Right = MemRegister<u16, 0x1F80_1DDE>;
+/// Same side reflection 2 address left register (dLSAME)
+pub type SameReflection2Left = MemRegister<u16, 0x1F80_1DE0>;
+/// Same side reflection 2 address right register (dRSAME)
+pub type SameReflection2Right = MemRegister<u16, 0x1F80_1DE2>;
+/// Different side reflection 1 address left register (mLDIFF)
+pub type DiffReflection1Left = MemRegister<u16, 0x1F80_1DE4>;
+/// Different side reflection 1 address right register (mRDIFF)
+pub type DiffReflection1Right = MemRegister<u16, 0x1F80_1DE6>;
+/// Comb 3 address left register (mLCOMB3)
+pub type Comb3Left = MemRegister<u16, 0x1F80_1DE8>;
+/// Comb 3 address right register (mRCOMB3)
+pub type Comb3Right = MemRegister<u16, 0x1F80_1DEA>;
+/// Comb 4 address left register (mLCOMB4)
+pub type Comb4Left = MemRegister<u16, 0x1F80_1DEC>;
+/// Comb 4 address right register (mRCOMB4)
+pub type Comb4Right = MemRegister<u16, 0x1F80_1DEE>;
+/// Different side reflection 2 address left register (dLDIFF)
+pub type DiffReflection2Left = MemRegister<u16, 0x1F80_1DF0>;
+/// Different side reflection 2 address right register (dRDIFF)
+pub type DiffReflection2Right = MemRegister<u16, 0x1F80_1DF2>;
+/// All-pass filter 1 address left register (mLAPF1)
+pub type APF1Left = MemRegister<u16, 0x1F80_1DF4>;
+/// All-pass filter 1 address right register (mRAPF1)
+pub type APF1Right = MemRegister<u16, 0x1F80_1DF6>;
+/// All-pass filter 2 address left register (mLAPF2)
+pub type APF2Left = MemRegister<u16, 0x1F80_1DF8>;
+/// All-pass filter 2 address right register (mRAPF2)
+pub type APF2Right = MemRegister<u16, 0x1F80_1DFA>;
+/// Input volume left register (vLIN)
+pub type InputVolumeLeft = MemRegister<u16, 0x1F80_1DFC>;
+/// Input volume right register (vRIN)
+pub type InputVolumeRight = MemRegister<u16, 0x1F80_1DFE>;
@@ -0,0 +1,110 @@
+use crate::hw::spu::{Status, TransferMode};
+use crate::hw::{MemRegister, Register};
+use core::fmt;
+use core::fmt::{Debug, Formatter};
+
+const MODE: u16 = 0x3F;
+const TRANSFER_MODE: u16 = 4;
+const IRQ: u16 = 6;
+const DMA_REQUEST: u16 = 7;
+const DMA_WRITE_REQUEST: u16 = 8;
+const DMA_READ_REQUEST: u16 = 9;
+const TRANSFER_BUSY: u16 = 10;
+const CAPTURE_HALF: u16 = 11;
+
+impl Status {
+    /// Creates a new handle and immediately reads the register's value.
+    ///
+    /// This does a single volatile read.
+    pub fn new() -> Self {
+        Status(MemRegister::new())
+    }
+
+    /// Load the register's value into a cache.
+    ///
+    /// This does a single volatile read.
+    pub fn load(&mut self) -> &mut Self {
+        self.0.load();
+        self
+    }
+
+    /// Gets the bits that were last applied from [`Control`][`super::Control`]
+    /// bits 0 to 5.
+    pub fn mode(&self) -> u16 {
+        self.0.to_bits() & MODE
+    }
+
+    /// Gets the SPU RAM transfer mode that was last applied.
+    pub fn transfer_mode(&self) -> TransferMode {
+        match (self.0.to_bits() >> TRANSFER_MODE) & 0b11 {
+            0 => TransferMode::Stop,
+            1 => TransferMode::ManualWrite,
+            2 => TransferMode::DMAWrite,
+            _ => TransferMode::DMARead,
+        }
+    }
+
+    /// Checks if the SPU RAM IRQ was requested.
+    pub fn irq_pending(&self) -> bool {
+        self.0.all_set(1 << IRQ)
+    }
+
+    /// Checks the DMA read/write request bit.
+    pub fn dma_request(&self) -> bool {
+        self.0.all_set(1 << DMA_REQUEST)
+    }
+
+    /// Checks the DMA write request bit.
+    pub fn dma_write_request(&self) -> bool {
+        self.0.all_set(1 << DMA_WRITE_REQUEST)
+    }
+
+    /// Checks the DMA read request bit.
+    pub fn dma_read_request(&self) -> bool {
+        self.0.all_set(1 << DMA_READ_REQUEST)
+    }
+
+    /// Checks if an SPU RAM transfer is in progress.
+    pub fn transfer_busy(&self) -> bool {
+        self.0.all_set(1 << TRANSFER_BUSY)
+    }
+
+    /// Checks if the SPU is writing to the second half of the capture buffers.
+    pub fn capture_second_half(&self) -> bool {
+        self.0.all_set(1 << CAPTURE_HALF)
+    }
+
+    /// Waits until the transfer mode in [`Control`][`super::Control`] is
+    /// applied. This loops and reloads the SPUSTAT register until it's done
+    /// waiting.
+    pub fn wait_mode(&mut self, mode: TransferMode) -> &mut Self {
+        while self.transfer_mode() != mode {
+            self.0.load();
+        }
+        self
+    }
+
+    /// Waits until the SPU RAM transfer is done. This loops and reloads the
+    /// SPUSTAT register until it's done waiting.
+    pub fn wait_transfer(&mut self) -> &mut Self {
+        while self.transfer_busy() {
+            self.0.load();
+        }
+        self
+    }
+}
+
+impl Debug for Status {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SPUSTAT")
+            .field("bits", &self.0.to_bits())
+            .field("transfer_mode", &self.transfer_mode())
+            .field("irq_pending", &self.irq_pending())
+            .field("dma_request", &self.dma_request())
+            .field("dma_write_request", &self.dma_write_request())
+            .field("dma_read_request", &self.dma_read_request())
+            .field("transfer_busy", &self.transfer_busy())
+            .field("capture_second_half", &self.capture_second_half())
+            .finish()
+    }
+}
@@ -0,0 +1,32 @@
+use crate::hw::spu::{Pitch, ADSR};
+use crate::spu::Envelope;
+
+/// The sample rate corresponding to a pitch of `0x1000`.
+const BASE_SAMPLE_RATE: u32 = 44_100;
+/// The largest pitch the SPU uses. Larger values are treated as this value.
+const MAX_PITCH: u32 = 0x3FFF;
+
+impl Pitch {
+    /// Gets the sample rate in Hz.
+    pub fn get_sample_rate(&self) -> u32 {
+        (self.to_bits() as u32 * BASE_SAMPLE_RATE) >> 12
+    }
+
+    /// Sets the sample rate in Hz, saturating at about 176 kHz.
+    pub fn set_sample_rate(&mut self, hz: u32) -> &mut Self {
+        let pitch = (((hz as u64) << 12) / BASE_SAMPLE_RATE as u64).min(MAX_PITCH as u64);
+        self.assign(pitch as u16)
+    }
+}
+
+impl ADSR {
+    /// Gets the voice's envelope.
+    pub fn get_envelope(&self) -> Envelope {
+        Envelope::from_bits(self.to_bits())
+    }
+
+    /// Sets the voice's envelope.
+    pub fn set_envelope(&mut self, envelope: Envelope) -> &mut Self {
+        self.assign(envelope.to_bits())
+    }
+}
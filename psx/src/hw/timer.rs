@@ -0,0 +1,320 @@
+//! Root counter (timer) registers
+//!
+//! Note that reading a [`CounterMode`] register resets its reached target and
+//! reached overflow bits and writing it resets the counter's current value.
+
+use crate::hw::irq::IRQ;
+use crate::hw::Register;
+
+const SYNC_ENABLE: u16 = 0;
+const SYNC_MODE: u16 = 1;
+const RESET_ON_TARGET: u16 = 3;
+const IRQ_ON_TARGET: u16 = 4;
+const IRQ_ON_OVERFLOW: u16 = 5;
+const IRQ_REPEAT: u16 = 6;
+const IRQ_TOGGLE: u16 = 7;
+const SOURCE: u16 = 8;
+const NO_IRQ_REQUEST: u16 = 10;
+const REACHED_TARGET: u16 = 11;
+const REACHED_OVERFLOW: u16 = 12;
+
+/// The name of a root counter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Name {
+    /// Root counter 0 (dot clock or sysclock)
+    Timer0 = 0,
+    /// Root counter 1 (hblank or sysclock)
+    Timer1,
+    /// Root counter 2 (sysclock or sysclock / 8)
+    Timer2,
+}
+
+impl Name {
+    /// Gets the root counter's interrupt request.
+    pub const fn irq(self) -> IRQ {
+        match self {
+            Name::Timer0 => IRQ::Timer0,
+            Name::Timer1 => IRQ::Timer1,
+            Name::Timer2 => IRQ::Timer2,
+        }
+    }
+}
+
+/// The synchronization mode for a root counter.
+///
+/// The blanks are hblank for timer 0 and vblank for timer 1. Timer 2 is
+/// stopped in [`SyncMode::Pause`] and [`SyncMode::PauseUntilBlank`] and runs
+/// freely in the other modes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncMode {
+    /// Pause the counter during blanks.
+    Pause = 0,
+    /// Reset the counter to zero at blanks.
+    Reset,
+    /// Reset the counter to zero at blanks and pause it outside of blanks.
+    ResetAndPause,
+    /// Pause the counter until a blank occurs then switch to free run.
+    PauseUntilBlank,
+}
+
+/// The clock source for a root counter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// The 33.8688 MHz system clock. Valid for all timers.
+    SysClock,
+    /// The GPU dot clock. Only valid for timer 0.
+    DotClock,
+    /// The horizontal blank. Only valid for timer 1.
+    Hblank,
+    /// The system clock divided by 8. Only valid for timer 2.
+    SysClockDiv8,
+}
+
+/// Whether a root counter interrupt is requested once or repeatedly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IRQRepeat {
+    /// Only request an interrupt the first time the condition is met.
+    OneShot = 0,
+    /// Request an interrupt each time the condition is met.
+    Repeat,
+}
+
+/// How a root counter signals its interrupt request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IRQPulse {
+    /// Briefly clear the interrupt request bit.
+    Pulse = 0,
+    /// Toggle the interrupt request bit.
+    Toggle,
+}
+
+/// Root counter 0 registers.
+pub mod timer0 {
+    use super::{CounterMode, CounterTarget, CounterValue};
+    use crate::hw::timer::Name;
+    use crate::hw::MemRegister;
+
+    /// Root counter current value register.
+    pub type Current = MemRegister<u16, 0x1F80_1100>;
+    impl CounterValue for Current {}
+
+    /// Root counter mode register.
+    pub type Mode = MemRegister<u16, 0x1F80_1104>;
+    impl CounterMode for Mode {
+        const NAME: Name = Name::Timer0;
+    }
+
+    /// Root counter target value register.
+    pub type Target = MemRegister<u16, 0x1F80_1108>;
+    impl CounterTarget for Target {}
+}
+
+/// Root counter 1 registers.
+pub mod timer1 {
+    use super::{CounterMode, CounterTarget, CounterValue};
+    use crate::hw::timer::Name;
+    use crate::hw::MemRegister;
+
+    /// Root counter current value register.
+    pub type Current = MemRegister<u16, 0x1F80_1110>;
+    impl CounterValue for Current {}
+
+    /// Root counter mode register.
+    pub type Mode = MemRegister<u16, 0x1F80_1114>;
+    impl CounterMode for Mode {
+        const NAME: Name = Name::Timer1;
+    }
+
+    /// Root counter target value register.
+    pub type Target = MemRegister<u16, 0x1F80_1118>;
+    impl CounterTarget for Target {}
+}
+
+/// Root counter 2 registers.
+pub mod timer2 {
+    use super::{CounterMode, CounterTarget, CounterValue};
+    use crate::hw::timer::Name;
+    use crate::hw::MemRegister;
+
+    /// Root counter current value register.
+    pub type Current = MemRegister<u16, 0x1F80_1120>;
+    impl CounterValue for Current {}
+
+    /// Root counter mode register.
+    pub type Mode = MemRegister<u16, 0x1F80_1124>;
+    impl CounterMode for Mode {
+        const NAME: Name = Name::Timer2;
+    }
+
+    /// Root counter target value register.
+    pub type Target = MemRegister<u16, 0x1F80_1128>;
+    impl CounterTarget for Target {}
+}
+
+/// A current value register for a root counter.
+pub trait CounterValue: Register<u16> {
+    /// Gets the counter's value.
+    fn get(&self) -> u16 {
+        self.to_bits()
+    }
+
+    /// Sets the counter's value.
+    fn set(&mut self, value: u16) -> &mut Self {
+        self.assign(value)
+    }
+}
+
+/// A target value register for a root counter.
+pub trait CounterTarget: Register<u16> {
+    /// Gets the counter's target value.
+    fn get_target(&self) -> u16 {
+        self.to_bits()
+    }
+
+    /// Sets the counter's target value.
+    fn set_target(&mut self, target: u16) -> &mut Self {
+        self.assign(target)
+    }
+}
+
+/// A mode register for a root counter.
+pub trait CounterMode: Register<u16> {
+    /// The name of the root counter.
+    const NAME: Name;
+
+    /// Gets the synchronization mode or `None` if the counter is free
+    /// running.
+    fn get_sync(&self) -> Option<SyncMode> {
+        if self.all_clear(1 << SYNC_ENABLE) {
+            return None
+        }
+        let mode = match (self.to_bits() >> SYNC_MODE) & 0b11 {
+            0 => SyncMode::Pause,
+            1 => SyncMode::Reset,
+            2 => SyncMode::ResetAndPause,
+            _ => SyncMode::PauseUntilBlank,
+        };
+        Some(mode)
+    }
+
+    /// Sets the synchronization mode or makes the counter free running if
+    /// `sync` is `None`.
+    fn set_sync(&mut self, sync: Option<SyncMode>) -> &mut Self {
+        self.clear_bits(1 << SYNC_ENABLE | 0b11 << SYNC_MODE);
+        match sync {
+            Some(mode) => self.set_bits(1 << SYNC_ENABLE | (mode as u16) << SYNC_MODE),
+            None => self,
+        }
+    }
+
+    /// Checks if the counter resets after reaching its target rather than
+    /// after overflowing.
+    fn resets_on_target(&self) -> bool {
+        self.all_set(1 << RESET_ON_TARGET)
+    }
+
+    /// Resets the counter after reaching its target if `target` is true or
+    /// after overflowing otherwise.
+    fn reset_on_target(&mut self, target: bool) -> &mut Self {
+        set_bit(self, RESET_ON_TARGET, target)
+    }
+
+    /// Checks if an interrupt is requested when the counter reaches its
+    /// target.
+    fn irq_on_target_enabled(&self) -> bool {
+        self.all_set(1 << IRQ_ON_TARGET)
+    }
+
+    /// Enables or disables requesting an interrupt when the counter reaches
+    /// its target.
+    fn irq_on_target(&mut self, enable: bool) -> &mut Self {
+        set_bit(self, IRQ_ON_TARGET, enable)
+    }
+
+    /// Checks if an interrupt is requested when the counter overflows.
+    fn irq_on_overflow_enabled(&self) -> bool {
+        self.all_set(1 << IRQ_ON_OVERFLOW)
+    }
+
+    /// Enables or disables requesting an interrupt when the counter
+    /// overflows.
+    fn irq_on_overflow(&mut self, enable: bool) -> &mut Self {
+        set_bit(self, IRQ_ON_OVERFLOW, enable)
+    }
+
+    /// Gets whether interrupts are requested once or repeatedly.
+    fn get_irq_repeat(&self) -> IRQRepeat {
+        if self.all_set(1 << IRQ_REPEAT) {
+            IRQRepeat::Repeat
+        } else {
+            IRQRepeat::OneShot
+        }
+    }
+
+    /// Sets whether interrupts are requested once or repeatedly.
+    fn set_irq_repeat(&mut self, repeat: IRQRepeat) -> &mut Self {
+        set_bit(self, IRQ_REPEAT, repeat == IRQRepeat::Repeat)
+    }
+
+    /// Gets how interrupt requests are signaled.
+    fn get_irq_pulse(&self) -> IRQPulse {
+        if self.all_set(1 << IRQ_TOGGLE) {
+            IRQPulse::Toggle
+        } else {
+            IRQPulse::Pulse
+        }
+    }
+
+    /// Sets how interrupt requests are signaled.
+    fn set_irq_pulse(&mut self, pulse: IRQPulse) -> &mut Self {
+        set_bit(self, IRQ_TOGGLE, pulse == IRQPulse::Toggle)
+    }
+
+    /// Gets the counter's clock source.
+    fn get_source(&self) -> Source {
+        let bits = (self.to_bits() >> SOURCE) & 0b11;
+        match Self::NAME {
+            Name::Timer0 if bits & 1 != 0 => Source::DotClock,
+            Name::Timer1 if bits & 1 != 0 => Source::Hblank,
+            Name::Timer2 if bits & 2 != 0 => Source::SysClockDiv8,
+            _ => Source::SysClock,
+        }
+    }
+
+    /// Sets the counter's clock source, returning `None` if the source isn't
+    /// valid for this counter.
+    fn set_source(&mut self, source: Source) -> Option<&mut Self> {
+        let bits = match (Self::NAME, source) {
+            (_, Source::SysClock) => 0,
+            (Name::Timer0, Source::DotClock) => 1,
+            (Name::Timer1, Source::Hblank) => 1,
+            (Name::Timer2, Source::SysClockDiv8) => 2,
+            _ => return None,
+        };
+        Some(self.clear_bits(0b11 << SOURCE).set_bits(bits << SOURCE))
+    }
+
+    /// Checks if the counter is currently requesting an interrupt.
+    fn irq_requested(&self) -> bool {
+        self.all_clear(1 << NO_IRQ_REQUEST)
+    }
+
+    /// Checks if the counter reached its target since the register was last
+    /// read.
+    fn reached_target(&self) -> bool {
+        self.all_set(1 << REACHED_TARGET)
+    }
+
+    /// Checks if the counter overflowed since the register was last read.
+    fn reached_overflow(&self) -> bool {
+        self.all_set(1 << REACHED_OVERFLOW)
+    }
+}
+
+fn set_bit<R: Register<u16>>(reg: &mut R, bit: u16, set: bool) -> &mut R {
+    if set {
+        reg.set_bits(1 << bit)
+    } else {
+        reg.clear_bits(1 << bit)
+    }
+}
@@ -58,21 +58,29 @@
 #[macro_use]
 mod test;
 
+pub mod cdrom;
+pub mod debug;
 pub mod dma;
 pub mod format;
 mod framebuffer;
 pub mod gpu;
+pub mod gte;
 #[doc(hidden)]
 pub mod heap;
 pub mod hw;
 mod macros;
 pub mod math;
+pub mod mdec;
 mod panic;
 #[doc(hidden)]
 pub mod runtime;
+pub mod sio;
+pub mod spu;
 #[doc(hidden)]
 pub mod std;
 pub mod sys;
+pub mod time;
+pub mod timer;
 
 /// Re-exported constants in a module for easy glob importing.
 pub mod constants {
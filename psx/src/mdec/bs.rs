@@ -0,0 +1,477 @@
+//! BS bitstream decompression
+//!
+//! BS frames hold the MDEC's run-length codes compressed with the MPEG-1
+//! variable-length codes. The bitstream is read from little-endian 16-bit
+//! words starting with their most significant bit.
+use crate::mdec::Error;
+
+/// The size of the BS header in bytes.
+pub const HEADER_SIZE: usize = 8;
+/// The second halfword of every BS header.
+pub const MAGIC: u16 = 0x3800;
+
+/// The MDEC code which ends a block.
+pub const END_OF_BLOCK: u16 = 0xFE00;
+/// The number of blocks in a color macroblock.
+pub const BLOCKS_PER_MACROBLOCK: usize = 6;
+/// The MDEC input is sent in DMA blocks of this many words.
+pub const PADDING_WORDS: usize = 32;
+
+// The macroblocks' blocks are stored as Cr, Cb, Y1, Y2, Y3 and Y4
+const LUMINANCE: usize = 2;
+
+const ESCAPE_LEN: u32 = 6;
+const RAW_CODE_LEN: u32 = 16;
+
+/// The AC codes with 6 to 11 leading zeros as `(run, level)`, indexed by the
+/// bits after the first one.
+const LONG_CODES: [&[(u8, u8)]; 6] = [
+    &[
+        (16, 1),
+        (5, 2),
+        (0, 7),
+        (2, 3),
+        (1, 4),
+        (15, 1),
+        (14, 1),
+        (4, 2),
+    ],
+    &[
+        (0, 11),
+        (8, 2),
+        (4, 3),
+        (0, 10),
+        (2, 4),
+        (7, 2),
+        (21, 1),
+        (20, 1),
+        (0, 9),
+        (19, 1),
+        (18, 1),
+        (1, 5),
+        (3, 3),
+        (0, 8),
+        (6, 2),
+        (17, 1),
+    ],
+    &[
+        (10, 2),
+        (9, 2),
+        (5, 3),
+        (3, 4),
+        (2, 5),
+        (1, 7),
+        (1, 6),
+        (0, 15),
+        (0, 14),
+        (0, 13),
+        (0, 12),
+        (26, 1),
+        (25, 1),
+        (24, 1),
+        (23, 1),
+        (22, 1),
+    ],
+    &[
+        (0, 31),
+        (0, 30),
+        (0, 29),
+        (0, 28),
+        (0, 27),
+        (0, 26),
+        (0, 25),
+        (0, 24),
+        (0, 23),
+        (0, 22),
+        (0, 21),
+        (0, 20),
+        (0, 19),
+        (0, 18),
+        (0, 17),
+        (0, 16),
+    ],
+    &[
+        (0, 40),
+        (0, 39),
+        (0, 38),
+        (0, 37),
+        (0, 36),
+        (0, 35),
+        (0, 34),
+        (0, 33),
+        (0, 32),
+        (1, 14),
+        (1, 13),
+        (1, 12),
+        (1, 11),
+        (1, 10),
+        (1, 9),
+        (1, 8),
+    ],
+    &[
+        (1, 18),
+        (1, 17),
+        (1, 16),
+        (1, 15),
+        (6, 3),
+        (16, 2),
+        (15, 2),
+        (14, 2),
+        (13, 2),
+        (12, 2),
+        (11, 2),
+        (31, 1),
+        (30, 1),
+        (29, 1),
+        (28, 1),
+        (27, 1),
+    ],
+];
+/// The AC codes starting with `00100` followed by 3 bits.
+const CODES_00100: [(u8, u8); 8] = [
+    (13, 1),
+    (0, 6),
+    (12, 1),
+    (11, 1),
+    (3, 2),
+    (1, 3),
+    (0, 5),
+    (10, 1),
+];
+/// The AC codes starting with `0001` followed by 2 bits.
+const CODES_0001: [(u8, u8); 4] = [(7, 1), (6, 1), (1, 2), (5, 1)];
+/// The AC codes starting with `00001` followed by 2 bits.
+const CODES_00001: [(u8, u8); 4] = [(2, 2), (9, 1), (0, 4), (8, 1)];
+
+/// A BS frame header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    /// The number of 32-bit words of MDEC codes, rounded up.
+    pub mdec_words: u16,
+    /// The quantization scale used by every block.
+    pub quant_scale: u16,
+    /// The format version, which is 2 or 3.
+    pub version: u16,
+}
+
+impl Header {
+    /// Parses a BS frame header.
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        if data.len() < HEADER_SIZE {
+            return Err(Error::InvalidHeader)
+        }
+        let halfword = |idx: usize| u16::from_le_bytes([data[idx * 2], data[idx * 2 + 1]]);
+        if halfword(1) != MAGIC {
+            return Err(Error::InvalidHeader)
+        }
+        let header = Header {
+            mdec_words: halfword(0),
+            quant_scale: halfword(2),
+            version: halfword(3),
+        };
+        if !matches!(header.version, 2 | 3) {
+            return Err(Error::UnsupportedVersion(header.version))
+        }
+        Ok(header)
+    }
+}
+
+/// Reads bits from little-endian 16-bit words starting with the most
+/// significant bit.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bits: u32,
+    len: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        BitReader {
+            data,
+            pos: 0,
+            bits: 0,
+            len: 0,
+        }
+    }
+
+    /// Gets the next `n` bits without consuming them, padding them with
+    /// zeros past the end of the data. `n` must be at most 17.
+    fn peek(&mut self, n: u32) -> u32 {
+        while self.len < n {
+            let word = match self.data.get(self.pos..self.pos + 2) {
+                Some(&[lo, hi]) => u16::from_le_bytes([lo, hi]),
+                _ => 0,
+            };
+            self.pos += 2;
+            self.bits = self.bits << 16 | word as u32;
+            self.len += 16;
+        }
+        (self.bits >> (self.len - n)) & ((1 << n) - 1)
+    }
+
+    fn skip(&mut self, n: u32) -> Result<(), Error> {
+        self.peek(n);
+        self.len -= n;
+        // Peeking past the end is fine but consuming those bits isn't
+        if self.pos * 8 - self.len as usize > self.data.len() * 8 {
+            return Err(Error::EndOfStream)
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, n: u32) -> Result<u32, Error> {
+        let bits = self.peek(n);
+        self.skip(n)?;
+        Ok(bits)
+    }
+}
+
+/// Writes MDEC codes into a buffer of words.
+struct CodeWriter<'a> {
+    out: &'a mut [u32],
+    len: usize,
+}
+
+impl CodeWriter<'_> {
+    fn push(&mut self, code: u16) -> Result<(), Error> {
+        let word = self
+            .out
+            .get_mut(self.len / 2)
+            .ok_or(Error::BufferTooSmall)?;
+        if self.len % 2 == 0 {
+            *word = code as u32;
+        } else {
+            *word = (*word & 0xFFFF) | (code as u32) << 16;
+        }
+        self.len += 1;
+        Ok(())
+    }
+}
+
+/// Gets an AC coefficient code from its run of zeros and level.
+fn ac_code(run: u32, level: u32, negative: bool) -> u16 {
+    let level = if negative {
+        level.wrapping_neg()
+    } else {
+        level
+    };
+    (run << 10 | level & 0x3FF) as u16
+}
+
+/// Reads an AC coefficient, returning `None` at the end of the block.
+fn read_ac(bits: &mut BitReader) -> Result<Option<u16>, Error> {
+    let window = bits.peek(16);
+    let zeros = (window << 16).leading_zeros();
+    let (len, run, level) = match zeros {
+        0 => {
+            if window & 0x4000 == 0 {
+                bits.skip(2)?;
+                return Ok(None)
+            }
+            (2, 0, 1)
+        },
+        1 => match (window >> 12) & 0b11 {
+            0b10 | 0b11 => (3, 1, 1),
+            0b00 => (4, 0, 2),
+            _ => (4, 2, 1),
+        },
+        2 => match (window >> 11) & 0b11 {
+            0b01 => (5, 0, 3),
+            0b10 => (5, 4, 1),
+            0b11 => (5, 3, 1),
+            _ => {
+                let (run, level) = CODES_00100[(window >> 8 & 0b111) as usize];
+                (8, run, level)
+            },
+        },
+        3 => {
+            let (run, level) = CODES_0001[(window >> 10 & 0b11) as usize];
+            (6, run, level)
+        },
+        4 => {
+            let (run, level) = CODES_00001[(window >> 9 & 0b11) as usize];
+            (7, run, level)
+        },
+        5 => {
+            bits.skip(ESCAPE_LEN)?;
+            return bits.read(RAW_CODE_LEN).map(|code| Some(code as u16))
+        },
+        6..=11 => {
+            let codes = LONG_CODES[zeros as usize - 6];
+            let index_len = if zeros == 6 { 3 } else { 4 };
+            let len = zeros + 1 + index_len;
+            let (run, level) = codes[((window >> (16 - len)) & ((1 << index_len) - 1)) as usize];
+            (len, run, level)
+        },
+        _ => return Err(Error::InvalidCode),
+    };
+    bits.skip(len)?;
+    let negative = bits.read(1)? != 0;
+    Ok(Some(ac_code(run as u32, level as u32, negative)))
+}
+
+/// Reads the size of a version 3 DC coefficient's difference.
+fn read_dc_size(bits: &mut BitReader, luminance: bool) -> Result<u32, Error> {
+    let window = bits.peek(8);
+    let (len, size) = if luminance {
+        match window >> 6 {
+            0b00 => (2, 1),
+            0b01 => (2, 2),
+            _ if window >> 5 == 0b100 => (3, 0),
+            _ if window >> 5 == 0b101 => (3, 3),
+            _ if window >> 5 == 0b110 => (3, 4),
+            _ => {
+                // 1110 is 5 up to 1111110 which is 8
+                let ones = (window << 24).leading_ones();
+                if ones > 6 {
+                    return Err(Error::InvalidCode)
+                }
+                (ones + 1, ones + 2)
+            },
+        }
+    } else {
+        match window >> 6 {
+            0b00 => (2, 0),
+            0b01 => (2, 1),
+            0b10 => (2, 2),
+            _ => {
+                // 110 is 3 up to 11111110 which is 8
+                let ones = (window << 24).leading_ones();
+                if ones > 7 {
+                    return Err(Error::InvalidCode)
+                }
+                (ones + 1, ones + 1)
+            },
+        }
+    };
+    bits.skip(len)?;
+    Ok(size)
+}
+
+/// Reads a version 3 DC coefficient's difference from the previous one in
+/// the same component.
+fn read_dc_diff(bits: &mut BitReader, luminance: bool) -> Result<i32, Error> {
+    let size = read_dc_size(bits, luminance)?;
+    if size == 0 {
+        return Ok(0)
+    }
+    let diff = bits.read(size)? as i32;
+    // Differences with the top bit cleared are negative
+    if diff < 1 << (size - 1) {
+        Ok(diff - (1 << size) + 1)
+    } else {
+        Ok(diff)
+    }
+}
+
+/// Decompresses a BS frame with `macroblocks` color macroblocks into MDEC
+/// codes.
+///
+/// The codes are padded with end of block codes to a multiple of
+/// [`PADDING_WORDS`] words. Returns the frame's header and the number of
+/// words written to `out`.
+pub fn decompress(
+    frame: &[u8], macroblocks: usize, out: &mut [u32],
+) -> Result<(Header, usize), Error> {
+    let header = Header::parse(frame)?;
+    let quant_scale = (header.quant_scale as u32 & 0x3F) << 10;
+    let mut bits = BitReader::new(&frame[HEADER_SIZE..]);
+    let mut codes = CodeWriter { out, len: 0 };
+    // The version 3 DC predictors for Cr, Cb and Y
+    let mut predictors = [0; 3];
+    for _ in 0..macroblocks {
+        for block in 0..BLOCKS_PER_MACROBLOCK {
+            let dc = if header.version == 2 {
+                bits.read(10)?
+            } else {
+                let component = block.min(LUMINANCE);
+                let diff = read_dc_diff(&mut bits, block >= LUMINANCE)?;
+                predictors[component] += diff;
+                // The predictors have 8 bits of precision instead of 10
+                (predictors[component] * 4) as u32 & 0x3FF
+            };
+            codes.push((quant_scale | dc) as u16)?;
+            let mut coefficients = 1;
+            while let Some(code) = read_ac(&mut bits)? {
+                coefficients += (code >> 10) as usize + 1;
+                if coefficients > 64 {
+                    return Err(Error::InvalidCode)
+                }
+                codes.push(code)?;
+            }
+            codes.push(END_OF_BLOCK)?;
+        }
+    }
+    while codes.len % (PADDING_WORDS * 2) != 0 {
+        codes.push(END_OF_BLOCK)?;
+    }
+    Ok((header, codes.len / 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decompress, Header, END_OF_BLOCK, PADDING_WORDS};
+    use crate::mdec::Error;
+
+    // One macroblock with a DC of 5 and the AC codes `11 0`, `000001`
+    // followed by 0x0C01 and `0000000011011 1` in the first block
+    const V2: [u8; 22] = [
+        0x00, 0x00, 0x00, 0x38, 0x01, 0x00, 0x02, 0x00, 0x70, 0x01, 0x80, 0x21, 0x1b, 0x20, 0x04,
+        0xc0, 0x40, 0x00, 0x00, 0x04, 0x04, 0x40,
+    ];
+    // One macroblock with DC differences of 0 for Cr, -1 for Cb and 3, 0,
+    // 0, 0 for Y
+    const V3: [u8; 12] = [
+        0x00, 0x00, 0x00, 0x38, 0x02, 0x00, 0x03, 0x00, 0x3d, 0x25, 0x48, 0x29,
+    ];
+
+    fn halfwords(words: &[u32]) -> impl Iterator<Item = u16> + '_ {
+        words
+            .iter()
+            .flat_map(|&word| [word as u16, (word >> 16) as u16])
+    }
+
+    #[test_case]
+    fn version2() {
+        let mut out = [0; PADDING_WORDS];
+        let (header, words) = decompress(&V2, 1, &mut out).unwrap();
+        assert!(header.version == 2);
+        assert!(header.quant_scale == 1);
+        assert!(words == PADDING_WORDS);
+        let mut codes = halfwords(&out);
+        for code in [0x0405, 0x0001, 0x0C01, 0x6BFF, END_OF_BLOCK] {
+            assert!(codes.next() == Some(code));
+        }
+        for _ in 1..6 {
+            assert!(codes.next() == Some(0x0400));
+            assert!(codes.next() == Some(END_OF_BLOCK));
+        }
+        assert!(codes.all(|code| code == END_OF_BLOCK));
+    }
+
+    #[test_case]
+    fn version3() {
+        let mut out = [0; PADDING_WORDS];
+        decompress(&V3, 1, &mut out).unwrap();
+        let mut codes = halfwords(&out).step_by(2);
+        for dc in [0x0800, 0x0BFC, 0x080C, 0x080C, 0x080C, 0x080C] {
+            assert!(codes.next() == Some(dc));
+        }
+    }
+
+    #[test_case]
+    fn errors() {
+        let mut out = [0; PADDING_WORDS];
+        assert!(decompress(&V2[..4], 1, &mut out) == Err(Error::InvalidHeader));
+        let mut bad_magic = V2;
+        bad_magic[3] = 0;
+        assert!(decompress(&bad_magic, 1, &mut out) == Err(Error::InvalidHeader));
+        let mut v1 = V2;
+        v1[6] = 1;
+        assert!(decompress(&v1, 1, &mut out) == Err(Error::UnsupportedVersion(1)));
+        assert!(decompress(&V2, 4, &mut out) == Err(Error::EndOfStream));
+        assert!(decompress(&V2, 1, &mut out[..4]) == Err(Error::BufferTooSmall));
+        assert!(Header::parse(&V3).unwrap().version == 3);
+    }
+}
@@ -0,0 +1,234 @@
+//! Macroblock decoder routines
+//!
+//! [`Decoder`] streams MDEC codes through the [`MDECIn`][dma::MDECIn] DMA
+//! channel and uploads the decoded pixels to VRAM. Frames are decoded in
+//! columns of 16 pixel wide macroblocks, so each column is received through
+//! [`MDECOut`][dma::MDECOut] into one half of a buffer while the previous
+//! one is uploaded from the other half. The codes are usually decompressed
+//! from [BS frames][bs] first.
+use crate::dma;
+use crate::gpu::{DMAMode, Depth, Vertex};
+use crate::hw::gpu::{GP0, GP1};
+use crate::hw::mdec::{Command, MDEC0, MDEC1, TABLE_LEN};
+use crate::hw::{gpu, mdec, Register};
+use core::mem;
+
+pub mod bs;
+pub mod movie;
+
+/// The width and height of a macroblock in pixels.
+pub const MACROBLOCK_SIZE: u16 = 16;
+/// The size of the GPU DMA blocks used to upload pixels.
+const GPU_BLOCK_WORDS: usize = 16;
+
+/// The standard quantization table used for both luminance and color.
+///
+/// This is the MPEG-1 intra quantization matrix in zigzag order with the DC
+/// entry set to 2.
+pub const QUANT_TABLE: [u8; TABLE_LEN] = [
+    0x02, 0x10, 0x10, 0x13, 0x10, 0x13, 0x16, 0x16, 0x16, 0x16, 0x16, 0x16, 0x1A, 0x18, 0x1A, 0x1B,
+    0x1B, 0x1B, 0x1A, 0x1A, 0x1A, 0x1A, 0x1B, 0x1B, 0x1B, 0x1D, 0x1D, 0x1D, 0x22, 0x22, 0x22, 0x1D,
+    0x1D, 0x1D, 0x1B, 0x1B, 0x1D, 0x1D, 0x20, 0x20, 0x22, 0x22, 0x25, 0x26, 0x25, 0x23, 0x23, 0x22,
+    0x23, 0x26, 0x26, 0x28, 0x28, 0x28, 0x30, 0x30, 0x2E, 0x2E, 0x38, 0x38, 0x3A, 0x45, 0x45, 0x53,
+];
+
+/// The standard IDCT scale table.
+pub const SCALE_TABLE: [i16; TABLE_LEN] = [
+    0x5A82, 0x5A82, 0x5A82, 0x5A82, 0x5A82, 0x5A82, 0x5A82, 0x5A82, 0x7D8A, 0x6A6D, 0x471C, 0x18F8,
+    -0x18F9, -0x471D, -0x6A6E, -0x7D8B, 0x7641, 0x30FB, -0x30FC, -0x7642, -0x7642, -0x30FC, 0x30FB,
+    0x7641, 0x6A6D, -0x18F9, -0x7D8B, -0x471D, 0x471C, 0x7D8A, 0x18F8, -0x6A6E, 0x5A82, -0x5A83,
+    -0x5A83, 0x5A82, 0x5A82, -0x5A83, -0x5A83, 0x5A82, 0x471C, -0x7D8B, 0x18F8, 0x6A6D, -0x6A6E,
+    -0x18F9, 0x7D8A, -0x471D, 0x30FB, -0x7642, 0x7641, -0x30FC, -0x30FC, 0x7641, -0x7642, 0x30FB,
+    0x18F8, -0x471D, 0x6A6D, -0x7D8B, 0x7D8A, -0x6A6E, 0x471C, -0x18F9,
+];
+
+/// An MDEC-specific error.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error {
+    /// The BS header is truncated or has the wrong magic number.
+    InvalidHeader,
+    /// The BS frame has a version other than 2 or 3.
+    UnsupportedVersion(u16),
+    /// The BS bitstream has an invalid variable-length code.
+    InvalidCode,
+    /// The BS bitstream ended before the last macroblock.
+    EndOfStream,
+    /// A buffer is too small for the frame.
+    BufferTooSmall,
+    /// The MDEC codes aren't padded to a multiple of
+    /// [`bs::PADDING_WORDS`] words or don't fit in a single command.
+    InvalidCodes,
+    /// A DMA transfer failed.
+    Transfer(dma::Error),
+}
+
+impl From<dma::Error> for Error {
+    fn from(err: dma::Error) -> Self {
+        Error::Transfer(err)
+    }
+}
+
+const fn columns(width: u16) -> usize {
+    width.div_ceil(MACROBLOCK_SIZE) as usize
+}
+
+const fn rows(height: u16) -> usize {
+    height.div_ceil(MACROBLOCK_SIZE) as usize
+}
+
+/// Gets the number of macroblocks in a frame.
+pub const fn macroblocks(width: u16, height: u16) -> usize {
+    columns(width) * rows(height)
+}
+
+/// Gets the number of words in a decoded macroblock.
+pub const fn macroblock_words(depth: Depth) -> usize {
+    match depth {
+        Depth::Bits15 => 128,
+        Depth::Bits24 => 192,
+    }
+}
+
+/// Gets the number of words in a column of decoded macroblocks.
+///
+/// [`Decoder::decode`] needs a buffer for two columns.
+pub const fn column_words(height: u16, depth: Depth) -> usize {
+    rows(height) * macroblock_words(depth)
+}
+
+/// Uploads pixels to a rectangle in VRAM through GPU DMA.
+fn upload(
+    gpu_dma: &mut dma::GPU, pixels: &[u32], offset: Vertex, size: Vertex,
+) -> Result<(), Error> {
+    gpu::Status::new().wait_cmd().wait_dma();
+    GP0::skip_load()
+        .assign(0xA0 << 24)
+        .store()
+        .assign(u32::from(offset))
+        .store()
+        .assign(u32::from(size))
+        .store();
+    gpu_dma.send_blocks_and(pixels, pixels.len() / GPU_BLOCK_WORDS, || ())?;
+    Ok(())
+}
+
+/// A handle to the MDEC which decodes frames into VRAM.
+pub struct Decoder {
+    depth: Depth,
+    bit15: bool,
+    mdec_in: dma::MDECIn,
+    mdec_out: dma::MDECOut,
+    gpu_dma: dma::GPU,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder {
+    /// Resets the MDEC and loads the standard quantization and scale tables.
+    ///
+    /// Frames are decoded into 15-bit pixels by default.
+    pub fn new() -> Self {
+        MDEC1::skip_load().reset();
+        MDEC0::skip_load()
+            .set_quant_tables(&QUANT_TABLE, Some(&QUANT_TABLE))
+            .set_scale_table(&SCALE_TABLE);
+        mdec::Status::new().wait_idle();
+        Decoder {
+            depth: Depth::Bits15,
+            bit15: false,
+            mdec_in: dma::MDECIn::new(),
+            mdec_out: dma::MDECOut::new(),
+            gpu_dma: dma::GPU::new(),
+        }
+    }
+
+    /// Sets the depth of the decoded pixels.
+    ///
+    /// 24-bit frames take 1.5 times as much VRAM horizontally and must be
+    /// displayed in 24-bit mode.
+    pub fn set_depth(&mut self, depth: Depth) -> &mut Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Sets bit 15 of 15-bit pixels.
+    pub fn set_bit15(&mut self, bit15: bool) -> &mut Self {
+        self.bit15 = bit15;
+        self
+    }
+
+    /// Decodes a frame of `width` by `height` pixels from MDEC codes into
+    /// VRAM at `offset`.
+    ///
+    /// The frame's size is rounded up to whole macroblocks. `buf` must hold
+    /// at least twice [`column_words`] and `codes` must be padded to a
+    /// multiple of [`bs::PADDING_WORDS`] words. This sets the GPU DMA mode to
+    /// [`DMAMode::GP0`].
+    pub fn decode(
+        &mut self, codes: &[u32], width: u16, height: u16, offset: (i16, i16), buf: &mut [u32],
+    ) -> Result<(), Error> {
+        if codes.len() % bs::PADDING_WORDS != 0 || codes.len() > u16::MAX as usize {
+            return Err(Error::InvalidCodes)
+        }
+        let column_len = column_words(height, self.depth);
+        if buf.len() < 2 * column_len {
+            return Err(Error::BufferTooSmall)
+        }
+        let (mut receiving, mut uploading) = buf[..2 * column_len].split_at_mut(column_len);
+        let (hw_depth, column_width) = match self.depth {
+            Depth::Bits15 => (mdec::Depth::Bits15, MACROBLOCK_SIZE),
+            Depth::Bits24 => (mdec::Depth::Bits24, MACROBLOCK_SIZE * 3 / 2),
+        };
+        let size = Vertex::new((
+            column_width as i16,
+            (rows(height) * MACROBLOCK_SIZE as usize) as i16,
+        ));
+        let mut cmd = Command::decode(codes.len() as u16);
+        cmd.set_depth(hw_depth).set_bit15(self.bit15);
+
+        mdec::Status::new().wait_idle();
+        MDEC0::skip_load().send_command(cmd);
+        MDEC1::skip_load().enable_dma(true, true);
+        GP1::skip_load().dma_mode(Some(DMAMode::GP0));
+        let total = columns(width);
+        let mdec_out = &mut self.mdec_out;
+        let gpu_dma = &mut self.gpu_dma;
+        let blocks = codes.len() / bs::PADDING_WORDS;
+        self.mdec_in.send_blocks_and(codes, blocks, || {
+            for column in 0..=total {
+                // Upload the previous column while receiving this one
+                let x = offset.0 + (column as i16 - 1) * column_width as i16;
+                let offset = Vertex::new((x, offset.1));
+                let mut upload_previous = || match column {
+                    0 => Ok(()),
+                    _ => upload(gpu_dma, uploading, offset, size),
+                };
+                if column < total {
+                    let blocks = column_len / bs::PADDING_WORDS;
+                    mdec_out.receive_blocks_and(receiving, blocks, upload_previous)??;
+                } else {
+                    upload_previous()?;
+                }
+                mem::swap(&mut receiving, &mut uploading);
+            }
+            Ok(())
+        })?
+    }
+
+    /// Decompresses a BS frame into `codes` and decodes it like
+    /// [`Decoder::decode`].
+    ///
+    /// `codes` must be large enough for the frame's decompressed MDEC codes.
+    pub fn decode_bs(
+        &mut self, frame: &[u8], width: u16, height: u16, offset: (i16, i16), codes: &mut [u32],
+        buf: &mut [u32],
+    ) -> Result<bs::Header, Error> {
+        let (header, len) = bs::decompress(frame, macroblocks(width, height), codes)?;
+        self.decode(&codes[..len], width, height, offset, buf)?;
+        Ok(header)
+    }
+}
@@ -0,0 +1,450 @@
+//! STR movie playback
+//!
+//! STR files interleave video sectors with XA-ADPCM audio sectors. Each
+//! video sector starts with a header giving its position in a BS frame which
+//! is split across several sectors. [`Movie::play`] reads the file at double
+//! speed into a ring buffer of sectors, reassembles the frames and decodes
+//! them into the framebuffer while the drive plays the audio. Frames are
+//! shown as soon as all their sectors arrive, so the disc sets the frame
+//! rate.
+use crate::cdrom::iso9660::{self, Filesystem};
+use crate::cdrom::xa::{self, Channel};
+use crate::cdrom::{self, next_sector, release_sector, stop_read, CdRom, SectorSize, Speed};
+use crate::gpu::{Depth, Vertex};
+use crate::hw::cdrom::Command;
+use crate::hw::{cop0, Register};
+use crate::mdec::{self, column_words, Decoder};
+use crate::sys::gamepad::{Button, Gamepad};
+use crate::Framebuffer;
+use core::ptr::addr_of_mut;
+
+/// The ID at the start of each video sector's header.
+const STR_ID: u16 = 0x0160;
+/// The type of video sectors containing MDEC data.
+const MDEC_TYPE: u16 = 0x8001;
+
+/// The size of the header at the start of each video sector.
+pub const HEADER_SIZE: usize = 32;
+/// The size of the frame data in each video sector.
+pub const DATA_SIZE: usize = SECTOR_SIZE - HEADER_SIZE;
+/// The maximum number of sectors in a frame.
+pub const MAX_FRAME_SECTORS: usize = 32;
+/// The maximum height of a movie.
+pub const MAX_HEIGHT: u16 = 480;
+
+const SECTOR_SIZE: usize = SectorSize::Data.bytes();
+const SECTOR_WORDS: usize = SectorSize::Data.words();
+const RING_SECTORS: usize = 32;
+const CODES_WORDS: usize = 0x8000;
+const COLUMNS_WORDS: usize = 2 * column_words(MAX_HEIGHT, Depth::Bits15);
+// About a second, which is far longer than the drive's XA-ADPCM buffer lasts
+const DRAIN_VBLANKS: u32 = 60;
+
+/// An error while playing a movie.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error {
+    /// The movie's file couldn't be found.
+    File(iso9660::Error),
+    /// Reading the movie failed.
+    Read(cdrom::Error),
+    /// Decoding a frame failed.
+    Decode(mdec::Error),
+    /// A frame has more than [`MAX_FRAME_SECTORS`] sectors, is taller than
+    /// [`MAX_HEIGHT`] or doesn't fit in the framebuffer.
+    FrameTooLarge,
+    /// Another movie is already playing.
+    Busy,
+}
+
+impl From<iso9660::Error> for Error {
+    fn from(err: iso9660::Error) -> Self {
+        Error::File(err)
+    }
+}
+
+impl From<cdrom::Error> for Error {
+    fn from(err: cdrom::Error) -> Self {
+        Error::Read(err)
+    }
+}
+
+impl From<mdec::Error> for Error {
+    fn from(err: mdec::Error) -> Self {
+        Error::Decode(err)
+    }
+}
+
+/// How a movie stopped playing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Playback {
+    /// The whole movie was played.
+    Finished,
+    /// The movie was skipped.
+    Skipped,
+}
+
+/// The header at the start of each video sector.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SectorHeader {
+    /// The sector's index within its frame.
+    pub sector: u16,
+    /// The number of sectors in the frame.
+    pub sectors: u16,
+    /// The frame number, starting from 1.
+    pub frame: u32,
+    /// The size of the frame's BS data in bytes.
+    pub frame_size: u32,
+    /// The frame's width in pixels.
+    pub width: u16,
+    /// The frame's height in pixels.
+    pub height: u16,
+}
+
+impl SectorHeader {
+    /// Parses the header of a video sector, returning `None` for other
+    /// sectors.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < HEADER_SIZE {
+            return None
+        }
+        let u16_at = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]);
+        let u32_at =
+            |i: usize| u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+        if u16_at(0) != STR_ID || u16_at(2) != MDEC_TYPE {
+            return None
+        }
+        Some(SectorHeader {
+            sector: u16_at(4),
+            sectors: u16_at(6),
+            frame: u32_at(8),
+            frame_size: u32_at(12),
+            width: u16_at(16),
+            height: u16_at(18),
+        })
+    }
+}
+
+/// Reassembles frames from video sectors.
+#[derive(Debug, Default)]
+struct Assembler {
+    frame: Option<u32>,
+    // A bit for each sector received for the frame
+    received: u32,
+}
+
+impl Assembler {
+    /// Copies a video sector's data into `buf`, returning whether its frame
+    /// is complete.
+    ///
+    /// A frame is dropped if a sector of another frame arrives before it's
+    /// complete.
+    fn push(&mut self, header: &SectorHeader, data: &[u8], buf: &mut [u8]) -> Result<bool, Error> {
+        let sectors = header.sectors as usize;
+        let sector = header.sector as usize;
+        if sectors > MAX_FRAME_SECTORS || sectors * DATA_SIZE > buf.len() {
+            return Err(Error::FrameTooLarge)
+        }
+        if sector >= sectors {
+            return Ok(false)
+        }
+        if self.frame != Some(header.frame) {
+            self.frame = Some(header.frame);
+            self.received = 0;
+        }
+        let len = data.len().min(DATA_SIZE);
+        buf[sector * DATA_SIZE..][..len].copy_from_slice(&data[..len]);
+        self.received |= 1 << sector;
+        let complete = self.received == u32::MAX >> (32 - sectors);
+        if complete {
+            self.frame = None;
+        }
+        Ok(complete)
+    }
+}
+
+/// The buffers used while playing a movie.
+struct Buffers {
+    ring: [u32; RING_SECTORS * SECTOR_WORDS],
+    frame: [u8; MAX_FRAME_SECTORS * DATA_SIZE],
+    codes: [u32; CODES_WORDS],
+    columns: [u32; COLUMNS_WORDS],
+}
+
+static mut BUFFERS: Buffers = Buffers {
+    ring: [0; RING_SECTORS * SECTOR_WORDS],
+    frame: [0; MAX_FRAME_SECTORS * DATA_SIZE],
+    codes: [0; CODES_WORDS],
+    columns: [0; COLUMNS_WORDS],
+};
+static mut PLAYING: bool = false;
+
+/// Runs `f` with the movie buffers unless another movie is playing.
+fn with_buffers<R>(f: impl FnOnce(&mut Buffers) -> Result<R, Error>) -> Result<R, Error> {
+    let available = cop0::Status::new().critical_section(|_| {
+        // SAFETY: We're in a critical section so no other code can access the
+        // flag
+        unsafe { !core::mem::replace(&mut *addr_of_mut!(PLAYING), true) }
+    });
+    if !available {
+        return Err(Error::Busy)
+    }
+    // SAFETY: The flag ensures that only one movie uses the buffers
+    let res = f(unsafe { &mut *addr_of_mut!(BUFFERS) });
+    // SAFETY: The buffers aren't borrowed anymore
+    unsafe { *addr_of_mut!(PLAYING) = false };
+    res
+}
+
+/// An STR movie on the disc.
+#[derive(Debug)]
+pub struct Movie<'a> {
+    cdrom: &'a mut CdRom,
+    lba: u32,
+    sectors: u32,
+    audio: Option<Channel>,
+    frames: u32,
+}
+
+impl<'a> Movie<'a> {
+    /// Opens the movie at `path`.
+    pub fn open<const N: usize>(
+        cdrom: &'a mut CdRom, fs: &Filesystem<N>, path: &str,
+    ) -> Result<Self, Error> {
+        let entry = fs.find(path)?;
+        Ok(Movie::new(cdrom, entry.lba(), entry.sectors()))
+    }
+
+    /// Creates a movie from the position and length of its file.
+    pub fn new(cdrom: &'a mut CdRom, lba: u32, sectors: u32) -> Self {
+        Movie {
+            cdrom,
+            lba,
+            sectors,
+            audio: Some(Channel::new(1, 1)),
+            frames: 0,
+        }
+    }
+
+    /// Sets the channel the audio is played from or `None` to play the
+    /// movie silently.
+    ///
+    /// This defaults to file 1, channel 1.
+    pub fn set_audio(&mut self, audio: Option<Channel>) -> &mut Self {
+        self.audio = audio;
+        self
+    }
+
+    /// Gets the number of frames shown by the last playback.
+    pub fn frames_shown(&self) -> u32 {
+        self.frames
+    }
+
+    /// Plays the whole movie into `fb`.
+    ///
+    /// Frames are decoded into the buffer being drawn to, centered, and the
+    /// buffers are swapped on the next vblank. The drive mode is restored
+    /// when the movie ends.
+    pub fn play(&mut self, fb: &mut Framebuffer) -> Result<Playback, Error> {
+        self.play_with(fb, || false)
+    }
+
+    /// Plays the movie until it ends or `button` is pressed on player 1's
+    /// gamepad.
+    pub fn play_skippable(
+        &mut self, fb: &mut Framebuffer, pad: &mut Gamepad, button: Button,
+    ) -> Result<Playback, Error> {
+        self.play_with(fb, || pad.poll_p1().pressed(button))
+    }
+
+    /// Plays the movie until it ends or `skip` returns true.
+    ///
+    /// `skip` is called after each frame and on each vblank while waiting for
+    /// the disc.
+    pub fn play_with(
+        &mut self, fb: &mut Framebuffer, skip: impl FnMut() -> bool,
+    ) -> Result<Playback, Error> {
+        with_buffers(|bufs| {
+            let mode = self.cdrom.mode();
+            let res = self.start(bufs).and_then(|()| self.run(fb, bufs, skip));
+            stop_read();
+            let paused = self.cdrom.pause();
+            // Let the drive finish playing the buffered audio
+            if res == Ok(Playback::Finished) {
+                for _ in 0..DRAIN_VBLANKS {
+                    if xa::buffer_empty() {
+                        break
+                    }
+                    fb.wait_vblank();
+                }
+            }
+            let restored = self.cdrom.set_mode(mode);
+            let playback = res?;
+            paused?;
+            restored?;
+            Ok(playback)
+        })
+    }
+
+    /// Sets the drive mode for the movie and starts reading it.
+    fn start(&mut self, bufs: &mut Buffers) -> Result<(), Error> {
+        let mut mode = self.cdrom.mode();
+        mode.set_speed(Speed::Double)
+            .set_sector_size(SectorSize::Data)
+            .set_xa_adpcm(self.audio.is_some())
+            .set_xa_filter(true);
+        if let Some(channel) = self.audio {
+            xa::set_filter(self.cdrom, channel)?;
+        }
+        self.cdrom.set_mode(mode)?;
+        self.cdrom
+            .start_read(Command::ReadS, self.lba, &mut bufs.ring, SECTOR_WORDS)?;
+        Ok(())
+    }
+
+    /// Decodes and shows frames until the end of the file is reached and the
+    /// ring buffer is empty.
+    fn run(
+        &mut self, fb: &mut Framebuffer, bufs: &mut Buffers, mut skip: impl FnMut() -> bool,
+    ) -> Result<Playback, Error> {
+        let end = self.lba + self.sectors;
+        let mut decoder = Decoder::new();
+        let mut assembler = Assembler::default();
+        let mut reached_end = false;
+        self.frames = 0;
+        loop {
+            if skip() {
+                return Ok(Playback::Skipped)
+            }
+            let mut complete = None;
+            while let Some(slot) = next_sector()? {
+                let words = &bufs.ring[slot * SECTOR_WORDS..][..SECTOR_WORDS];
+                // SAFETY: The sector's words can be read as bytes
+                let sector = unsafe {
+                    core::slice::from_raw_parts(words.as_ptr().cast::<u8>(), SECTOR_SIZE)
+                };
+                let header = SectorHeader::parse(sector);
+                if let Some(header) = header {
+                    if assembler.push(&header, &sector[HEADER_SIZE..], &mut bufs.frame)? {
+                        complete = Some(header);
+                    }
+                }
+                release_sector();
+                if complete.is_some() {
+                    break
+                }
+            }
+            match complete {
+                Some(header) => {
+                    let offset = fb.draw_offset() + centering(&header, fb.resolution())?;
+                    let len = (header.frame_size as usize).min(bufs.frame.len());
+                    decoder.decode_bs(
+                        &bufs.frame[..len],
+                        header.width,
+                        header.height,
+                        (offset.0, offset.1),
+                        &mut bufs.codes,
+                        &mut bufs.columns,
+                    )?;
+                    fb.wait_vblank();
+                    fb.swap();
+                    self.frames += 1;
+                },
+                None if reached_end => return Ok(Playback::Finished),
+                None => {
+                    reached_end = match xa::last_header(self.cdrom) {
+                        Ok(header) => header.lba() + 1 >= end,
+                        // No sector header was read yet
+                        Err(cdrom::Error::Command(_, cdrom::NOT_READY)) => false,
+                        Err(cdrom::Error::EndOfDisc) => true,
+                        Err(err) => return Err(err.into()),
+                    };
+                    fb.wait_vblank();
+                },
+            }
+        }
+    }
+}
+
+/// Computes the offset which centers a frame in a framebuffer with resolution
+/// `res`.
+fn centering(header: &SectorHeader, res: Vertex) -> Result<Vertex, Error> {
+    let (width, height) = (header.width as i32, header.height as i32);
+    if header.height > MAX_HEIGHT || width > res.0 as i32 || height > res.1 as i32 {
+        return Err(Error::FrameTooLarge)
+    }
+    Ok(Vertex::new((
+        (res.0 - width as i16) / 2,
+        (res.1 - height as i16) / 2,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{centering, Assembler, Error, SectorHeader, DATA_SIZE, HEADER_SIZE};
+    use crate::gpu::Vertex;
+
+    fn sector(sector: u16, sectors: u16, frame: u32) -> [u8; HEADER_SIZE + 4] {
+        let mut data = [0; HEADER_SIZE + 4];
+        data[0..2].copy_from_slice(&0x0160u16.to_le_bytes());
+        data[2..4].copy_from_slice(&0x8001u16.to_le_bytes());
+        data[4..6].copy_from_slice(&sector.to_le_bytes());
+        data[6..8].copy_from_slice(&sectors.to_le_bytes());
+        data[8..12].copy_from_slice(&frame.to_le_bytes());
+        data[12..16].copy_from_slice(&0x1234u32.to_le_bytes());
+        data[16..18].copy_from_slice(&320u16.to_le_bytes());
+        data[18..20].copy_from_slice(&240u16.to_le_bytes());
+        data[HEADER_SIZE..].copy_from_slice(&[sector as u8 + 1; 4]);
+        data
+    }
+
+    #[test_case]
+    fn headers() {
+        let header = SectorHeader::parse(&sector(1, 3, 7)).unwrap();
+        assert!(header.sector == 1);
+        assert!(header.sectors == 3);
+        assert!(header.frame == 7);
+        assert!(header.frame_size == 0x1234);
+        assert!(header.width == 320);
+        assert!(header.height == 240);
+        let mut audio = sector(0, 1, 1);
+        audio[2] = 0;
+        assert!(SectorHeader::parse(&audio).is_none());
+        assert!(SectorHeader::parse(&[0x60, 0x01]).is_none());
+    }
+
+    #[test_case]
+    fn frames() {
+        let mut asm = Assembler::default();
+        let mut buf = [0; 3 * DATA_SIZE];
+        let mut push = |asm: &mut Assembler, idx: u16, frame: u32| {
+            let data = sector(idx, 3, frame);
+            let header = SectorHeader::parse(&data).unwrap();
+            asm.push(&header, &data[HEADER_SIZE..], &mut buf).unwrap()
+        };
+        assert!(!push(&mut asm, 0, 1));
+        assert!(!push(&mut asm, 2, 1));
+        assert!(push(&mut asm, 1, 1));
+        // Frame 2 is dropped when frame 3 starts
+        assert!(!push(&mut asm, 0, 2));
+        assert!(!push(&mut asm, 0, 3));
+        assert!(!push(&mut asm, 1, 3));
+        assert!(push(&mut asm, 2, 3));
+        assert!(buf[DATA_SIZE] == 2);
+        assert!(buf[2 * DATA_SIZE + 3] == 3);
+    }
+
+    #[test_case]
+    fn frame_offsets() {
+        let mut header = SectorHeader::parse(&sector(0, 1, 1)).unwrap();
+        let res = Vertex::new((320, 240));
+        assert!(centering(&header, res) == Ok(Vertex::new((0, 0))));
+        assert!(centering(&header, Vertex::new((640, 480))) == Ok(Vertex::new((160, 120))));
+        header.width = 321;
+        assert!(centering(&header, res) == Err(Error::FrameTooLarge));
+        header.width = 0x8000;
+        assert!(centering(&header, res) == Err(Error::FrameTooLarge));
+        header.width = 320;
+        header.height = 256;
+        assert!(centering(&header, res) == Err(Error::FrameTooLarge));
+    }
+}
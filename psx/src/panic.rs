@@ -1,4 +1,5 @@
 use crate::hw::{cop0, Register};
+use crate::sio;
 use crate::{dprintln, println, Framebuffer};
 
 #[panic_handler]
@@ -45,6 +46,8 @@ fn min_panic(info: &core::panic::PanicInfo) {
         },
     }
     println!("{}", info.message());
+    // Make sure the message gets out if stdout was redirected to the serial port
+    sio::flush_stdout();
 }
 
 fn normal_panic(info: &core::panic::PanicInfo) {
@@ -0,0 +1,440 @@
+//! Serial port (SIO1) driver
+//!
+//! [`Serial`] sends and receives 8N1 characters through the serial port. It
+//! starts out polling the hardware and switches to interrupt-driven ring
+//! buffers after [`Serial::enable_irq`]. The hardware only transmits while
+//! the CTS input is asserted regardless of the [`FlowControl`] setting.
+//!
+//! [`set_stdout`] sends the output of `print!`, `println!` and panics through
+//! a serial port handle instead of the BIOS.
+use crate::hw::cop0::IntSrc;
+use crate::hw::irq::IRQ;
+use crate::hw::sio::{Baud, BaudFactor, CharLength, Control, Data, Mode, RxIRQMode, Status,
+                     StopBits};
+use crate::hw::{cop0, irq, Register};
+use crate::sys::interrupt;
+use crate::sys::interrupt::Handler;
+use crate::time::SYSCLOCK_HZ;
+use core::fmt;
+use core::mem::replace;
+use core::ptr::addr_of_mut;
+use ring::RingBuffer;
+
+mod ring;
+
+/// The size of the TX and RX ring buffers in bytes.
+pub const BUFFER_SIZE: usize = 256;
+/// RTS is deasserted when fewer bytes than this are free in the RX buffer.
+const RTS_THRESHOLD: usize = 32;
+
+/// Error for the serial port.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error {
+    /// The baud rate is zero or can't be generated from the system clock.
+    InvalidBaudRate,
+    /// Received bytes were dropped because the RX FIFO or buffer was full.
+    Overrun,
+    /// A byte was received with the wrong parity.
+    Parity,
+    /// A byte was received with a bad stop bit.
+    Framing,
+}
+
+/// How the serial port signals when it can receive bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlowControl {
+    /// Always assert RTS.
+    None,
+    /// Deassert RTS while the RX buffer is almost full.
+    RtsCts,
+}
+
+static mut TX: RingBuffer<BUFFER_SIZE> = RingBuffer::new();
+static mut RX: RingBuffer<BUFFER_SIZE> = RingBuffer::new();
+static mut RX_ERROR: Option<Error> = None;
+static mut RTS_CTS: bool = false;
+static mut HANDLER: Handler = Handler::new(on_interrupt);
+static mut HANDLER_INSTALLED: bool = false;
+static mut STDOUT: Option<Serial> = None;
+
+/// Gets the baud rate factor and reload value closest to `baud_rate`.
+const fn baud_config(baud_rate: u32) -> Result<(BaudFactor, u16), Error> {
+    if baud_rate == 0 || baud_rate > SYSCLOCK_HZ {
+        return Err(Error::InvalidBaudRate)
+    }
+    let factors = [BaudFactor::Mul1, BaudFactor::Mul16, BaudFactor::Mul64];
+    let mut i = 0;
+    while i < factors.len() {
+        let divisor = baud_rate * factors[i].multiplier();
+        let reload = (SYSCLOCK_HZ + divisor / 2) / divisor;
+        if reload == 0 {
+            break
+        }
+        if reload <= u16::MAX as u32 {
+            return Ok((factors[i], reload as u16))
+        }
+        i += 1;
+    }
+    Err(Error::InvalidBaudRate)
+}
+
+/// Checks the status register's error bits.
+fn check_errors(status: &Status) -> Result<(), Error> {
+    if status.rx_overrun() {
+        Err(Error::Overrun)
+    } else if status.parity_error() {
+        Err(Error::Parity)
+    } else if status.framing_error() {
+        Err(Error::Framing)
+    } else {
+        Ok(())
+    }
+}
+
+/// Moves received bytes from the RX FIFO to the RX buffer.
+///
+/// # Safety
+///
+/// This must be called in a critical section.
+unsafe fn service_rx() {
+    let mut status = Status::new();
+    while status.rx_ready() {
+        let byte = Data::new().get();
+        if !unsafe { RX.push(byte) } {
+            unsafe { RX_ERROR = Some(Error::Overrun) };
+        }
+        status.load();
+    }
+    if unsafe { RTS_CTS && RX.free() < RTS_THRESHOLD } {
+        Control::new().set_rts(false).store();
+    }
+}
+
+/// Moves bytes from the TX buffer to the TX FIFO and requests an interrupt
+/// when the FIFO is ready if any bytes are left.
+///
+/// # Safety
+///
+/// This must be called in a critical section.
+unsafe fn service_tx() {
+    let mut status = Status::new();
+    while status.tx_ready() {
+        match unsafe { TX.pop() } {
+            Some(byte) => Data::skip_load().set(byte).store(),
+            None => break,
+        };
+        status.load();
+    }
+    Control::new().tx_irq(unsafe { !TX.is_empty() }).store();
+}
+
+extern "C" fn on_interrupt() -> u32 {
+    if irq::Mask::new().irq_disabled(IRQ::SIO) || !irq::Status::new().requested(IRQ::SIO) {
+        return 0
+    }
+    let status = Status::new();
+    // SAFETY: This runs in the BIOS interrupt handler so it can't be
+    // interrupted by the `Serial` methods
+    unsafe {
+        if let Err(err) = check_errors(&status) {
+            RX_ERROR = Some(err);
+        }
+    }
+    Control::new().ack().store();
+    // Writing ones leaves the other interrupt requests alone
+    irq::Status::skip_load().assign(!0).ack(IRQ::SIO).store();
+    // SAFETY: We're in the interrupt handler
+    unsafe {
+        service_rx();
+        service_tx();
+    }
+    0
+}
+
+/// A handle to the serial port configured for 8N1 characters.
+#[derive(Debug)]
+pub struct Serial {
+    flow_control: FlowControl,
+    baud_rate: u32,
+    buffered: bool,
+}
+
+impl Serial {
+    /// Resets the serial port and configures it for 8N1 characters at the
+    /// closest possible rate to `baud_rate`.
+    pub fn new(baud_rate: u32, flow_control: FlowControl) -> Result<Self, Error> {
+        let (factor, reload) = baud_config(baud_rate)?;
+        cop0::Status::new().critical_section(|_| {
+            irq::Mask::new().disable_irq(IRQ::SIO).store();
+            Control::skip_load().reset().store();
+            Mode::skip_load()
+                .set_baud_factor(factor)
+                .set_char_length(CharLength::Bits8)
+                .set_parity(None)
+                .set_stop_bits(StopBits::One)
+                .store();
+            Baud::skip_load().set_reload(reload).store();
+            Control::skip_load()
+                .enable_tx(true)
+                .enable_rx(true)
+                .set_dtr(true)
+                .set_rts(true)
+                .ack()
+                .store();
+            // SAFETY: We're in a critical section so the interrupt handler
+            // can't access the buffers
+            unsafe {
+                TX.clear();
+                RX.clear();
+                RX_ERROR = None;
+                RTS_CTS = flow_control == FlowControl::RtsCts;
+            }
+        });
+        Ok(Serial {
+            flow_control,
+            baud_rate: SYSCLOCK_HZ / (reload as u32 * factor.multiplier()),
+            buffered: false,
+        })
+    }
+
+    /// Gets the actual baud rate.
+    pub fn baud_rate(&self) -> u32 {
+        self.baud_rate
+    }
+
+    /// Gets the flow control setting.
+    pub fn flow_control(&self) -> FlowControl {
+        self.flow_control
+    }
+
+    /// Changes the baud rate to the closest possible rate to `baud_rate`.
+    ///
+    /// Call [`Serial::flush`] first to avoid corrupting bytes being sent.
+    pub fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Error> {
+        let (factor, reload) = baud_config(baud_rate)?;
+        Mode::new().set_baud_factor(factor).store();
+        Baud::skip_load().set_reload(reload).store();
+        self.baud_rate = SYSCLOCK_HZ / (reload as u32 * factor.multiplier());
+        Ok(())
+    }
+
+    /// Sends and receives bytes through ring buffers from the serial port's
+    /// interrupt handler.
+    ///
+    /// This installs the interrupt handler if necessary and enables the
+    /// serial port's interrupt in [`irq::Mask`] and hardware interrupts in
+    /// cop0.
+    pub fn enable_irq(&mut self) {
+        cop0::Status::new().critical_section(|_| {
+            // SAFETY: We're in a critical section so the interrupt handler
+            // can't run
+            unsafe {
+                if !HANDLER_INSTALLED {
+                    interrupt::enqueue(interrupt::DEFAULT_PRIORITY, &mut *addr_of_mut!(HANDLER));
+                    HANDLER_INSTALLED = true;
+                }
+            }
+            Control::new()
+                .set_rx_irq_mode(RxIRQMode::Bytes1)
+                .rx_irq(true)
+                .tx_irq(false)
+                .ack()
+                .store();
+            irq::Mask::new().enable_irq(IRQ::SIO).store();
+        });
+        cop0::Status::new()
+            .unmask_interrupt(IntSrc::Hardware)
+            .enable_interrupts()
+            .store();
+        self.buffered = true;
+    }
+
+    /// Stops using interrupts after sending all buffered bytes.
+    ///
+    /// Bytes left in the RX buffer can still be read.
+    pub fn disable_irq(&mut self) {
+        self.flush();
+        cop0::Status::new().critical_section(|_| {
+            irq::Mask::new().disable_irq(IRQ::SIO).store();
+            Control::new().rx_irq(false).tx_irq(false).store();
+        });
+        self.buffered = false;
+    }
+
+    /// Checks if bytes are sent and received through interrupts.
+    pub fn irq_enabled(&self) -> bool {
+        self.buffered
+    }
+
+    /// Sends a byte, blocking until there's room for it.
+    pub fn write_byte(&mut self, byte: u8) {
+        if !self.buffered {
+            Status::new().wait_tx_ready();
+            Data::skip_load().set(byte).store();
+            return
+        }
+        loop {
+            // Servicing the FIFO here means this doesn't deadlock if
+            // interrupts are disabled
+            let queued = cop0::Status::new().critical_section(|_| {
+                // SAFETY: We're in a critical section so the interrupt
+                // handler can't access the buffers
+                unsafe {
+                    let queued = TX.push(byte);
+                    service_tx();
+                    queued
+                }
+            });
+            if queued {
+                return
+            }
+        }
+    }
+
+    /// Sends `data`, blocking until all of it is sent or buffered.
+    pub fn write(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.write_byte(byte);
+        }
+    }
+
+    /// Blocks until all buffered bytes are sent.
+    pub fn flush(&mut self) {
+        if self.buffered {
+            loop {
+                let empty = cop0::Status::new().critical_section(|_| {
+                    // SAFETY: We're in a critical section so the interrupt
+                    // handler can't access the buffers
+                    unsafe {
+                        service_tx();
+                        TX.is_empty()
+                    }
+                });
+                if empty {
+                    break
+                }
+            }
+        }
+        Status::new().wait_tx_idle();
+    }
+
+    /// Receives a byte if one is available.
+    ///
+    /// Returns an error if bytes were dropped or received with errors since
+    /// the last call.
+    pub fn try_read_byte(&mut self) -> Result<Option<u8>, Error> {
+        let flow_control = self.flow_control;
+        cop0::Status::new().critical_section(|_| {
+            // SAFETY: We're in a critical section so the interrupt handler
+            // can't access the buffers
+            unsafe {
+                if let Some(err) = RX_ERROR.take() {
+                    return Err(err)
+                }
+                if let Some(byte) = RX.pop() {
+                    if flow_control == FlowControl::RtsCts && RX.free() >= 2 * RTS_THRESHOLD {
+                        Control::new().set_rts(true).store();
+                    }
+                    return Ok(Some(byte))
+                }
+            }
+            let status = Status::new();
+            if let Err(err) = check_errors(&status) {
+                Control::new().ack().store();
+                return Err(err)
+            }
+            if status.rx_ready() {
+                Ok(Some(Data::new().get()))
+            } else {
+                Ok(None)
+            }
+        })
+    }
+
+    /// Receives a byte, blocking until one is available.
+    pub fn read_byte(&mut self) -> Result<u8, Error> {
+        loop {
+            if let Some(byte) = self.try_read_byte()? {
+                return Ok(byte)
+            }
+        }
+    }
+
+    /// Receives the available bytes into `buf` without blocking.
+    ///
+    /// Returns the number of bytes received.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        for (i, dst) in buf.iter_mut().enumerate() {
+            match self.try_read_byte()? {
+                Some(byte) => *dst = byte,
+                None => return Ok(i),
+            }
+        }
+        Ok(buf.len())
+    }
+}
+
+/// Sends the output of `print!`, `println!` and panics through `serial`
+/// instead of the BIOS.
+///
+/// Returns the previous stdout serial port handle, if any.
+pub fn set_stdout(serial: Serial) -> Option<Serial> {
+    let mut serial = Some(serial);
+    // SAFETY: We're in a critical section so printing from an interrupt
+    // handler can't access `STDOUT`
+    cop0::Status::new()
+        .critical_section(|_| unsafe { replace(&mut *addr_of_mut!(STDOUT), serial.take()) })
+}
+
+/// Sends the output of `print!`, `println!` and panics back through the BIOS.
+///
+/// Returns the stdout serial port handle, if any.
+pub fn take_stdout() -> Option<Serial> {
+    // SAFETY: We're in a critical section so printing from an interrupt
+    // handler can't access `STDOUT`
+    cop0::Status::new().critical_section(|_| unsafe { (*addr_of_mut!(STDOUT)).take() })
+}
+
+/// Sends `msg` through the stdout serial port, returning false if stdout
+/// isn't redirected.
+pub(crate) fn write_stdout(msg: &[u8]) -> bool {
+    // SAFETY: Printing is currently not reentrant so this ignores interrupts
+    match unsafe { (*addr_of_mut!(STDOUT)).as_mut() } {
+        Some(serial) => {
+            serial.write(msg);
+            true
+        },
+        None => false,
+    }
+}
+
+/// Blocks until all bytes buffered by the stdout serial port are sent.
+pub(crate) fn flush_stdout() {
+    // SAFETY: Printing is currently not reentrant so this ignores interrupts
+    if let Some(serial) = unsafe { (*addr_of_mut!(STDOUT)).as_mut() } {
+        serial.flush();
+    }
+}
+
+impl fmt::Write for Serial {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write(s.as_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{baud_config, Error};
+    use crate::hw::sio::BaudFactor;
+
+    #[test_case]
+    fn baud_rates() {
+        assert!(baud_config(115_200) == Ok((BaudFactor::Mul1, 294)));
+        assert!(baud_config(9_600) == Ok((BaudFactor::Mul1, 3_528)));
+        assert!(baud_config(300) == Ok((BaudFactor::Mul16, 7_056)));
+        assert!(baud_config(0) == Err(Error::InvalidBaudRate));
+        assert!(baud_config(1) == Err(Error::InvalidBaudRate));
+        assert!(baud_config(u32::MAX) == Err(Error::InvalidBaudRate));
+    }
+}
@@ -0,0 +1,81 @@
+/// A fixed-size FIFO of bytes.
+#[derive(Debug)]
+pub struct RingBuffer<const N: usize> {
+    data: [u8; N],
+    start: usize,
+    len: usize,
+}
+
+impl<const N: usize> RingBuffer<N> {
+    /// Creates an empty buffer.
+    pub const fn new() -> Self {
+        RingBuffer {
+            data: [0; N],
+            start: 0,
+            len: 0,
+        }
+    }
+
+    /// Checks if the buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Gets the number of bytes which can be pushed before the buffer is full.
+    pub fn free(&self) -> usize {
+        N - self.len
+    }
+
+    /// Pushes a byte to the back of the buffer, returning false if it's full.
+    pub fn push(&mut self, byte: u8) -> bool {
+        if self.len == N {
+            return false
+        }
+        self.data[(self.start + self.len) % N] = byte;
+        self.len += 1;
+        true
+    }
+
+    /// Pops the byte at the front of the buffer.
+    pub fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None
+        }
+        let byte = self.data[self.start];
+        self.start = (self.start + 1) % N;
+        self.len -= 1;
+        Some(byte)
+    }
+
+    /// Removes all bytes from the buffer.
+    pub fn clear(&mut self) {
+        self.start = 0;
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RingBuffer;
+
+    #[test_case]
+    fn push_pop() {
+        let mut ring = RingBuffer::<4>::new();
+        assert!(ring.pop().is_none());
+        for byte in 0..4 {
+            assert!(ring.push(byte));
+        }
+        assert!(!ring.push(4));
+        assert!(ring.free() == 0);
+        assert!(ring.pop() == Some(0));
+        assert!(ring.pop() == Some(1));
+        // Wrap around the end of the buffer
+        assert!(ring.push(5));
+        assert!(ring.push(6));
+        assert!(ring.free() == 0);
+        for byte in [2, 3, 5, 6] {
+            assert!(ring.pop() == Some(byte));
+        }
+        assert!(ring.is_empty());
+    }
+}
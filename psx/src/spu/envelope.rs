@@ -0,0 +1,189 @@
+use crate::hw::spu::{SweepDirection, SweepMode};
+
+const ATTACK_MODE: u32 = 15;
+const ATTACK_SHIFT: u32 = 10;
+const ATTACK_STEP: u32 = 8;
+const DECAY_SHIFT: u32 = 4;
+const SUSTAIN_LEVEL: u32 = 0;
+const SUSTAIN_MODE: u32 = 31;
+const SUSTAIN_DIRECTION: u32 = 30;
+const SUSTAIN_SHIFT: u32 = 24;
+const SUSTAIN_STEP: u32 = 22;
+const RELEASE_MODE: u32 = 21;
+const RELEASE_SHIFT: u32 = 16;
+
+/// A voice's attack, decay, sustain and release envelope.
+///
+/// Shifts range from `0` (fastest) to `0x1F` (slowest) except for the decay
+/// shift which ranges from `0` to `0xF`. Steps range from `0` to `3` and
+/// correspond to `+7` to `+4` when increasing or `-8` to `-5` when decreasing.
+/// Out of range values are truncated.
+///
+/// The decay phase is always exponential and the release phase always
+/// decreases so their modes and directions can't be changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Envelope {
+    attack_mode: SweepMode,
+    attack_shift: u8,
+    attack_step: u8,
+    decay_shift: u8,
+    sustain_level: u8,
+    sustain_mode: SweepMode,
+    sustain_direction: SweepDirection,
+    sustain_shift: u8,
+    sustain_step: u8,
+    release_mode: SweepMode,
+    release_shift: u8,
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Envelope {
+    /// Creates an envelope which starts at full volume, holds it until the
+    /// voice is keyed off and then quickly fades out.
+    pub const fn new() -> Self {
+        Envelope {
+            attack_mode: SweepMode::Linear,
+            attack_shift: 0,
+            attack_step: 0,
+            decay_shift: 0,
+            sustain_level: 0xF,
+            sustain_mode: SweepMode::Linear,
+            sustain_direction: SweepDirection::Increase,
+            sustain_shift: 0x1F,
+            sustain_step: 0,
+            release_mode: SweepMode::Exponential,
+            release_shift: 0x8,
+        }
+    }
+
+    /// Sets the attack phase which ramps the volume up to its maximum after a
+    /// key on.
+    pub const fn attack(mut self, mode: SweepMode, shift: u8, step: u8) -> Self {
+        self.attack_mode = mode;
+        self.attack_shift = shift & 0x1F;
+        self.attack_step = step & 0b11;
+        self
+    }
+
+    /// Sets the decay phase which exponentially lowers the volume to the
+    /// sustain level after the attack phase.
+    pub const fn decay(mut self, shift: u8) -> Self {
+        self.decay_shift = shift & 0xF;
+        self
+    }
+
+    /// Sets the level from `0` to `0xF` at which the decay phase ends.
+    ///
+    /// Each level is `0x800` so `0xF` is the maximum volume.
+    pub const fn sustain_level(mut self, level: u8) -> Self {
+        self.sustain_level = level & 0xF;
+        self
+    }
+
+    /// Sets the sustain phase which runs after the decay phase until the
+    /// voice is keyed off.
+    pub const fn sustain(
+        mut self, mode: SweepMode, direction: SweepDirection, shift: u8, step: u8,
+    ) -> Self {
+        self.sustain_mode = mode;
+        self.sustain_direction = direction;
+        self.sustain_shift = shift & 0x1F;
+        self.sustain_step = step & 0b11;
+        self
+    }
+
+    /// Sets the release phase which lowers the volume to zero after the voice
+    /// is keyed off.
+    pub const fn release(mut self, mode: SweepMode, shift: u8) -> Self {
+        self.release_mode = mode;
+        self.release_shift = shift & 0x1F;
+        self
+    }
+
+    /// Encodes the envelope in the layout used by the
+    /// [`ADSR`][crate::hw::spu::ADSR] register.
+    pub const fn to_bits(&self) -> u32 {
+        (self.attack_mode as u32) << ATTACK_MODE |
+            (self.attack_shift as u32) << ATTACK_SHIFT |
+            (self.attack_step as u32) << ATTACK_STEP |
+            (self.decay_shift as u32) << DECAY_SHIFT |
+            (self.sustain_level as u32) << SUSTAIN_LEVEL |
+            (self.sustain_mode as u32) << SUSTAIN_MODE |
+            (self.sustain_direction as u32) << SUSTAIN_DIRECTION |
+            (self.sustain_shift as u32) << SUSTAIN_SHIFT |
+            (self.sustain_step as u32) << SUSTAIN_STEP |
+            (self.release_mode as u32) << RELEASE_MODE |
+            (self.release_shift as u32) << RELEASE_SHIFT
+    }
+
+    /// Decodes an envelope from the layout used by the
+    /// [`ADSR`][crate::hw::spu::ADSR] register.
+    pub const fn from_bits(bits: u32) -> Self {
+        const fn mode(bits: u32, bit: u32) -> SweepMode {
+            if bits & (1 << bit) != 0 {
+                SweepMode::Exponential
+            } else {
+                SweepMode::Linear
+            }
+        }
+        let sustain_direction = if bits & (1 << SUSTAIN_DIRECTION) != 0 {
+            SweepDirection::Decrease
+        } else {
+            SweepDirection::Increase
+        };
+        Envelope {
+            attack_mode: mode(bits, ATTACK_MODE),
+            attack_shift: ((bits >> ATTACK_SHIFT) & 0x1F) as u8,
+            attack_step: ((bits >> ATTACK_STEP) & 0b11) as u8,
+            decay_shift: ((bits >> DECAY_SHIFT) & 0xF) as u8,
+            sustain_level: ((bits >> SUSTAIN_LEVEL) & 0xF) as u8,
+            sustain_mode: mode(bits, SUSTAIN_MODE),
+            sustain_direction,
+            sustain_shift: ((bits >> SUSTAIN_SHIFT) & 0x1F) as u8,
+            sustain_step: ((bits >> SUSTAIN_STEP) & 0b11) as u8,
+            release_mode: mode(bits, RELEASE_MODE),
+            release_shift: ((bits >> RELEASE_SHIFT) & 0x1F) as u8,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Envelope;
+    use crate::hw::spu::{SweepDirection, SweepMode};
+
+    #[test_case]
+    fn encode() {
+        let env = Envelope::new()
+            .attack(SweepMode::Exponential, 0x1F, 3)
+            .decay(0xF)
+            .sustain_level(0xA)
+            .sustain(SweepMode::Exponential, SweepDirection::Decrease, 0x13, 2)
+            .release(SweepMode::Exponential, 0x1D);
+        assert!(env.to_bits() == 0xD3BD_FFFA);
+        assert!(Envelope::new().to_bits() == 0x1F28_000F);
+    }
+
+    #[test_case]
+    fn truncate() {
+        let env = Envelope::new()
+            .attack(SweepMode::Linear, 0xFF, 0xFF)
+            .decay(0xFF)
+            .sustain_level(0xFF);
+        assert!(env.to_bits() & 0xFFFF == 0x7FFF);
+    }
+
+    #[test_case]
+    fn round_trip() {
+        fuzz!(|bits: u32| {
+            // Bit 29 is unused
+            let bits = bits & !(1 << 29);
+            assert!(Envelope::from_bits(bits).to_bits() == bits);
+        });
+    }
+}
@@ -0,0 +1,81 @@
+//! Sound processing unit routines
+//!
+//! This module provides a high-level interface to the SPU voices built on the
+//! registers in [`hw::spu`][crate::hw::spu].
+use crate::dma;
+use crate::hw::spu::{CDVolumeLeft, CDVolumeRight, Control, ExternalVolumeLeft,
+                     ExternalVolumeRight, KeyOff, MainVolumeLeft, MainVolumeRight, Noise,
+                     PitchModulation, ReverbEnable, ReverbVolumeLeft, ReverbVolumeRight, Status,
+                     TransferControl, TransferMode, VoiceMask, Volume};
+use crate::hw::Register;
+
+mod envelope;
+mod ram;
+mod reverb;
+/// SPU RAM transfers.
+pub mod transfer;
+mod voice;
+
+pub use crate::hw::spu::{SweepDirection, SweepMode, NUM_VOICES};
+pub use envelope::Envelope;
+pub use ram::{RamAllocator, Sample, Usage, MAX_SAMPLES};
+pub use reverb::{ReverbConfig, ReverbMode};
+pub use voice::{note_sample_rate, Voice, VoiceAllocator};
+
+/// The size of SPU RAM in bytes.
+pub const RAM_SIZE: usize = 512 * 1024;
+
+/// An SPU-specific error.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error {
+    /// There isn't a large enough free region in SPU RAM.
+    OutOfMemory,
+    /// The allocator can't track any more samples.
+    TooManySamples,
+    /// The sample wasn't allocated by this allocator.
+    UnknownSample,
+    /// A DMA transfer to or from SPU RAM failed.
+    Transfer(dma::Error),
+}
+
+impl From<dma::Error> for Error {
+    fn from(err: dma::Error) -> Self {
+        Error::Transfer(err)
+    }
+}
+
+/// Resets the SPU, keys off all voices and sets the main volume to the
+/// maximum.
+///
+/// Reverb, CD audio and external audio volumes are set to zero.
+pub fn init() {
+    let mut control = Control::skip_load();
+    control.store();
+    Status::new().wait_mode(TransferMode::Stop);
+
+    KeyOff::skip_load().add_all().store();
+    PitchModulation::skip_load().store();
+    Noise::skip_load().store();
+    ReverbEnable::skip_load().store();
+    set_reverb_volume(0, 0);
+    CDVolumeLeft::skip_load().set_fixed(0).store();
+    CDVolumeRight::skip_load().set_fixed(0).store();
+    ExternalVolumeLeft::skip_load().set_fixed(0).store();
+    ExternalVolumeRight::skip_load().set_fixed(0).store();
+    TransferControl::skip_load().set_normal().store();
+    set_main_volume(i16::MAX, i16::MAX);
+
+    control.enable(true).mute(false).store();
+}
+
+/// Sets the main output volume.
+pub fn set_main_volume(left: i16, right: i16) {
+    MainVolumeLeft::skip_load().set_fixed(left).store();
+    MainVolumeRight::skip_load().set_fixed(right).store();
+}
+
+/// Sets the reverb unit's output volume.
+pub fn set_reverb_volume(left: i16, right: i16) {
+    ReverbVolumeLeft::skip_load().set_fixed(left).store();
+    ReverbVolumeRight::skip_load().set_fixed(right).store();
+}
@@ -0,0 +1,373 @@
+use crate::dma;
+use crate::format::vag::VAG;
+use crate::spu::{transfer, Error, ReverbMode, RAM_SIZE};
+
+/// The maximum number of samples a [`RamAllocator`] can track.
+pub const MAX_SAMPLES: usize = 128;
+
+/// The end of the capture buffers at the start of SPU RAM.
+const CAPTURE_BUFFERS_END: u32 = 0x1000;
+/// The alignment of SPU RAM addresses in bytes.
+const ALIGN: u32 = 8;
+/// The smallest buffer [`RamAllocator::compact`] accepts in words.
+const MIN_BUFFER_WORDS: usize = 16;
+
+/// An ADPCM sample in SPU RAM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sample {
+    address: u32,
+    size: u32,
+    sample_rate: u32,
+}
+
+impl Sample {
+    /// Gets the sample's address in SPU RAM in bytes.
+    pub fn address(&self) -> u32 {
+        self.address
+    }
+
+    /// Gets the sample's size in bytes.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Gets the sample rate in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Region {
+    address: u32,
+    size: u32,
+}
+
+impl Region {
+    fn end(&self) -> u32 {
+        self.address + self.size
+    }
+}
+
+/// A summary of SPU RAM usage returned by [`RamAllocator::usage`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Usage {
+    /// The number of bytes used by samples.
+    pub used: u32,
+    /// The number of bytes available for samples.
+    pub free: u32,
+    /// The size of the largest free region in bytes.
+    pub largest_free: u32,
+    /// The number of free regions.
+    pub free_regions: usize,
+}
+
+impl Usage {
+    /// Gets the percentage of free memory which isn't in the largest free
+    /// region.
+    pub fn fragmentation(&self) -> u32 {
+        if self.free == 0 {
+            0
+        } else {
+            100 - (self.largest_free * 100 / self.free)
+        }
+    }
+}
+
+/// An allocator for samples in SPU RAM.
+///
+/// The capture buffers at the start of SPU RAM and the reverb work area at the
+/// end are never handed out. All regions are 8-byte aligned.
+#[derive(Debug)]
+pub struct RamAllocator {
+    regions: [Region; MAX_SAMPLES],
+    len: usize,
+    reverb_start: u32,
+}
+
+impl Default for RamAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+const fn align(size: u32) -> u32 {
+    (size + ALIGN - 1) & !(ALIGN - 1)
+}
+
+impl RamAllocator {
+    /// Creates an allocator for an empty SPU RAM with the work area for
+    /// [`ReverbMode::Off`][crate::spu::ReverbMode::Off] reserved.
+    pub const fn new() -> Self {
+        RamAllocator {
+            regions: [Region {
+                address: 0,
+                size: 0,
+            }; MAX_SAMPLES],
+            len: 0,
+            reverb_start: RAM_SIZE as u32 - align(ReverbMode::Off.work_area_size()),
+        }
+    }
+
+    fn regions(&self) -> &[Region] {
+        &self.regions[..self.len]
+    }
+
+    /// Gets the start address of the reverb work area in bytes.
+    pub fn reverb_work_area(&self) -> u32 {
+        self.reverb_start
+    }
+
+    /// Reserves `size` bytes at the end of SPU RAM for the reverb work area.
+    ///
+    /// The size for each preset is given by
+    /// [`ReverbMode::work_area_size`][crate::spu::ReverbMode::work_area_size].
+    /// Returns [`Error::OutOfMemory`] if the work area would overlap a sample.
+    pub fn set_reverb_work_area(&mut self, size: u32) -> Result<(), Error> {
+        if size > RAM_SIZE as u32 - CAPTURE_BUFFERS_END {
+            return Err(Error::OutOfMemory)
+        }
+        let size = align(size);
+        let start = RAM_SIZE as u32 - size;
+        let used_end = self
+            .regions()
+            .last()
+            .map_or(CAPTURE_BUFFERS_END, Region::end);
+        if used_end > start {
+            return Err(Error::OutOfMemory)
+        }
+        self.reverb_start = start;
+        Ok(())
+    }
+
+    /// Allocates `size` bytes for a sample played at `sample_rate` Hz.
+    ///
+    /// The region is placed in the first gap large enough to hold it. Use
+    /// [`transfer`][crate::spu::transfer] to write to it or use
+    /// [`RamAllocator::upload`] instead.
+    pub fn alloc(&mut self, size: u32, sample_rate: u32) -> Result<Sample, Error> {
+        if self.len == MAX_SAMPLES {
+            return Err(Error::TooManySamples)
+        }
+        // Check before aligning since that could overflow
+        if size > RAM_SIZE as u32 {
+            return Err(Error::OutOfMemory)
+        }
+        let aligned = align(size.max(1));
+        let mut start = CAPTURE_BUFFERS_END;
+        let mut idx = self.len;
+        for (i, region) in self.regions().iter().enumerate() {
+            if region.address - start >= aligned {
+                idx = i;
+                break
+            }
+            start = region.end();
+        }
+        if idx == self.len && self.reverb_start.saturating_sub(start) < aligned {
+            return Err(Error::OutOfMemory)
+        }
+        self.regions.copy_within(idx..self.len, idx + 1);
+        self.regions[idx] = Region {
+            address: start,
+            size: aligned,
+        };
+        self.len += 1;
+        Ok(Sample {
+            address: start,
+            size,
+            sample_rate,
+        })
+    }
+
+    /// Allocates space for a VAG file's ADPCM data and uploads it to SPU RAM.
+    pub fn upload(&mut self, vag: &VAG, spu_dma: &mut dma::SPU) -> Result<Sample, Error> {
+        let sample = self.alloc(vag.data.len() as u32, vag.sample_rate)?;
+        transfer::write(sample.address, vag.data, spu_dma);
+        Ok(sample)
+    }
+
+    /// Uploads a bank of VAG files to SPU RAM.
+    ///
+    /// If any file doesn't fit, the files uploaded so far are freed.
+    pub fn upload_bank<const N: usize>(
+        &mut self, vags: &[VAG; N], spu_dma: &mut dma::SPU,
+    ) -> Result<[Sample; N], Error> {
+        let mut samples = [Sample {
+            address: 0,
+            size: 0,
+            sample_rate: 0,
+        }; N];
+        for i in 0..N {
+            match self.upload(&vags[i], spu_dma) {
+                Ok(sample) => samples[i] = sample,
+                Err(err) => {
+                    for &sample in &samples[..i] {
+                        // These were just allocated so this can't fail
+                        self.free(sample).ok();
+                    }
+                    return Err(err)
+                },
+            }
+        }
+        Ok(samples)
+    }
+
+    fn find(&self, address: u32) -> Option<usize> {
+        self.regions()
+            .binary_search_by_key(&address, |region| region.address)
+            .ok()
+    }
+
+    /// Frees a sample's region of SPU RAM.
+    ///
+    /// Returns [`Error::UnknownSample`] if the sample wasn't allocated by this
+    /// allocator.
+    pub fn free(&mut self, sample: Sample) -> Result<(), Error> {
+        let idx = self.find(sample.address).ok_or(Error::UnknownSample)?;
+        if sample.size > RAM_SIZE as u32 || self.regions[idx].size != align(sample.size.max(1)) {
+            return Err(Error::UnknownSample)
+        }
+        self.regions.copy_within(idx + 1..self.len, idx);
+        self.len -= 1;
+        Ok(())
+    }
+
+    /// Frees all samples, e.g. between levels.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Moves all samples to the start of SPU RAM to merge the free regions.
+    ///
+    /// `samples` must contain every sample allocated by this allocator and is
+    /// updated with their new addresses. The ADPCM data is copied through
+    /// `buffer` which should be as large as possible. Returns
+    /// [`Error::UnknownSample`] without moving anything if `samples` doesn't
+    /// match the allocated samples. Panics if `buffer` is smaller than 16
+    /// words.
+    pub fn compact(
+        &mut self, samples: &mut [Sample], buffer: &mut [u32], spu_dma: &mut dma::SPU,
+    ) -> Result<(), Error> {
+        assert!(
+            buffer.len() >= MIN_BUFFER_WORDS,
+            "SPU RAM compaction buffer is too small"
+        );
+        let all_known = samples
+            .iter()
+            .all(|sample| self.find(sample.address).is_some());
+        let all_listed = self.regions().iter().all(|region| {
+            samples
+                .iter()
+                .any(|sample| sample.address == region.address)
+        });
+        if !all_known || !all_listed {
+            return Err(Error::UnknownSample)
+        }
+        let chunk = (buffer.len() - buffer.len() % MIN_BUFFER_WORDS) as u32 * 4;
+        let mut next = CAPTURE_BUFFERS_END;
+        for i in 0..self.len {
+            let Region { address, size } = self.regions[i];
+            if address != next {
+                let mut offset = 0;
+                while offset < size {
+                    let words = &mut buffer[..(chunk.min(size - offset) / 4) as usize];
+                    transfer::read_dma(address + offset, words, spu_dma)?;
+                    transfer::write_dma(next + offset, words, spu_dma)?;
+                    offset += chunk;
+                }
+                for sample in samples.iter_mut().filter(|s| s.address == address) {
+                    sample.address = next;
+                }
+                self.regions[i].address = next;
+            }
+            next += size;
+        }
+        Ok(())
+    }
+
+    /// Summarizes the current SPU RAM usage.
+    pub fn usage(&self) -> Usage {
+        let mut usage = Usage {
+            used: 0,
+            free: 0,
+            largest_free: 0,
+            free_regions: 0,
+        };
+        let mut start = CAPTURE_BUFFERS_END;
+        let gaps = self
+            .regions()
+            .iter()
+            .map(|region| (region.address, region.end()))
+            .chain([(self.reverb_start, self.reverb_start)]);
+        for (address, end) in gaps {
+            let gap = address - start;
+            if gap != 0 {
+                usage.free += gap;
+                usage.largest_free = usage.largest_free.max(gap);
+                usage.free_regions += 1;
+            }
+            usage.used += end - address;
+            start = end;
+        }
+        usage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RamAllocator, CAPTURE_BUFFERS_END};
+    use crate::spu::{Error, ReverbMode, RAM_SIZE};
+
+    const TOTAL: u32 = RAM_SIZE as u32 - CAPTURE_BUFFERS_END - 0x10;
+
+    #[test_case]
+    fn alloc_free() {
+        let mut ram = RamAllocator::new();
+        let a = ram.alloc(0x100, 44_100).unwrap();
+        let b = ram.alloc(0x13, 22_050).unwrap();
+        let c = ram.alloc(0x200, 11_025).unwrap();
+        assert!(a.address() == CAPTURE_BUFFERS_END);
+        assert!(b.address() == a.address() + 0x100);
+        assert!(c.address() == b.address() + 0x18);
+        assert!(ram.usage().used == 0x318);
+        assert!(ram.usage().free == TOTAL - 0x318);
+
+        ram.free(b).unwrap();
+        assert!(ram.free(b) == Err(Error::UnknownSample));
+        let usage = ram.usage();
+        assert!(usage.free_regions == 2);
+        assert!(usage.largest_free == TOTAL - 0x318);
+        assert!(usage.fragmentation() == 1);
+
+        // First fit reuses the gap left by `b`
+        let d = ram.alloc(0x8, 44_100).unwrap();
+        assert!(d.address() == b.address());
+        ram.clear();
+        assert!(ram.usage().free == TOTAL);
+    }
+
+    #[test_case]
+    fn out_of_memory() {
+        let mut ram = RamAllocator::new();
+        assert!(ram.alloc(TOTAL + 1, 44_100) == Err(Error::OutOfMemory));
+        assert!(ram.alloc(u32::MAX, 44_100) == Err(Error::OutOfMemory));
+        assert!(ram.set_reverb_work_area(u32::MAX) == Err(Error::OutOfMemory));
+        let all = ram.alloc(TOTAL, 44_100).unwrap();
+        assert!(ram.alloc(1, 44_100) == Err(Error::OutOfMemory));
+        assert!(ram.usage().free == 0);
+        assert!(
+            ram.set_reverb_work_area(ReverbMode::Hall.work_area_size()) == Err(Error::OutOfMemory)
+        );
+        ram.free(all).unwrap();
+        assert!(ram.alloc(0, 44_100).is_ok());
+    }
+
+    #[test_case]
+    fn reverb_work_area() {
+        let mut ram = RamAllocator::new();
+        let size = ReverbMode::Echo.work_area_size();
+        ram.set_reverb_work_area(size).unwrap();
+        assert!(ram.reverb_work_area() == RAM_SIZE as u32 - size);
+        assert!(ram.usage().free == RAM_SIZE as u32 - CAPTURE_BUFFERS_END - size);
+    }
+}
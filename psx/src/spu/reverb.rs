@@ -0,0 +1,317 @@
+use crate::dma;
+use crate::hw::spu::reverb::*;
+use crate::hw::spu::{Control, RAMAddress};
+use crate::hw::Register;
+use crate::spu::{transfer, Error, RamAllocator, RAM_SIZE};
+
+/// The reverb presets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReverbMode {
+    /// No reverb.
+    Off,
+    /// A small room.
+    Room,
+    /// A small studio.
+    StudioSmall,
+    /// A medium studio.
+    StudioMedium,
+    /// A large studio.
+    StudioLarge,
+    /// A concert hall.
+    Hall,
+    /// A long echo with reverb.
+    SpaceEcho,
+    /// A long repeating echo.
+    Echo,
+    /// A long single echo.
+    Delay,
+    /// A short echo.
+    HalfEcho,
+}
+
+impl ReverbMode {
+    /// Gets the size in bytes of the work area the preset needs at the end of
+    /// SPU RAM.
+    pub const fn work_area_size(self) -> u32 {
+        match self {
+            ReverbMode::Off => 0x10,
+            ReverbMode::Room => 0x26C0,
+            ReverbMode::StudioSmall => 0x1F40,
+            ReverbMode::StudioMedium => 0x4840,
+            ReverbMode::StudioLarge => 0x6FE0,
+            ReverbMode::Hall => 0xADE0,
+            ReverbMode::SpaceEcho => 0xF6C0,
+            ReverbMode::Echo => 0x1_8040,
+            ReverbMode::Delay => 0x1_8040,
+            ReverbMode::HalfEcho => 0x3C00,
+        }
+    }
+}
+
+macro_rules! reverb_config {
+    ($($(#[$meta:meta])* $field:ident: $reg:ident,)*) => {
+        /// A reverb unit configuration.
+        ///
+        /// The register fields hold raw register values. Addresses are in
+        /// 8-byte units relative to the start of the work area and volumes are
+        /// signed.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub struct ReverbConfig {
+            /// The size of the work area at the end of SPU RAM in bytes.
+            pub work_area_size: u32,
+            $($(#[$meta])* pub $field: u16,)*
+        }
+
+        impl ReverbConfig {
+            /// Creates a config from the registers in address order.
+            pub const fn from_registers(work_area_size: u32, registers: [u16; 32]) -> Self {
+                let [$($field),*] = registers;
+                ReverbConfig {
+                    work_area_size,
+                    $($field),*
+                }
+            }
+
+            fn store_registers(&self) {
+                $($reg::skip_load().assign(self.$field).store();)*
+            }
+        }
+    };
+}
+
+reverb_config! {
+    /// All-pass filter 1 offset (dAPF1)
+    apf_offset1: APFOffset1,
+    /// All-pass filter 2 offset (dAPF2)
+    apf_offset2: APFOffset2,
+    /// Reflection volume 1 (vIIR)
+    reflection_volume1: ReflectionVolume1,
+    /// Comb volume 1 (vCOMB1)
+    comb_volume1: CombVolume1,
+    /// Comb volume 2 (vCOMB2)
+    comb_volume2: CombVolume2,
+    /// Comb volume 3 (vCOMB3)
+    comb_volume3: CombVolume3,
+    /// Comb volume 4 (vCOMB4)
+    comb_volume4: CombVolume4,
+    /// Reflection volume 2 (vWALL)
+    reflection_volume2: ReflectionVolume2,
+    /// All-pass filter 1 volume (vAPF1)
+    apf_volume1: APFVolume1,
+    /// All-pass filter 2 volume (vAPF2)
+    apf_volume2: APFVolume2,
+    /// Same side reflection 1 address left (mLSAME)
+    same_reflection1_left: SameReflection1Left,
+    /// Same side reflection 1 address right (mRSAME)
+    same_reflection1_right: SameReflection1Right,
+    /// Comb 1 address left (mLCOMB1)
+    comb1_left: Comb1Left,
+    /// Comb 1 address right (mRCOMB1)
+    comb1_right: Comb1Right,
+    /// Comb 2 address left (mLCOMB2)
+    comb2_left: Comb2Left,
+    /// Comb 2 address right (mRCOMB2)
+    comb2_right: Comb2Right,
+    /// Same side reflection 2 address left (dLSAME)
+    same_reflection2_left: SameReflection2Left,
+    /// Same side reflection 2 address right (dRSAME)
+    same_reflection2_right: SameReflection2Right,
+    /// Different side reflection 1 address left (mLDIFF)
+    diff_reflection1_left: DiffReflection1Left,
+    /// Different side reflection 1 address right (mRDIFF)
+    diff_reflection1_right: DiffReflection1Right,
+    /// Comb 3 address left (mLCOMB3)
+    comb3_left: Comb3Left,
+    /// Comb 3 address right (mRCOMB3)
+    comb3_right: Comb3Right,
+    /// Comb 4 address left (mLCOMB4)
+    comb4_left: Comb4Left,
+    /// Comb 4 address right (mRCOMB4)
+    comb4_right: Comb4Right,
+    /// Different side reflection 2 address left (dLDIFF)
+    diff_reflection2_left: DiffReflection2Left,
+    /// Different side reflection 2 address right (dRDIFF)
+    diff_reflection2_right: DiffReflection2Right,
+    /// All-pass filter 1 address left (mLAPF1)
+    apf1_left: APF1Left,
+    /// All-pass filter 1 address right (mRAPF1)
+    apf1_right: APF1Right,
+    /// All-pass filter 2 address left (mLAPF2)
+    apf2_left: APF2Left,
+    /// All-pass filter 2 address right (mRAPF2)
+    apf2_right: APF2Right,
+    /// Input volume left (vLIN)
+    input_volume_left: InputVolumeLeft,
+    /// Input volume right (vRIN)
+    input_volume_right: InputVolumeRight,
+}
+
+#[rustfmt::skip]
+const OFF: [u16; 32] = [
+    0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,
+    0x0000, 0x0000, 0x0001, 0x0001, 0x0001, 0x0001, 0x0001, 0x0001,
+    0x0000, 0x0000, 0x0001, 0x0001, 0x0001, 0x0001, 0x0001, 0x0001,
+    0x0000, 0x0000, 0x0001, 0x0001, 0x0001, 0x0001, 0x0000, 0x0000,
+];
+#[rustfmt::skip]
+const ROOM: [u16; 32] = [
+    0x007D, 0x005B, 0x6D80, 0x54B8, 0xBED0, 0x0000, 0x0000, 0xBA80,
+    0x5800, 0x5300, 0x04D6, 0x0333, 0x03F0, 0x0227, 0x0374, 0x01EF,
+    0x0334, 0x01B5, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,
+    0x0000, 0x0000, 0x01B4, 0x0136, 0x00B8, 0x005C, 0x8000, 0x8000,
+];
+#[rustfmt::skip]
+const STUDIO_SMALL: [u16; 32] = [
+    0x0033, 0x0025, 0x70F0, 0x4FA8, 0xBCE0, 0x4410, 0xC0F0, 0x9C00,
+    0x5280, 0x4EC0, 0x03E4, 0x031B, 0x03A4, 0x02AF, 0x0372, 0x0266,
+    0x031C, 0x025D, 0x025C, 0x018E, 0x022F, 0x0135, 0x01D2, 0x00B7,
+    0x018F, 0x00B5, 0x00B4, 0x0080, 0x004C, 0x0026, 0x8000, 0x8000,
+];
+#[rustfmt::skip]
+const STUDIO_MEDIUM: [u16; 32] = [
+    0x00B1, 0x007F, 0x70F0, 0x4FA8, 0xBCE0, 0x4510, 0xBEF0, 0xB4C0,
+    0x5280, 0x4EC0, 0x0904, 0x076B, 0x0824, 0x065F, 0x07A2, 0x0616,
+    0x076C, 0x05ED, 0x05EC, 0x042E, 0x050F, 0x0305, 0x0462, 0x02B7,
+    0x042F, 0x0265, 0x0264, 0x01B2, 0x0100, 0x0080, 0x8000, 0x8000,
+];
+#[rustfmt::skip]
+const STUDIO_LARGE: [u16; 32] = [
+    0x00E3, 0x00A9, 0x6F60, 0x4FA8, 0xBCE0, 0x4510, 0xBEF0, 0xA680,
+    0x5680, 0x52C0, 0x0DFB, 0x0B58, 0x0D09, 0x0A3C, 0x0BD9, 0x0973,
+    0x0B59, 0x08DA, 0x08D9, 0x05E9, 0x07EC, 0x04B0, 0x06EF, 0x03D2,
+    0x05EA, 0x031D, 0x031C, 0x0238, 0x0154, 0x00AA, 0x8000, 0x8000,
+];
+#[rustfmt::skip]
+const HALL: [u16; 32] = [
+    0x01A5, 0x0139, 0x6000, 0x5000, 0x4C00, 0xB800, 0xBC00, 0xC000,
+    0x6000, 0x5C00, 0x15BA, 0x11BB, 0x14C2, 0x10BD, 0x11BC, 0x0DC1,
+    0x11C0, 0x0DC3, 0x0DC0, 0x09C1, 0x0BC4, 0x07C1, 0x0A00, 0x06CD,
+    0x09C2, 0x05C1, 0x05C0, 0x041A, 0x0274, 0x013A, 0x8000, 0x8000,
+];
+#[rustfmt::skip]
+const SPACE_ECHO: [u16; 32] = [
+    0x033D, 0x0231, 0x7E00, 0x5000, 0xB400, 0xB000, 0x4C00, 0xB000,
+    0x6000, 0x5400, 0x1ED6, 0x1A31, 0x1D14, 0x183B, 0x1BC2, 0x16B2,
+    0x1A32, 0x15EF, 0x15EE, 0x1055, 0x1334, 0x0F2D, 0x11F6, 0x0C5D,
+    0x1056, 0x0AE1, 0x0AE0, 0x07A2, 0x0464, 0x0232, 0x8000, 0x8000,
+];
+#[rustfmt::skip]
+const ECHO: [u16; 32] = [
+    0x0001, 0x0001, 0x7FFF, 0x7FFF, 0x0000, 0x0000, 0x0000, 0x8100,
+    0x0000, 0x0000, 0x1FFF, 0x0FFF, 0x1005, 0x0005, 0x0000, 0x0000,
+    0x1005, 0x0005, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,
+    0x0000, 0x0000, 0x1004, 0x1002, 0x0004, 0x0002, 0x8000, 0x8000,
+];
+#[rustfmt::skip]
+const DELAY: [u16; 32] = [
+    0x0001, 0x0001, 0x7FFF, 0x7FFF, 0x0000, 0x0000, 0x0000, 0x0000,
+    0x0000, 0x0000, 0x1FFF, 0x0FFF, 0x1005, 0x0005, 0x0000, 0x0000,
+    0x1005, 0x0005, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,
+    0x0000, 0x0000, 0x1004, 0x1002, 0x0004, 0x0002, 0x8000, 0x8000,
+];
+#[rustfmt::skip]
+const HALF_ECHO: [u16; 32] = [
+    0x0017, 0x0013, 0x70F0, 0x4FA8, 0xBCE0, 0x4510, 0xBEF0, 0x8500,
+    0x5F80, 0x54C0, 0x0371, 0x02AF, 0x02E5, 0x01DF, 0x02B0, 0x01D7,
+    0x0358, 0x026A, 0x01D6, 0x011E, 0x012D, 0x00B1, 0x011F, 0x0059,
+    0x01A0, 0x00E3, 0x0058, 0x0040, 0x0028, 0x0014, 0x8000, 0x8000,
+];
+
+/// Zeros used to clear the work area.
+static ZEROS: [u32; 256] = [0; 256];
+
+impl ReverbConfig {
+    /// Creates the config for a reverb preset.
+    pub const fn preset(mode: ReverbMode) -> Self {
+        let registers = match mode {
+            ReverbMode::Off => OFF,
+            ReverbMode::Room => ROOM,
+            ReverbMode::StudioSmall => STUDIO_SMALL,
+            ReverbMode::StudioMedium => STUDIO_MEDIUM,
+            ReverbMode::StudioLarge => STUDIO_LARGE,
+            ReverbMode::Hall => HALL,
+            ReverbMode::SpaceEcho => SPACE_ECHO,
+            ReverbMode::Echo => ECHO,
+            ReverbMode::Delay => DELAY,
+            ReverbMode::HalfEcho => HALF_ECHO,
+        };
+        Self::from_registers(mode.work_area_size(), registers)
+    }
+
+    /// Applies the config to the reverb unit.
+    ///
+    /// This reserves the work area in `ram`, programs the reverb registers and
+    /// clears the work area to avoid playing stale data. The reverb unit is
+    /// disabled while this runs and re-enabled afterwards unless the config
+    /// is for [`ReverbMode::Off`]. Returns [`Error::OutOfMemory`] if the work
+    /// area would overlap a sample.
+    pub fn apply(&self, ram: &mut RamAllocator, spu_dma: &mut dma::SPU) -> Result<(), Error> {
+        ram.set_reverb_work_area(self.work_area_size)?;
+        Control::new().enable_reverb(false).store();
+
+        self.store_registers();
+        let start = ram.reverb_work_area();
+        WorkArea::skip_load().set_address(start).store();
+
+        let mut address = start;
+        while address < RAM_SIZE as u32 {
+            let bytes = (RAM_SIZE as u32 - address).min(ZEROS.len() as u32 * 4);
+            transfer::write_dma(address, &ZEROS[..bytes as usize / 4], spu_dma)?;
+            address += bytes;
+        }
+
+        if *self != Self::preset(ReverbMode::Off) {
+            Control::new().enable_reverb(true).store();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ReverbConfig, ReverbMode};
+
+    #[test_case]
+    fn presets_fit_work_area() {
+        let modes = [
+            ReverbMode::Off,
+            ReverbMode::Room,
+            ReverbMode::StudioSmall,
+            ReverbMode::StudioMedium,
+            ReverbMode::StudioLarge,
+            ReverbMode::Hall,
+            ReverbMode::SpaceEcho,
+            ReverbMode::Echo,
+            ReverbMode::Delay,
+            ReverbMode::HalfEcho,
+        ];
+        for mode in modes {
+            let config = ReverbConfig::preset(mode);
+            let addresses = [
+                config.same_reflection1_left,
+                config.same_reflection1_right,
+                config.comb1_left,
+                config.comb1_right,
+                config.comb2_left,
+                config.comb2_right,
+                config.same_reflection2_left,
+                config.same_reflection2_right,
+                config.diff_reflection1_left,
+                config.diff_reflection1_right,
+                config.comb3_left,
+                config.comb3_right,
+                config.comb4_left,
+                config.comb4_right,
+                config.diff_reflection2_left,
+                config.diff_reflection2_right,
+                config.apf1_left,
+                config.apf1_right,
+                config.apf2_left,
+                config.apf2_right,
+            ];
+            for address in addresses {
+                assert!((address as u32) * 8 < config.work_area_size);
+            }
+        }
+    }
+}
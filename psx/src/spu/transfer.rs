@@ -0,0 +1,98 @@
+use crate::dma;
+use crate::hw::spu::{Control, RAMAddress, Status, TransferAddress, TransferControl, TransferFifo,
+                     TransferMode};
+use crate::hw::Register;
+use crate::spu::RAM_SIZE;
+
+/// The size of the SPU transfer FIFO in bytes.
+const FIFO_SIZE: usize = 64;
+/// Possible DMA block sizes in words, largest first.
+const BLOCK_SIZES: [usize; 5] = [16, 8, 4, 2, 1];
+
+/// Gets the largest DMA block size in words which evenly divides `len`.
+fn block_words(len: usize) -> usize {
+    BLOCK_SIZES
+        .into_iter()
+        .find(|&words| len % words == 0)
+        .unwrap_or(1)
+}
+
+fn set_transfer_mode(mode: TransferMode) {
+    Control::new().set_transfer_mode(mode).store();
+    Status::new().wait_mode(mode);
+}
+
+fn start_transfer(address: u32, len: usize) {
+    assert!(address % 8 == 0, "SPU RAM address must be 8-byte aligned");
+    assert!(
+        address as usize + len <= RAM_SIZE,
+        "SPU RAM transfer out of bounds"
+    );
+    set_transfer_mode(TransferMode::Stop);
+    TransferControl::skip_load().set_normal().store();
+    TransferAddress::skip_load().set_address(address).store();
+}
+
+/// Writes `data` to SPU RAM at `address` in bytes through the SPU DMA
+/// channel.
+///
+/// This blocks until the transfer completes. Panics if `address` isn't 8-byte
+/// aligned or the data doesn't fit in SPU RAM.
+pub fn write_dma(address: u32, data: &[u32], spu_dma: &mut dma::SPU) -> Result<(), dma::Error> {
+    start_transfer(address, data.len() * 4);
+    set_transfer_mode(TransferMode::DMAWrite);
+    let res = spu_dma.send_blocks_and(data, data.len() / block_words(data.len()), || ());
+    Status::new().wait_transfer();
+    set_transfer_mode(TransferMode::Stop);
+    res
+}
+
+/// Reads SPU RAM at `address` in bytes into `data` through the SPU DMA
+/// channel.
+///
+/// This blocks until the transfer completes. Panics if `address` isn't 8-byte
+/// aligned or the data doesn't fit in SPU RAM.
+pub fn read_dma(address: u32, data: &mut [u32], spu_dma: &mut dma::SPU) -> Result<(), dma::Error> {
+    start_transfer(address, data.len() * 4);
+    set_transfer_mode(TransferMode::DMARead);
+    let blocks = data.len() / block_words(data.len());
+    let res = spu_dma.receive_blocks_and(data, blocks, || ());
+    Status::new().wait_transfer();
+    set_transfer_mode(TransferMode::Stop);
+    res
+}
+
+/// Writes `data` to SPU RAM at `address` in bytes through the transfer FIFO.
+///
+/// This blocks until the transfer completes. Odd-sized data is padded with a
+/// null byte. Panics if `address` isn't 8-byte aligned or the data doesn't fit
+/// in SPU RAM.
+pub fn write_manual(address: u32, data: &[u8]) {
+    start_transfer(address, data.len());
+    let mut fifo = TransferFifo::skip_load();
+    for chunk in data.chunks(FIFO_SIZE) {
+        for pair in chunk.chunks(2) {
+            let hi = pair.get(1).copied().unwrap_or(0);
+            fifo.assign(u16::from_le_bytes([pair[0], hi])).store();
+        }
+        set_transfer_mode(TransferMode::ManualWrite);
+        Status::new().wait_transfer();
+        set_transfer_mode(TransferMode::Stop);
+    }
+}
+
+/// Writes `data` to SPU RAM at `address` in bytes.
+///
+/// This uses the SPU DMA channel if `data` is word-aligned and falls back to
+/// the transfer FIFO otherwise. Panics if `address` isn't 8-byte aligned or
+/// the data doesn't fit in SPU RAM.
+pub fn write(address: u32, data: &[u8], spu_dma: &mut dma::SPU) {
+    // SAFETY: Any bit pattern is a valid u32
+    let sent = match unsafe { data.align_to::<u32>() } {
+        (&[], words, &[]) => write_dma(address, words, spu_dma).is_ok(),
+        _ => false,
+    };
+    if !sent {
+        write_manual(address, data);
+    }
+}
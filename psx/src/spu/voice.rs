@@ -0,0 +1,281 @@
+use crate::hw::spu::{CurrentVolume, KeyOff, KeyOn, Pitch, RAMAddress, RepeatAddress, ReverbEnable,
+                     StartAddress, VoiceMask, VoiceStatus, Volume, VolumeLeft, VolumeRight, ADSR,
+                     ALL_VOICES, NUM_VOICES};
+use crate::hw::Register;
+use crate::spu::{Envelope, Sample};
+
+/// The highest sample rate a voice can play back at.
+const MAX_SAMPLE_RATE: u32 = 4 * 44_100;
+
+/// The frequency ratio between each semitone in an octave as 20.12 fixed-point.
+const SEMITONES: [u32; 12] = [
+    4096, 4340, 4598, 4871, 5161, 5468, 5793, 6137, 6502, 6889, 7298, 7732,
+];
+
+/// Gets the sample rate needed to play a sample recorded at `base_note` and
+/// `sample_rate` Hz as the MIDI note `note`.
+pub const fn note_sample_rate(note: u8, base_note: u8, sample_rate: u32) -> u32 {
+    let diff = note as i32 - base_note as i32;
+    let octave = diff.div_euclid(12);
+    let semitone = diff.rem_euclid(12) as usize;
+    let sample_rate = if sample_rate > MAX_SAMPLE_RATE {
+        MAX_SAMPLE_RATE
+    } else {
+        sample_rate
+    };
+    let hz = (sample_rate * SEMITONES[semitone]) >> 12;
+    if octave >= 0 {
+        if octave >= 8 || (hz << octave) > MAX_SAMPLE_RATE {
+            MAX_SAMPLE_RATE
+        } else {
+            hz << octave
+        }
+    } else if octave <= -32 {
+        0
+    } else {
+        hz >> -octave
+    }
+}
+
+/// A handle to one of the SPU voices.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Voice {
+    index: u8,
+}
+
+impl Voice {
+    /// Creates a handle for the voice `index`.
+    ///
+    /// Panics if `index` is not less than [`NUM_VOICES`]. Note that this
+    /// doesn't coordinate with [`VoiceAllocator`].
+    pub fn new(index: usize) -> Self {
+        assert!(index < NUM_VOICES, "Invalid SPU voice");
+        Voice { index: index as u8 }
+    }
+
+    /// Gets the voice's index.
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    /// Starts the voice's attack, decay and sustain phases.
+    pub fn key_on(&mut self) -> &mut Self {
+        KeyOn::skip_load().add_voice(self.index()).store();
+        self
+    }
+
+    /// Starts the voice's release phase.
+    pub fn key_off(&mut self) -> &mut Self {
+        KeyOff::skip_load().add_voice(self.index()).store();
+        self
+    }
+
+    /// Sets the voice's left and right volumes.
+    pub fn set_volume(&mut self, left: i16, right: i16) -> &mut Self {
+        VolumeLeft::skip_load(self.index()).set_fixed(left).store();
+        VolumeRight::skip_load(self.index())
+            .set_fixed(right)
+            .store();
+        self
+    }
+
+    /// Sets the voice's `volume` and stereo `pan` from `-127` (left) to `127`
+    /// (right).
+    ///
+    /// Panning attenuates the opposite channel so a `pan` of `0` plays both
+    /// channels at `volume`.
+    pub fn set_pan(&mut self, volume: i16, pan: i8) -> &mut Self {
+        let pan = pan.max(-127) as i32;
+        let left = volume as i32 * (127 - pan.max(0)) / 127;
+        let right = volume as i32 * (127 + pan.min(0)) / 127;
+        self.set_volume(left as i16, right as i16)
+    }
+
+    /// Sets the sample rate in Hz to play the voice's ADPCM data at.
+    pub fn set_sample_rate(&mut self, hz: u32) -> &mut Self {
+        Pitch::skip_load(self.index())
+            .set_sample_rate(hz.min(MAX_SAMPLE_RATE))
+            .store();
+        self
+    }
+
+    /// Sets the voice's pitch to play the MIDI note `note` using ADPCM data
+    /// recorded at `base_note` and `sample_rate` Hz.
+    pub fn set_note(&mut self, note: u8, base_note: u8, sample_rate: u32) -> &mut Self {
+        self.set_sample_rate(note_sample_rate(note, base_note, sample_rate))
+    }
+
+    /// Sets the voice to play `sample` at its sample rate on key on.
+    pub fn set_sample(&mut self, sample: &Sample) -> &mut Self {
+        self.set_start_address(sample.address())
+            .set_sample_rate(sample.sample_rate())
+    }
+
+    /// Sets the voice's attack, decay, sustain and release envelope.
+    pub fn set_envelope(&mut self, envelope: Envelope) -> &mut Self {
+        ADSR::skip_load(self.index()).set_envelope(envelope).store();
+        self
+    }
+
+    /// Sets the SPU RAM address in bytes where the voice starts playing on
+    /// key on.
+    pub fn set_start_address(&mut self, address: u32) -> &mut Self {
+        StartAddress::skip_load(self.index())
+            .set_address(address)
+            .store();
+        self
+    }
+
+    /// Sets the SPU RAM address in bytes the voice jumps to when it reaches
+    /// an ADPCM block with the loop end flag.
+    ///
+    /// This is usually overwritten by the ADPCM data's loop start flag.
+    pub fn set_repeat_address(&mut self, address: u32) -> &mut Self {
+        RepeatAddress::skip_load(self.index())
+            .set_address(address)
+            .store();
+        self
+    }
+
+    /// Enables or disables reverb for the voice.
+    pub fn enable_reverb(&mut self, enable: bool) -> &mut Self {
+        let mut reverb = ReverbEnable::new();
+        if enable {
+            reverb.add_voice(self.index());
+        } else {
+            reverb.remove_voice(self.index());
+        }
+        reverb.store();
+        self
+    }
+
+    /// Gets the voice's current envelope volume.
+    pub fn envelope_volume(&self) -> i16 {
+        CurrentVolume::new(self.index()).to_bits()
+    }
+
+    /// Checks if the voice reached an ADPCM block with the loop end flag
+    /// since it was last keyed on.
+    pub fn ended(&self) -> bool {
+        VoiceStatus::new().contains(self.index())
+    }
+}
+
+/// Allocates SPU voices, stealing voices in use when none are available.
+///
+/// Voices are picked in the following order:
+/// 1. Voices which are silent, either because they were released or because
+///    they reached the end of their ADPCM data.
+/// 2. Released voices which are still in their release phase, oldest first.
+/// 3. Voices in use with a priority less than or equal to the requested
+///    priority, lowest priority first then oldest first.
+///
+/// Stolen voices aren't keyed off so stale [`Voice`] handles may still
+/// modify them.
+#[derive(Debug)]
+pub struct VoiceAllocator {
+    voices: u32,
+    allocated: u32,
+    priority: [u8; NUM_VOICES],
+    age: [u32; NUM_VOICES],
+    clock: u32,
+}
+
+impl Default for VoiceAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VoiceAllocator {
+    /// Creates an allocator managing all voices.
+    pub const fn new() -> Self {
+        Self::with_voices(ALL_VOICES)
+    }
+
+    /// Creates an allocator managing the voices set in the `voices` bitmask.
+    ///
+    /// This allows reserving voices for other uses, e.g. music.
+    pub const fn with_voices(voices: u32) -> Self {
+        VoiceAllocator {
+            voices: voices & ALL_VOICES,
+            allocated: 0,
+            priority: [0; NUM_VOICES],
+            age: [0; NUM_VOICES],
+            clock: 0,
+        }
+    }
+
+    /// Allocates a voice with the given `priority`, returning `None` if all
+    /// voices are in use by higher priority sounds.
+    pub fn alloc(&mut self, priority: u8) -> Option<Voice> {
+        let ended = VoiceStatus::new();
+        let mut best = None;
+        for voice in 0..NUM_VOICES {
+            if self.voices & (1 << voice) == 0 {
+                continue
+            }
+            let allocated = self.allocated & (1 << voice) != 0;
+            let silent = CurrentVolume::new(voice).to_bits() == 0;
+            // Voices which were just keyed on are also silent during their
+            // attack phase so only consider the volume for voices which ended
+            let key = if silent && (!allocated || ended.contains(voice)) {
+                (0, 0)
+            } else if !allocated {
+                (1, 0)
+            } else if self.priority[voice] <= priority {
+                (2, self.priority[voice])
+            } else {
+                continue
+            };
+            let key = (key.0, key.1, self.age[voice]);
+            if best.map_or(true, |(_, best_key)| key < best_key) {
+                best = Some((voice, key));
+            }
+        }
+        best.map(|(voice, _)| {
+            self.clock = self.clock.wrapping_add(1);
+            self.allocated |= 1 << voice;
+            self.priority[voice] = priority;
+            self.age[voice] = self.clock;
+            Voice::new(voice)
+        })
+    }
+
+    /// Keys off the voice and returns it to the allocator.
+    ///
+    /// The voice may still be stolen during its release phase.
+    pub fn release(&mut self, mut voice: Voice) {
+        voice.key_off();
+        self.allocated &= !(1 << voice.index());
+    }
+
+    /// Checks if the voice is currently allocated.
+    pub fn is_allocated(&self, voice: usize) -> bool {
+        self.allocated & (1 << voice) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::note_sample_rate;
+    use crate::hw::spu::Pitch;
+
+    #[test_case]
+    fn notes() {
+        assert!(note_sample_rate(60, 60, 22_050) == 22_050);
+        assert!(note_sample_rate(72, 60, 22_050) == 44_100);
+        assert!(note_sample_rate(48, 60, 22_050) == 11_025);
+        assert!(note_sample_rate(67, 60, 44_100) == 66_074);
+        assert!(note_sample_rate(127, 0, 44_100) == 4 * 44_100);
+        assert!(note_sample_rate(0, 127, 44_100) == 28);
+    }
+
+    #[test_case]
+    fn pitches() {
+        let mut pitch = Pitch::skip_load(0);
+        assert!(pitch.set_sample_rate(44_100).to_bits() == 0x1000);
+        assert!(pitch.set_sample_rate(1 << 20).to_bits() == 0x3FFF);
+        assert!(pitch.set_sample_rate(u32::MAX).to_bits() == 0x3FFF);
+    }
+}
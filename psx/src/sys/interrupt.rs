@@ -0,0 +1,53 @@
+//! BIOS interrupt handler chains
+//!
+//! The BIOS calls each [`Handler`] in its chains on every interrupt. Handlers
+//! must check and acknowledge the interrupts they service themselves.
+use crate::sys::kernel;
+
+/// The chain priority for handlers which don't need to run before the BIOS's
+/// timer and vblank handlers.
+pub const DEFAULT_PRIORITY: u32 = 2;
+
+/// An entry in a BIOS interrupt handler chain.
+#[repr(C)]
+#[derive(Debug)]
+pub struct Handler {
+    next: *mut Handler,
+    second: Option<extern "C" fn(u32)>,
+    first: extern "C" fn() -> u32,
+    reserved: u32,
+}
+
+impl Handler {
+    /// Creates a chain entry which calls `func` on every interrupt.
+    ///
+    /// `func` should return zero.
+    pub const fn new(func: extern "C" fn() -> u32) -> Self {
+        Handler {
+            next: core::ptr::null_mut(),
+            second: None,
+            first: func,
+            reserved: 0,
+        }
+    }
+}
+
+/// Adds `handler` to the chain with the given priority from 0 to 3 where 0
+/// runs first.
+///
+/// # Safety
+///
+/// `handler` must not already be in a chain. This must be called in a
+/// critical section.
+pub unsafe fn enqueue(priority: u32, handler: &'static mut Handler) {
+    unsafe { kernel::psx_sys_enq_int_rp(priority, (handler as *mut Handler).cast()) }
+}
+
+/// Removes `handler` from the chain with the given priority.
+///
+/// # Safety
+///
+/// `handler` must be in the chain. This must be called in a critical section.
+pub unsafe fn dequeue(priority: u32, handler: &'static mut Handler) {
+    unsafe { kernel::psx_sys_deq_int_rp(priority, (handler as *mut Handler).cast()) }
+}
@@ -146,6 +146,10 @@ extern "C" {
     pub fn psx_get_last_file_error(fd: i8) -> u32;
     /// Calls BIOS function [B(5Bh)](http://problemkaputt.de/psx-spx.htm#biosfunctionsummary)
     pub fn psx_change_clear_pad(int: u32);
+    /// Calls BIOS function [C(02h)](http://problemkaputt.de/psx-spx.htm#biosfunctionsummary)
+    pub fn psx_sys_enq_int_rp(priority: u32, struc: *mut u32);
+    /// Calls BIOS function [C(03h)](http://problemkaputt.de/psx-spx.htm#biosfunctionsummary)
+    pub fn psx_sys_deq_int_rp(priority: u32, struc: *mut u32);
     /// Calls BIOS function [C(0Ah)](http://problemkaputt.de/psx-spx.htm#biosfunctionsummary)
     pub fn psx_change_clear_rcnt(t: u32, flag: bool) -> bool;
     /// Calls BIOS function [C(13h)](http://problemkaputt.de/psx-spx.htm#biosfunctionsummary)
@@ -437,6 +441,14 @@ pub const GET_LAST_FILE_ERROR_TY: u8 = 0xB0;
 pub const CHANGE_CLEAR_PAD_NUM: u8 = 0x5B;
 /// The BIOS function type for change_clear_pad
 pub const CHANGE_CLEAR_PAD_TY: u8 = 0xB0;
+/// The BIOS function number for sys_enq_int_rp
+pub const SYS_ENQ_INT_RP_NUM: u8 = 0x02;
+/// The BIOS function type for sys_enq_int_rp
+pub const SYS_ENQ_INT_RP_TY: u8 = 0xC0;
+/// The BIOS function number for sys_deq_int_rp
+pub const SYS_DEQ_INT_RP_NUM: u8 = 0x03;
+/// The BIOS function type for sys_deq_int_rp
+pub const SYS_DEQ_INT_RP_TY: u8 = 0xC0;
 /// The BIOS function number for change_clear_rcnt
 pub const CHANGE_CLEAR_RCNT_NUM: u8 = 0x0A;
 /// The BIOS function type for change_clear_rcnt
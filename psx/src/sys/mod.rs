@@ -8,6 +8,7 @@ use core::ffi::CStr;
 pub mod fs;
 pub mod gamepad;
 pub mod heap;
+pub mod interrupt;
 pub mod kernel;
 pub mod rng;
 pub mod tty;
@@ -491,6 +491,20 @@ psx_change_clear_pad:
     jr $8
     li $9, 0x5B
 
+.section .text.bios.psx_sys_enq_int_rp
+.globl psx_sys_enq_int_rp
+psx_sys_enq_int_rp:
+    la $8, 0xC0
+    jr $8
+    li $9, 0x02
+
+.section .text.bios.psx_sys_deq_int_rp
+.globl psx_sys_deq_int_rp
+psx_sys_deq_int_rp:
+    la $8, 0xC0
+    jr $8
+    li $9, 0x03
+
 .section .text.bios.psx_change_clear_rcnt
 .globl psx_change_clear_rcnt
 psx_change_clear_rcnt:
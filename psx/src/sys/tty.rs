@@ -1,6 +1,7 @@
 #![doc(hidden)]
 /// This module is hidden because only the macros defined in this module are
 /// explicitly public-facing and they're exported from the crate root.
+use crate::sio;
 use crate::std::AsCStr;
 use crate::sys::kernel;
 use core::fmt;
@@ -84,8 +85,9 @@ macro_rules! printf_impl {
 
 /// Prints as ASCII string containing Rust-style escape codes to stdout.
 ///
-/// This may call into the BIOS multiple times. The format string must be a
-/// literal.
+/// This may call into the BIOS multiple times or write to the serial port if
+/// stdout was redirected with [`sio::set_stdout`][crate::sio::set_stdout].
+/// The format string must be a literal.
 #[macro_export]
 macro_rules! print {
     ($($args:tt)*) => {
@@ -99,26 +101,25 @@ macro_rules! print {
 /// Prints as ASCII string containing Rust-style escape codes to stdout with a
 /// newline.
 ///
-/// This may call into the BIOS multiple times. The format string must be a
-/// literal.
+/// This may call into the BIOS multiple times or write to the serial port if
+/// stdout was redirected with [`sio::set_stdout`][crate::sio::set_stdout].
+/// The format string must be a literal.
 #[macro_export]
 macro_rules! println {
     ($($args:tt)*) => {
         {
             use $crate::sys::tty::TTY;
             <TTY as core::fmt::Write>::write_fmt(&mut TTY, format_args!($($args)*)).ok();
-            // SAFETY: The string is null-terminated. The attribute avoids warnings for nested
-            // unsafe blocks
-            #[allow(unused_unsafe)]
-            unsafe {
-                $crate::sys::kernel::psx_printf(b"\n\0".as_ptr() as *const i8);
-            }
+            <TTY as core::fmt::Write>::write_str(&mut TTY, "\n").ok();
         }
     };
 }
 
 impl fmt::Write for TTY {
     fn write_str(&mut self, msg: &str) -> fmt::Result {
+        if sio::write_stdout(msg.as_bytes()) {
+            return Ok(())
+        }
         msg.as_cstr(|cstr|
             // SAFETY: The format string and string argument are both null-terminated.
             unsafe {
@@ -0,0 +1,321 @@
+//! Monotonic time measurement
+//!
+//! Time is measured with root counter 2 running at the system clock divided
+//! by 8. Its overflows are counted by an interrupt handler registered with
+//! the BIOS so [`init`] must be called before measuring time. Overflows are
+//! also counted when reading the time so measurements stay accurate in
+//! critical sections as long as the time is read at least every 15 ms.
+use crate::gpu::VideoMode;
+use crate::hw::cop0::IntSrc;
+use crate::hw::irq::IRQ;
+use crate::hw::timer::timer2::{Current, Mode};
+use crate::hw::timer::{CounterMode, CounterValue, IRQPulse, IRQRepeat, Name, Source};
+use crate::hw::{cop0, irq, Register};
+use crate::timer::{open_rcnt_event, rcnt_owner, Owner};
+use core::ops::{Add, AddAssign, Div, Mul, Sub, SubAssign};
+
+/// The CPU clock rate in Hz.
+pub const SYSCLOCK_HZ: u32 = 33_868_800;
+
+/// The number of CPU cycles per root counter 2 tick.
+const PRESCALER: u64 = 8;
+/// The number of bits in the root counters.
+const COUNTER_BITS: u32 = 16;
+/// The number of CPU cycles per NTSC frame.
+const NTSC_FRAME_CYCLES: u64 = 566_204;
+/// The number of CPU cycles per PAL frame.
+const PAL_FRAME_CYCLES: u64 = 680_823;
+// SYSCLOCK_HZ / 1_000_000 = 21168 / 625
+const CYCLES_PER_625_US: u64 = 21_168;
+
+static mut OVERFLOWS: u32 = 0;
+
+/// A span of time measured in CPU cycles.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Duration(u64);
+
+impl Duration {
+    /// A duration of zero.
+    pub const ZERO: Duration = Duration(0);
+
+    /// Creates a duration from a number of CPU cycles.
+    pub const fn from_cycles(cycles: u64) -> Self {
+        Duration(cycles)
+    }
+
+    /// Creates a duration from a number of microseconds rounded up to the
+    /// next CPU cycle.
+    pub const fn from_micros(micros: u64) -> Self {
+        Duration((micros * CYCLES_PER_625_US).div_ceil(625))
+    }
+
+    /// Creates a duration from a number of milliseconds.
+    pub const fn from_millis(millis: u64) -> Self {
+        Self::from_micros(millis * 1_000)
+    }
+
+    /// Creates a duration from a number of seconds.
+    pub const fn from_secs(secs: u64) -> Self {
+        Duration(secs * SYSCLOCK_HZ as u64)
+    }
+
+    /// Creates a duration from a number of frames in the given video mode.
+    pub const fn from_frames(frames: u64, video_mode: VideoMode) -> Self {
+        Duration(frames * frame_cycles(video_mode))
+    }
+
+    /// Gets the duration in CPU cycles.
+    pub const fn as_cycles(&self) -> u64 {
+        self.0
+    }
+
+    /// Gets the duration in whole microseconds.
+    pub const fn as_micros(&self) -> u64 {
+        self.0 * 625 / CYCLES_PER_625_US
+    }
+
+    /// Gets the duration in whole milliseconds.
+    pub const fn as_millis(&self) -> u64 {
+        self.as_micros() / 1_000
+    }
+
+    /// Gets the duration in whole seconds.
+    pub const fn as_secs(&self) -> u64 {
+        self.0 / SYSCLOCK_HZ as u64
+    }
+
+    /// Gets the duration in whole frames in the given video mode.
+    pub const fn as_frames(&self, video_mode: VideoMode) -> u64 {
+        self.0 / frame_cycles(video_mode)
+    }
+
+    /// Subtracts `other`, returning `None` if the result would be negative.
+    pub const fn checked_sub(self, other: Duration) -> Option<Duration> {
+        match self.0.checked_sub(other.0) {
+            Some(cycles) => Some(Duration(cycles)),
+            None => None,
+        }
+    }
+
+    /// Subtracts `other`, returning [`Duration::ZERO`] if the result would be
+    /// negative.
+    pub const fn saturating_sub(self, other: Duration) -> Duration {
+        Duration(self.0.saturating_sub(other.0))
+    }
+}
+
+const fn frame_cycles(video_mode: VideoMode) -> u64 {
+    match video_mode {
+        VideoMode::NTSC => NTSC_FRAME_CYCLES,
+        VideoMode::PAL => PAL_FRAME_CYCLES,
+    }
+}
+
+impl Add for Duration {
+    type Output = Duration;
+    fn add(self, other: Duration) -> Duration {
+        Duration(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Duration {
+    fn add_assign(&mut self, other: Duration) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Duration {
+    type Output = Duration;
+    fn sub(self, other: Duration) -> Duration {
+        Duration(self.0 - other.0)
+    }
+}
+
+impl SubAssign for Duration {
+    fn sub_assign(&mut self, other: Duration) {
+        self.0 -= other.0;
+    }
+}
+
+impl Mul<u32> for Duration {
+    type Output = Duration;
+    fn mul(self, n: u32) -> Duration {
+        Duration(self.0 * n as u64)
+    }
+}
+
+impl Div<u32> for Duration {
+    type Output = Duration;
+    fn div(self, n: u32) -> Duration {
+        Duration(self.0 / n as u64)
+    }
+}
+
+impl From<Duration> for core::time::Duration {
+    fn from(duration: Duration) -> Self {
+        let secs = duration.as_secs();
+        let cycles = duration.0 % SYSCLOCK_HZ as u64;
+        let nanos = cycles * 1_000_000_000 / SYSCLOCK_HZ as u64;
+        core::time::Duration::new(secs, nanos as u32)
+    }
+}
+
+/// A measurement of the monotonic clock.
+///
+/// Instants are measured in CPU cycles since [`init`] was called with a
+/// resolution of 8 cycles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(u64);
+
+impl Instant {
+    /// Gets the current time.
+    pub fn now() -> Self {
+        cop0::Status::new().critical_section(|_| {
+            let mut mode = Mode::skip_load();
+            // SAFETY: We're in a critical section so the interrupt handler
+            // can't modify `OVERFLOWS`
+            unsafe {
+                if mode.load().reached_overflow() {
+                    OVERFLOWS += 1;
+                }
+                let mut count = Current::new().get();
+                // If the counter overflowed after checking the mode register
+                // `count` might be from before or after the overflow
+                if mode.load().reached_overflow() {
+                    OVERFLOWS += 1;
+                    count = Current::new().get();
+                }
+                let ticks = (OVERFLOWS as u64) << COUNTER_BITS | count as u64;
+                Instant(ticks * PRESCALER)
+            }
+        })
+    }
+
+    /// Gets the time elapsed since this instant.
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+
+    /// Gets the time elapsed from `earlier` to this instant or
+    /// [`Duration::ZERO`] if `earlier` is later than this instant.
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        Duration(self.0.saturating_sub(earlier.0))
+    }
+
+    /// Gets the number of CPU cycles since [`init`] was called.
+    pub fn as_cycles(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+    fn add(self, duration: Duration) -> Instant {
+        Instant(self.0 + duration.0)
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, duration: Duration) {
+        self.0 += duration.0;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+    fn sub(self, duration: Duration) -> Instant {
+        Instant(self.0 - duration.0)
+    }
+}
+
+impl Sub for Instant {
+    type Output = Duration;
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+extern "C" fn on_overflow() {
+    // SAFETY: This runs in the BIOS interrupt handler so it can't be
+    // interrupted by `Instant::now`
+    unsafe {
+        if Mode::new().reached_overflow() {
+            OVERFLOWS += 1;
+        }
+    }
+}
+
+/// Starts the monotonic clock.
+///
+/// This configures root counter 2, registers its overflow interrupt handler
+/// with the BIOS and enables hardware interrupts. This stops
+/// [`Timer2`][crate::timer::Timer2] since the clock needs root counter 2.
+///
+/// The clock keeps running until the program exits, so calling this again
+/// does nothing. Programs which execute other executables shouldn't use the
+/// clock since those may reconfigure root counter 2.
+pub fn init() {
+    cop0::Status::new().critical_section(|_| {
+        if rcnt_owner(Name::Timer2) == Some(Owner::Clock) {
+            return
+        }
+        let mut mode = Mode::skip_load();
+        mode.set_sync(None)
+            .reset_on_target(false)
+            .irq_on_target(false)
+            .irq_on_overflow(true)
+            .set_irq_repeat(IRQRepeat::Repeat)
+            .set_irq_pulse(IRQPulse::Pulse)
+            .set_source(Source::SysClockDiv8)
+            .expect("Timer 2 supports sysclock / 8")
+            .store();
+        open_rcnt_event(Name::Timer2, Owner::Clock, on_overflow);
+        irq::Mask::new().enable_irq(IRQ::Timer2).store();
+    });
+    cop0::Status::new()
+        .unmask_interrupt(IntSrc::Hardware)
+        .enable_interrupts()
+        .store();
+}
+
+/// Blocks for at least `duration`.
+pub fn sleep(duration: Duration) {
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline {}
+}
+
+/// Blocks for at least `micros` microseconds.
+pub fn delay_us(micros: u32) {
+    sleep(Duration::from_micros(micros as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Duration, SYSCLOCK_HZ};
+    use crate::gpu::VideoMode;
+
+    #[test_case]
+    fn conversions() {
+        assert!(Duration::from_secs(1).as_cycles() == SYSCLOCK_HZ as u64);
+        assert!(Duration::from_millis(1_000) == Duration::from_secs(1));
+        assert!(Duration::from_micros(625).as_cycles() == 21_168);
+        assert!(Duration::from_frames(60, VideoMode::NTSC).as_secs() == 1);
+        assert!(Duration::from_frames(50, VideoMode::PAL).as_secs() == 1);
+        fuzz!(|micros: u32| {
+            let duration = Duration::from_micros(micros as u64);
+            assert!(duration.as_micros() == micros as u64);
+        });
+    }
+
+    #[test_case]
+    fn arithmetic() {
+        let a = Duration::from_millis(3);
+        let b = Duration::from_millis(1);
+        assert!(a - b == b * 2);
+        assert!((a + b) / 4 == b);
+        assert!(b.checked_sub(a).is_none());
+        assert!(b.saturating_sub(a) == Duration::ZERO);
+        let core_duration = core::time::Duration::from(a);
+        assert!(core_duration.as_micros() == 3_000);
+    }
+}
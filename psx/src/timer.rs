@@ -0,0 +1,322 @@
+//! Periodic root counter interrupts
+//!
+//! A [`Timer`] calls a function from its root counter's interrupt handler at a
+//! fixed frequency independent of vblank. The counter runs from the system
+//! clock in target-reset mode. Frequencies needing more than 65536 cycles per
+//! period are reached by only calling the function every few interrupts.
+//!
+//! Root counter 2 is used by [`time`][crate::time] so [`Timer2`] can't be
+//! started while the monotonic clock is running and
+//! [`time::init`][crate::time::init] stops it.
+use crate::hw::cop0::IntSrc;
+use crate::hw::timer::{timer0, timer1, timer2};
+use crate::hw::timer::{CounterMode, CounterTarget, CounterValue, IRQPulse, IRQRepeat, Name, Source};
+use crate::hw::{cop0, irq, Register};
+use crate::sys::kernel;
+use crate::time::SYSCLOCK_HZ;
+
+/// The smallest number of CPU cycles between interrupts.
+const MIN_PERIOD: u32 = 1024;
+/// The highest frequency a [`Timer`] can call its function at in Hz.
+pub const MAX_FREQUENCY: u32 = SYSCLOCK_HZ / MIN_PERIOD;
+
+/// The BIOS event class for root counter 0.
+const RCNT0_EVENT: u32 = 0xF200_0000;
+/// The BIOS event spec for interrupts.
+const INTERRUPT_EVENT: u16 = 0x0002;
+/// The BIOS event mode which calls a function when the event is delivered.
+const CALLBACK_MODE: u16 = 0x1000;
+
+/// Error for periodic timers.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error {
+    /// The frequency is zero or higher than [`MAX_FREQUENCY`].
+    InvalidFrequency,
+    /// The root counter is used by the monotonic clock or the timer wasn't
+    /// started.
+    InUse,
+}
+
+/// The periodic timer for root counter 0.
+pub type Timer0 = Timer<timer0::Current, timer0::Mode, timer0::Target>;
+/// The periodic timer for root counter 1.
+pub type Timer1 = Timer<timer1::Current, timer1::Mode, timer1::Target>;
+/// The periodic timer for root counter 2.
+pub type Timer2 = Timer<timer2::Current, timer2::Mode, timer2::Target>;
+
+/// The user of a root counter's BIOS event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Owner {
+    /// A periodic [`Timer`].
+    Timer,
+    /// The monotonic clock in [`time`][crate::time].
+    Clock,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct State {
+    callback: Option<fn()>,
+    prescaler: u16,
+    ticks: u16,
+    event: Option<(u32, Owner)>,
+}
+
+static mut STATES: [State; 3] = [State {
+    callback: None,
+    prescaler: 1,
+    ticks: 0,
+    event: None,
+}; 3];
+
+/// The counter target and the number of interrupts per call for a frequency.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Rate {
+    target: u16,
+    prescaler: u16,
+}
+
+const fn rate(hz: u32) -> Result<Rate, Error> {
+    if hz == 0 || hz > MAX_FREQUENCY {
+        return Err(Error::InvalidFrequency)
+    }
+    let cycles = (SYSCLOCK_HZ + hz / 2) / hz;
+    let prescaler = cycles.div_ceil(1 << 16);
+    let period = (cycles + prescaler / 2) / prescaler;
+    Ok(Rate {
+        target: (period - 1) as u16,
+        prescaler: prescaler as u16,
+    })
+}
+
+/// Registers `handler` with the BIOS to be called on root counter interrupts.
+///
+/// This does nothing if `owner` already registered a handler for the counter.
+/// Otherwise it replaces the previous owner's handler and callback. This must
+/// be called in a critical section.
+pub(crate) fn open_rcnt_event(name: Name, owner: Owner, handler: extern "C" fn()) {
+    // SAFETY: The caller is in a critical section so the interrupt handler
+    // can't access `STATES`
+    let state = unsafe { &mut STATES[name as usize] };
+    if matches!(state.event, Some((_, o)) if o == owner) {
+        return
+    }
+    close_rcnt_event(name);
+    // SAFETY: The BIOS only calls `handler` from its interrupt handler
+    unsafe {
+        kernel::psx_change_clear_rcnt(name as u32, true);
+        let event = kernel::psx_open_event(
+            RCNT0_EVENT + name as u32,
+            INTERRUPT_EVENT,
+            CALLBACK_MODE,
+            handler as *const u32,
+        );
+        kernel::psx_enable_event(event);
+        state.event = Some((event, owner));
+    }
+}
+
+/// Gets the user of a root counter's BIOS event.
+///
+/// This must be called in a critical section.
+pub(crate) fn rcnt_owner(name: Name) -> Option<Owner> {
+    // SAFETY: The caller is in a critical section so the interrupt handler
+    // can't access `STATES`
+    unsafe { STATES[name as usize].event.map(|(_, owner)| owner) }
+}
+
+/// Closes the BIOS event for a root counter and removes its callback.
+///
+/// This must be called in a critical section.
+fn close_rcnt_event(name: Name) {
+    // SAFETY: The caller is in a critical section so the interrupt handler
+    // can't access `STATES`
+    unsafe {
+        let state = &mut STATES[name as usize];
+        state.callback = None;
+        if let Some((event, _)) = state.event.take() {
+            kernel::psx_disable_event(event);
+            kernel::psx_close_event(event);
+        }
+    }
+}
+
+extern "C" fn on_target<M: CounterMode>() {
+    // SAFETY: This runs in the BIOS interrupt handler so it can't be
+    // interrupted by the `Timer` methods
+    let callback = unsafe {
+        let state = &mut STATES[M::NAME as usize];
+        state.ticks += 1;
+        if state.ticks < state.prescaler {
+            return
+        }
+        state.ticks = 0;
+        state.callback
+    };
+    if let Some(f) = callback {
+        f();
+    }
+}
+
+/// A handle to a root counter which periodically calls a function. These
+/// should be created through the type aliases in the
+/// [`timer`][crate::timer] module.
+pub struct Timer<C: CounterValue, M: CounterMode, T: CounterTarget> {
+    current: C,
+    mode: M,
+    target: T,
+}
+
+impl<C: CounterValue, M: CounterMode, T: CounterTarget> Default for Timer<C, M, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: CounterValue, M: CounterMode, T: CounterTarget> Timer<C, M, T> {
+    /// Creates a handle to a root counter.
+    pub fn new() -> Self {
+        Timer {
+            current: C::skip_load(),
+            mode: M::skip_load(),
+            target: T::skip_load(),
+        }
+    }
+
+    /// Calls `callback` from the root counter's interrupt handler `hz` times
+    /// per second.
+    ///
+    /// This replaces any previous callback and enables the counter's interrupt
+    /// in [`irq::Mask`] and hardware interrupts in cop0. `callback` runs with
+    /// interrupts disabled so it should return quickly. Returns
+    /// [`Error::InUse`] if the monotonic clock is using the counter.
+    pub fn start(&mut self, hz: u32, callback: fn()) -> Result<(), Error> {
+        let rate = rate(hz)?;
+        cop0::Status::new().critical_section(|_| {
+            // SAFETY: We're in a critical section so the interrupt handler
+            // can't access `STATES`
+            unsafe {
+                if matches!(STATES[M::NAME as usize].event, Some((_, Owner::Clock))) {
+                    return Err(Error::InUse)
+                }
+                open_rcnt_event(M::NAME, Owner::Timer, on_target::<M>);
+                let state = &mut STATES[M::NAME as usize];
+                state.callback = Some(callback);
+                state.prescaler = rate.prescaler;
+                state.ticks = 0;
+            }
+            self.target.set_target(rate.target).store();
+            self.mode
+                .assign(0)
+                .set_sync(None)
+                .reset_on_target(true)
+                .irq_on_target(true)
+                .irq_on_overflow(false)
+                .set_irq_repeat(IRQRepeat::Repeat)
+                .set_irq_pulse(IRQPulse::Pulse)
+                .set_source(Source::SysClock)
+                .expect("All root counters support sysclock")
+                .store();
+            irq::Mask::new().enable_irq(M::NAME.irq()).store();
+            Ok(())
+        })?;
+        cop0::Status::new()
+            .unmask_interrupt(IntSrc::Hardware)
+            .enable_interrupts()
+            .store();
+        Ok(())
+    }
+
+    /// Stops calling the callback, disables the counter's interrupt and closes
+    /// its BIOS event.
+    ///
+    /// This does nothing if the monotonic clock is using the counter.
+    pub fn stop(&mut self) {
+        cop0::Status::new().critical_section(|_| {
+            // SAFETY: We're in a critical section so the interrupt handler
+            // can't access `STATES`
+            if !matches!(
+                unsafe { STATES[M::NAME as usize].event },
+                Some((_, Owner::Timer))
+            ) {
+                return
+            }
+            irq::Mask::new().disable_irq(M::NAME.irq()).store();
+            self.mode.load().irq_on_target(false).store();
+            close_rcnt_event(M::NAME);
+        });
+    }
+
+    /// Changes the frequency the callback is called at without replacing it.
+    ///
+    /// This restarts the current period. Returns [`Error::InUse`] if the
+    /// timer wasn't started, e.g. because the monotonic clock is using the
+    /// counter.
+    pub fn set_frequency(&mut self, hz: u32) -> Result<(), Error> {
+        let rate = rate(hz)?;
+        cop0::Status::new().critical_section(|_| {
+            if rcnt_owner(M::NAME) != Some(Owner::Timer) {
+                return Err(Error::InUse)
+            }
+            // SAFETY: We're in a critical section so the interrupt handler
+            // can't access `STATES`
+            unsafe {
+                let state = &mut STATES[M::NAME as usize];
+                state.prescaler = rate.prescaler;
+                state.ticks = 0;
+            }
+            self.target.set_target(rate.target).store();
+            self.current.set(0).store();
+            Ok(())
+        })
+    }
+
+    /// Checks if the timer is calling a callback.
+    pub fn is_running(&self) -> bool {
+        // SAFETY: Reading the callback can't race with the interrupt handler
+        // since it doesn't modify it
+        unsafe { STATES[M::NAME as usize].callback.is_some() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rate, Error, Owner, Rate, Timer2, MAX_FREQUENCY, STATES};
+    use crate::hw::timer::Name;
+    use crate::time::SYSCLOCK_HZ;
+
+    #[test_case]
+    fn rates() {
+        assert!(rate(0) == Err(Error::InvalidFrequency));
+        assert!(rate(MAX_FREQUENCY + 1) == Err(Error::InvalidFrequency));
+        let sequencer = Rate {
+            target: 47_039,
+            prescaler: 3,
+        };
+        assert!(rate(240) == Ok(sequencer));
+        fuzz!(|hz: u32| {
+            let hz = hz % MAX_FREQUENCY + 1;
+            let Rate { target, prescaler } = rate(hz).unwrap();
+            let cycles = (target as u32 + 1) * prescaler as u32;
+            let error = cycles.abs_diff(SYSCLOCK_HZ / hz);
+            assert!(error <= prescaler as u32);
+        });
+    }
+
+    // Replaces root counter 2's event without opening it
+    fn set_event(event: Option<(u32, Owner)>) -> Option<(u32, Owner)> {
+        // SAFETY: The counter's interrupt isn't enabled by the calls using the
+        // fake event so the interrupt handler doesn't access `STATES`
+        unsafe { core::mem::replace(&mut STATES[Name::Timer2 as usize].event, event) }
+    }
+
+    #[test_case]
+    fn in_use() {
+        let mut timer = Timer2::new();
+        let event = set_event(Some((0, Owner::Clock)));
+        assert!(timer.set_frequency(60) == Err(Error::InUse));
+        assert!(timer.start(60, || ()) == Err(Error::InUse));
+        set_event(None);
+        assert!(timer.set_frequency(60) == Err(Error::InUse));
+        set_event(event);
+    }
+}